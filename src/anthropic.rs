@@ -1,15 +1,19 @@
 // src/anthropic.rs
 use serde::{Deserialize, Serialize};
-use reqwest::header::{HeaderMap, HeaderValue};
-use reqwest::Client;
-use std::env;
-use crate::errors::GeneralError;
-use dotenv::dotenv;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crate::errors::{LlmError, parse_retry_after};
 
 use futures::stream::{BoxStream, StreamExt};
 use async_stream::stream;
-use crate::structs::general::{Message, LlmResponse, LlmChunk, MessagePart, ImageSource};
-use crate::config::LlmConfig; // <-- Import config
+use crate::structs::general::{Message, MessagePart, MediaSource, DocumentPart, ImagePart, LlmResponse, LlmChunk, ThinkingBlock, ToolCall, ToolCallAssembler, ToolChoice, ToolDefinition, Usage, FinishReason};
+use crate::config::{check_config, with_warnings, LlmConfig, ReasoningEffort};
+use crate::client::{LlmClient, RequestBuilderExt, ANTHROPIC_FILES_BETA};
+use crate::normalize::normalize_messages;
+use crate::media::prepare_media;
+use crate::llm::LLM;
+use crate::structured::output_schema;
+use crate::options::AnthropicCacheStrategy;
 
 // --- NEW: Added Thinking Config struct ---
 #[derive(Debug, Serialize, Clone)]
@@ -19,11 +23,26 @@ pub struct ThinkingConfig {
 }
 
 #[derive(Debug, Serialize, Clone)]
-pub struct AnthropicImageSource {
-    #[serde(rename = "type")]
-    pub source_type: String, // "base64"
-    pub media_type: String,
-    pub data: String,
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicImageSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+    File { file_id: String },
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicDocumentSource {
+    Base64 { media_type: String, data: String },
+    /// Plain text, sent as-is rather than base64.
+    Text { media_type: String, data: String },
+    Url { url: String },
+    File { file_id: String },
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AnthropicCitations {
+    pub enabled: bool,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -31,6 +50,51 @@ pub struct AnthropicImageSource {
 pub enum AnthropicContentBlock {
     Text { text: String },
     Image { source: AnthropicImageSource },
+    Document {
+        source: AnthropicDocumentSource,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        citations: Option<AnthropicCitations>,
+    },
+    // Replayed ahead of `tool_use` while extended thinking is on
+    Thinking { thinking: String, signature: String },
+    RedactedThinking { data: String },
+    ToolUse { id: String, name: String, input: serde_json::Value },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        is_error: bool,
+    },
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AnthropicTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: serde_json::Value,
+}
+
+impl From<&ToolDefinition> for AnthropicTool {
+    fn from(tool: &ToolDefinition) -> Self {
+        AnthropicTool {
+            name: tool.name.clone(),
+            description: tool.description.clone(),
+            input_schema: tool.parameters.clone(),
+        }
+    }
+}
+
+fn map_tool_choice_to_anthropic(choice: &ToolChoice) -> serde_json::Value {
+    match choice {
+        ToolChoice::Auto => serde_json::json!({ "type": "auto" }),
+        ToolChoice::None => serde_json::json!({ "type": "none" }),
+        ToolChoice::Required => serde_json::json!({ "type": "any" }),
+        ToolChoice::Tool(name) => serde_json::json!({ "type": "tool", "name": name }),
+    }
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -49,7 +113,7 @@ pub struct AnthropicResult {
     pub response: AnthropicResponseBody,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, Default)]
 pub struct AnthropicRequest {
     pub model: String,
     pub max_tokens: usize,
@@ -59,6 +123,43 @@ pub struct AnthropicRequest {
     pub thinking: Option<ThinkingConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<AnthropicMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<AnthropicTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AnthropicMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// Fields from `AnthropicOptions::metadata`.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Error body returned by the Messages API, both for failed requests and `error` stream events.
+#[derive(Debug, Deserialize)]
+pub struct AnthropicErrorResponse {
+    pub error: AnthropicErrorDetail,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnthropicErrorDetail {
+    pub r#type: String,
+    pub message: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -66,11 +167,48 @@ pub struct AnthropicResponse {
     pub id: String,
     pub role: String,
     pub content: Vec<Content>,
+    pub stop_reason: Option<String>,
+    pub usage: Option<AnthropicUsage>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct AnthropicUsage {
+    #[serde(default)]
+    pub input_tokens: u32,
+    #[serde(default)]
+    pub output_tokens: u32,
+    pub cache_creation_input_tokens: Option<u32>,
+    pub cache_read_input_tokens: Option<u32>,
+}
+
+impl From<AnthropicUsage> for Usage {
+    fn from(usage: AnthropicUsage) -> Self {
+        // Anthropic reports cache reads and writes separately from `input_tokens`
+        let cache_read = usage.cache_read_input_tokens.unwrap_or_default();
+        let cache_write = usage.cache_creation_input_tokens.unwrap_or_default();
+        Usage {
+            prompt_tokens: usage.input_tokens + cache_read + cache_write,
+            completion_tokens: usage.output_tokens,
+            reasoning_tokens: None,
+            cached_input_tokens: usage.cache_read_input_tokens,
+        }
+    }
+}
+
+fn map_anthropic_stop_reason(reason: &str) -> FinishReason {
+    match reason {
+        "end_turn" | "stop_sequence" => FinishReason::Stop,
+        "max_tokens" | "model_context_window_exceeded" => FinishReason::Length,
+        "tool_use" => FinishReason::ToolCalls,
+        "refusal" => FinishReason::ContentFilter,
+        other => FinishReason::Other(other.to_string()),
+    }
 }
 
 const MODEL: &str = "claude-haiku-4-5";
 const DEFAULT_MAX_TOKENS: usize = 4096;
 
+use std::collections::HashMap;
 use std::str;
 
 #[derive(Debug, Deserialize)]
@@ -82,6 +220,8 @@ pub enum Content {
     Thinking { thinking: String, signature: String },
     #[serde(rename = "redacted_thinking")]
     RedactedThinking { data: String },
+    #[serde(rename = "tool_use")]
+    ToolUse { id: String, name: String, input: serde_json::Value },
     #[serde(other)]
     Unknown,
 }
@@ -91,182 +231,369 @@ pub enum Content {
 #[allow(dead_code)]
 enum AnthropicEvent {
     #[serde(rename = "message_start")]
-    MessageStart { message: serde_json::Value },
+    MessageStart { message: AnthropicStreamMessage },
     #[serde(rename = "content_block_start")]
-    ContentBlockStart { index: u32 },
+    ContentBlockStart { index: usize, content_block: AnthropicStreamBlock },
     #[serde(rename = "content_block_delta")]
-    ContentBlockDelta { delta: AnthropicDelta },
+    ContentBlockDelta { index: usize, delta: AnthropicDelta },
     #[serde(rename = "content_block_stop")]
-    ContentBlockStop { index: u32 },
+    ContentBlockStop { index: usize },
+    #[serde(rename = "message_delta")]
+    MessageDelta { delta: AnthropicMessageDelta, usage: Option<AnthropicUsage> },
     #[serde(rename = "message_stop")]
-    MessageStop { stop_reason: String },
+    MessageStop,
+    #[serde(rename = "error")]
+    Error { error: AnthropicErrorDetail },
     #[serde(other)]
     Unknown,
 }
 
+#[derive(Debug, Deserialize)]
+struct AnthropicStreamMessage {
+    model: Option<String>,
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicMessageDelta {
+    stop_reason: Option<String>,
+}
+
+/// The block announced by `content_block_start`; only tool-use blocks carry data we need.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum AnthropicStreamBlock {
+    #[serde(rename = "tool_use")]
+    ToolUse { id: String, name: String },
+    #[serde(rename = "thinking")]
+    Thinking,
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
+    #[serde(other)]
+    Other,
+}
+
 #[derive(Debug, Deserialize)]
 struct AnthropicDelta {
     pub text: Option<String>,
     pub thinking: Option<String>,
+    /// Sent in a `signature_delta` event just before a thinking block stops.
+    pub signature: Option<String>,
+    /// Tool input fragment sent in `input_json_delta` events.
+    pub partial_json: Option<String>,
+}
+
+/// Removes `system` (and OpenAI-style `developer`) messages, which the Messages API only accepts
+/// in the top-level `system` field, and joins their text.
+fn split_system_prompt(messages: Vec<Message>) -> (Option<String>, Vec<Message>) {
+    let (system, conversation): (Vec<Message>, Vec<Message>) = messages
+        .into_iter()
+        .partition(|m| m.role == "system" || m.role == "developer");
+
+    let prompt = system.iter().map(|m| m.content.extract_text()).collect::<Vec<_>>().join("\n\n");
+    (if prompt.is_empty() { None } else { Some(prompt) }, conversation)
+}
+
+/// PDFs go as base64, URL or uploaded-file sources; text documents are decoded and sent as plain text.
+fn map_document_to_anthropic(document: DocumentPart) -> Result<AnthropicContentBlock, LlmError> {
+    let source = match document.source {
+        MediaSource::Url { url } => AnthropicDocumentSource::Url { url },
+        MediaSource::File { file_id, .. } => AnthropicDocumentSource::File { file_id },
+        MediaSource::Base64 { media_type, data } if media_type == "application/pdf" => AnthropicDocumentSource::Base64 {
+            data: MediaSource::strip_base64_prefix(&data).to_string(),
+            media_type,
+        },
+        MediaSource::Base64 { media_type, data } if media_type.starts_with("text/") => {
+            let bytes = STANDARD.decode(MediaSource::strip_base64_prefix(&data)).map_err(|e| LlmError::InvalidRequest {
+                message: format!("Document data is not valid base64: {}", e),
+            })?;
+            let text = String::from_utf8(bytes).map_err(|e| LlmError::InvalidRequest {
+                message: format!("Text document is not valid UTF-8: {}", e),
+            })?;
+            AnthropicDocumentSource::Text { media_type: "text/plain".to_string(), data: text }
+        }
+        MediaSource::Base64 { media_type, .. } => {
+            return Err(LlmError::Unsupported {
+                message: format!("Anthropic only accepts PDF and plain-text documents, not {}", media_type),
+            });
+        }
+    };
+
+    Ok(AnthropicContentBlock::Document {
+        source,
+        title: document.title,
+        context: document.context,
+        citations: document.citations.then_some(AnthropicCitations { enabled: true }),
+    })
+}
+
+/// Whether any block points at an uploaded file, which needs the Files API beta header.
+fn references_files(messages: &[AnthropicMessage]) -> bool {
+    messages.iter().flat_map(|msg| &msg.content).any(|block| matches!(block,
+        AnthropicContentBlock::Image { source: AnthropicImageSource::File { .. } }
+        | AnthropicContentBlock::Document { source: AnthropicDocumentSource::File { .. }, .. }
+    ))
+}
+
+/// Builds the POST to `/messages`, with the `anthropic-beta` header the request needs: the Files
+/// API beta when it references files, plus any betas from `AnthropicOptions`.
+fn post_messages(client: &LlmClient, request: &AnthropicRequest, config: Option<&LlmConfig>) -> Result<reqwest::RequestBuilder, LlmError> {
+    let mut betas: Vec<&str> = Vec::new();
+    if references_files(&request.messages) {
+        betas.push(ANTHROPIC_FILES_BETA);
+    }
+    if let Some(options) = config.and_then(|cfg| cfg.anthropic_options.as_ref()) {
+        betas.extend(options.betas.iter().map(String::as_str));
+    }
+
+    let mut post = client.post("/messages")?;
+    if !betas.is_empty() {
+        post = post.header("anthropic-beta", betas.join(","));
+    }
+    match cached_request_body(request, config)? {
+        Some(body) => post.json_with_extras(&body, config),
+        None => post.json_with_extras(request, config),
+    }
 }
 
-fn map_to_anthropic_messages(messages: Vec<Message>) -> Vec<AnthropicMessage> {
+/// Serializes the request with the `cache_control` breakpoint that `AnthropicCacheStrategy`
+/// asks for; `None` without a strategy. Nothing is marked when the named part is missing.
+fn cached_request_body(request: &AnthropicRequest, config: Option<&LlmConfig>) -> Result<Option<serde_json::Value>, LlmError> {
+    let Some(strategy) = config
+        .and_then(|cfg| cfg.anthropic_options.as_ref())
+        .and_then(|options| options.cache_strategy)
+    else {
+        return Ok(None);
+    };
+    let mut body = serde_json::to_value(request)?;
+
+    let breakpoint = match strategy {
+        AnthropicCacheStrategy::Tools => body.pointer_mut("/tools").and_then(last_item),
+        AnthropicCacheStrategy::System => {
+            // Only the block form of `system` takes `cache_control`
+            if let Some(text) = request.system.as_ref() {
+                body["system"] = serde_json::json!([{ "type": "text", "text": text }]);
+            }
+            body.pointer_mut("/system").and_then(last_item)
+        }
+        AnthropicCacheStrategy::Conversation => body
+            .pointer_mut("/messages")
+            .and_then(last_item)
+            .and_then(|message| message.get_mut("content"))
+            .and_then(last_item),
+    };
+    if let Some(serde_json::Value::Object(block)) = breakpoint {
+        block.insert("cache_control".to_string(), serde_json::json!({ "type": "ephemeral" }));
+    }
+    Ok(Some(body))
+}
+
+fn last_item(value: &mut serde_json::Value) -> Option<&mut serde_json::Value> {
+    value.as_array_mut()?.last_mut()
+}
+
+fn map_to_anthropic_messages(messages: Vec<Message>) -> Result<Vec<AnthropicMessage>, LlmError> {
     messages.into_iter().map(|msg| {
-        let role = if msg.role == "model" { "assistant".to_string() } else { msg.role };
+        // Tool results travel back to Anthropic inside a user turn
+        let role = match msg.role.as_str() {
+            "model" => "assistant".to_string(),
+            "tool" => "user".to_string(),
+            _ => msg.role,
+        };
         let content = msg.content.as_parts().into_iter().map(|part| {
-            if part.r#type == "text" {
-                AnthropicContentBlock::Text { text: part.text.unwrap_or_default() }
-            } else if part.r#type == "image_url" {
-                if let Some(ImageSource::Base64 { media_type, data }) = part.image_url {
-                    AnthropicContentBlock::Image {
-                        source: AnthropicImageSource {
-                            source_type: "base64".to_string(),
-                            media_type,
-                            data,
-                        }
+            Ok(match part {
+                MessagePart::Text(text) => AnthropicContentBlock::Text { text },
+                MessagePart::Image(ImagePart { source: MediaSource::Base64 { media_type, data }, .. }) => AnthropicContentBlock::Image {
+                    source: AnthropicImageSource::Base64 {
+                        media_type,
+                        data: MediaSource::strip_base64_prefix(&data).to_string(),
                     }
-                } else {
-                    AnthropicContentBlock::Text { text: "".to_string() }
-                }
-            } else {
-                AnthropicContentBlock::Text { text: "".to_string() }
-            }
-        }).collect();
-        
-        AnthropicMessage { role, content }
+                },
+                MessagePart::Image(ImagePart { source: MediaSource::Url { url }, .. }) => AnthropicContentBlock::Image {
+                    source: AnthropicImageSource::Url { url },
+                },
+                MessagePart::Image(ImagePart { source: MediaSource::File { file_id, .. }, .. }) => AnthropicContentBlock::Image {
+                    source: AnthropicImageSource::File { file_id },
+                },
+                MessagePart::Document(document) => map_document_to_anthropic(document)?,
+                MessagePart::Thinking(ThinkingBlock::Thinking { thinking, signature }) => AnthropicContentBlock::Thinking { thinking, signature },
+                MessagePart::Thinking(ThinkingBlock::RedactedThinking { data }) => AnthropicContentBlock::RedactedThinking { data },
+                MessagePart::ToolCall(call) => AnthropicContentBlock::ToolUse { id: call.id, name: call.name, input: call.arguments },
+                MessagePart::ToolResult(result) => AnthropicContentBlock::ToolResult {
+                    tool_use_id: result.tool_call_id,
+                    content: result.content,
+                    is_error: result.is_error,
+                },
+                other => return Err(other.unsupported("Anthropic")),
+            })
+        }).collect::<Result<Vec<_>, LlmError>>()?;
+
+        Ok(AnthropicMessage { role, content })
     }).collect()
 }
 
+/// Applies `LlmConfig` to a request. Returns the name of the tool that carries structured
+/// output when `json_schema` is set: Anthropic gets the schema as a tool's input schema and the
+/// model is made to call it.
+///
+/// Extended thinking is on when `reasoning_config` has a non-zero budget; a dynamic budget (-1)
+/// becomes the `Medium` one. With thinking, `temperature` and `top_k` are dropped, `top_p` is
+/// raised to at least 0.95, and a forced `tool_choice` becomes `auto`, as the API rejects them.
+/// `check_config` reports each of these changes.
+fn apply_config(request: &mut AnthropicRequest, cfg: &LlmConfig) -> Option<String> {
+    if let Some(max_t) = cfg.max_tokens {
+        request.max_tokens = max_t as usize;
+    }
+
+    request.stop_sequences = cfg.stop.clone();
+    request.top_p = cfg.top_p;
+    let extra_metadata = cfg.anthropic_options.as_ref().and_then(|options| options.metadata.clone());
+    if cfg.user_id.is_some() || extra_metadata.is_some() {
+        request.metadata = Some(AnthropicMetadata { user_id: cfg.user_id.clone(), extra: extra_metadata.unwrap_or_default() });
+    }
+    request.service_tier = cfg.service_tier.clone();
+
+    let budget = match cfg.reasoning_budget() {
+        Some(budget) if budget < 0 => Some(ReasoningEffort::Medium.budget_tokens()),
+        Some(0) => None,
+        budget => budget,
+    };
+    if let Some(budget) = budget {
+        let valid_budget = if budget < 1024 { 1024 } else { budget as usize };
+
+        request.thinking = Some(ThinkingConfig {
+            r#type: "enabled".to_string(),
+            budget_tokens: valid_budget,
+        });
+
+        if request.max_tokens <= valid_budget {
+            request.max_tokens = valid_budget + 1024;
+        }
+
+        // Extended thinking rejects temperature and top_k, and only accepts top_p from 0.95
+        request.top_p = request.top_p.map(|p| p.max(0.95));
+    } else {
+        request.temperature = cfg.temperature.map(|temp| temp as f32);
+        request.top_k = cfg.top_k;
+    }
+
+    request.tools = cfg.tools.as_ref().map(|tools| tools.iter().map(AnthropicTool::from).collect());
+    request.tool_choice = cfg.tool_choice.as_ref().map(|choice| match choice {
+        // Nor can thinking be combined with forced tool use
+        ToolChoice::Required | ToolChoice::Tool(_) if request.thinking.is_some() => map_tool_choice_to_anthropic(&ToolChoice::Auto),
+        _ => map_tool_choice_to_anthropic(choice),
+    });
+
+    let output = cfg.json_schema.as_ref().and_then(output_schema)?;
+    request.tools.get_or_insert_with(Vec::new).push(AnthropicTool {
+        name: output.name.clone(),
+        description: "Respond by calling this tool with your answer as its input.".to_string(),
+        input_schema: output.schema.unwrap_or_else(|| serde_json::json!({ "type": "object" })),
+    });
+    if request.tool_choice.is_none() {
+        // Anthropic rejects forced tool use while extended thinking is on
+        request.tool_choice = match (request.thinking.is_some(), cfg.tools.is_some()) {
+            (true, _) => None,
+            (false, true) => Some(serde_json::json!({ "type": "any" })),
+            (false, false) => Some(serde_json::json!({ "type": "tool", "name": output.name })),
+        };
+    }
+    Some(output.name)
+}
+
 pub async fn call_anthropic(
     messages: Vec<Message>,
     model: Option<&str>,
     config: Option<&LlmConfig>,
-) -> Result<LlmResponse, Box<dyn std::error::Error + Send + Sync>> {
-    dotenv().ok();
-
-    let api_key: String = env::var("ANTHROPIC_API_KEY").map_err(|_| GeneralError {
-        message: "ANTHROPIC API KEY not found in environment variables".to_string(),
-    })?;
-
-    let url: &str = "https://api.anthropic.com/v1/messages";
-
-    let mut headers: HeaderMap = HeaderMap::new();
-
-    headers.insert(
-        "x-api-key",
-        HeaderValue::from_str(&api_key)
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> {
-                Box::new(GeneralError {
-                    message: format!("Invalid Anthropic API key: {}", e.to_string()),
-                })
-            })?,
-    );
-
-    headers.insert(
-        "anthropic-version",
-        HeaderValue::from_str("2023-06-01") 
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> {
-                Box::new(GeneralError {
-                    message: format!("Failed to set Anthropic version header: {}", e.to_string()),
-                })
-            })?,
-    );
-
-    headers.insert(
-        "content-type",
-        HeaderValue::from_str("application/json")
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> {
-                Box::new(GeneralError {
-                    message: format!("Failed to set content-type header: {}", e.to_string()),
-                })
-            })?,
-    );
-
-    let client = Client::builder()
-        .default_headers(headers)
-        .build()
-        .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> {
-            Box::new(GeneralError {
-                message: format!("Failed to create HTTP client: {}", e.to_string()),
-            })
-        })?;
+) -> Result<LlmResponse, LlmError> {
+    let client = LlmClient::from_env(LLM::Anthropic)?;
+    call_anthropic_with_client(&client, messages, model, config).await
+}
 
-    let anthropic_messages = map_to_anthropic_messages(messages);
+pub async fn call_anthropic_with_client(
+    client: &LlmClient,
+    messages: Vec<Message>,
+    model: Option<&str>,
+    config: Option<&LlmConfig>,
+) -> Result<LlmResponse, LlmError> {
+    let (config, warnings) = check_config(config, &LLM::Anthropic, model)?;
+    let config = config.as_ref();
+    let messages = prepare_media(client, normalize_messages(messages, &LLM::Anthropic)?, config).await?;
+    let (system, messages) = split_system_prompt(messages);
+    let anthropic_messages = map_to_anthropic_messages(messages)?;
 
     let mut request = AnthropicRequest {
         model: model.unwrap_or(MODEL).to_string(),
         max_tokens: DEFAULT_MAX_TOKENS,
         messages: anthropic_messages,
         stream: false,
-        thinking: None,
-        temperature: None,
+        system,
+        ..Default::default()
     };
 
-    if let Some(cfg) = config {
-        if let Some(max_t) = cfg.max_tokens {
-            request.max_tokens = max_t as usize;
-        }
-
-        if let Some(budget) = cfg.thinking_budget {
-            let valid_budget = if budget < 1024 { 1024 } else { budget as usize };
-            
-            request.thinking = Some(ThinkingConfig {
-                r#type: "enabled".to_string(),
-                budget_tokens: valid_budget,
-            });
+    let output_tool = config.and_then(|cfg| apply_config(&mut request, cfg));
 
-            if request.max_tokens <= valid_budget {
-                request.max_tokens = valid_budget + 1024; 
-            }
-        } else if let Some(temp) = cfg.temperature {
-            request.temperature = Some(temp as f32);
-        }
-    }
-
-    let res = client
-        .post(url)
-        .json(&request)
+    let res = post_messages(client, &request, config)?
         .send()
         .await
-        .map_err(|e| {
-            Box::new(GeneralError {
-                message: format!("Failed to send request to Anthropic API: {}", e.to_string()),
-            }) as Box<dyn std::error::Error + Send + Sync>
+        .map_err(|e| LlmError::Network {
+            message: format!("Failed to send request to Anthropic API: {}", e),
         })?;
 
     let status = res.status();
-    let rspns_strng = res.text().await.map_err(|e: reqwest::Error| {
-        Box::new(GeneralError {
-            message: format!("Failed to read response from Anthropic API: {}", e.to_string()),
-        }) as Box<dyn std::error::Error + Send + Sync>
+    let retry_after = parse_retry_after(res.headers());
+    let rspns_strng = res.text().await.map_err(|e| LlmError::Network {
+        message: format!("Failed to read response from Anthropic API: {}", e),
     })?;
 
     if !status.is_success() {
-        return Err(Box::new(GeneralError {
-            message: format!("Anthropic API Error (HTTP {}): {}", status, rspns_strng),
-        }));
+        return Err(LlmError::from_anthropic(status.as_u16(), &rspns_strng, retry_after));
     }
 
-    let res: AnthropicResponse = serde_json::from_str(&rspns_strng).map_err(|e| {
-        Box::new(GeneralError {
-            message: format!("Failed to parse response from Anthropic API: {} | Raw: {}", e.to_string(), rspns_strng),
-        }) as Box<dyn std::error::Error + Send + Sync>
+    let res: AnthropicResponse = serde_json::from_str(&rspns_strng).map_err(|e| LlmError::Parse {
+        message: format!("Failed to parse response from Anthropic API: {} | Raw: {}", e, rspns_strng),
     })?;
 
     let mut text_output = String::new();
     let mut reasoning_output = None;
+    let mut tool_calls = Vec::new();
+    let mut thinking_blocks = Vec::new();
+
+    let mut structured_output = None;
 
     for block in res.content {
         match block {
             Content::Text { text } => text_output.push_str(&text),
-            Content::Thinking { thinking, .. } => reasoning_output = Some(thinking),
+            Content::Thinking { thinking, signature } => {
+                reasoning_output = Some(thinking.clone());
+                thinking_blocks.push(ThinkingBlock::Thinking { thinking, signature });
+            }
+            Content::RedactedThinking { data } => thinking_blocks.push(ThinkingBlock::RedactedThinking { data }),
+            Content::ToolUse { input, ref name, .. } if Some(name) == output_tool.as_ref() => {
+                structured_output = Some(input.to_string());
+            }
+            Content::ToolUse { id, name, input } => tool_calls.push(ToolCall { id, name, arguments: input, signature: None }),
             _ => {}
         }
     }
 
+    // The structured-output tool call is the answer, not a call for the caller to run
+    let mut finish_reason = res.stop_reason.as_deref().map(map_anthropic_stop_reason);
+    if let Some(json) = structured_output {
+        text_output = json;
+        if tool_calls.is_empty() {
+            finish_reason = Some(FinishReason::Stop);
+        }
+    }
+
     Ok(LlmResponse {
         text: text_output,
         reasoning: reasoning_output,
+        tool_calls,
+        usage: res.usage.map(Usage::from),
+        finish_reason,
+        warnings,
+        thinking: thinking_blocks,
     })
 }
 
@@ -274,44 +601,40 @@ pub async fn call_anthropic_stream(
     messages: Vec<Message>,
     model: Option<&str>,
     config: Option<&LlmConfig>,
-) -> Result<BoxStream<'static, Result<LlmChunk, Box<dyn std::error::Error + Send + Sync>>>, Box<dyn std::error::Error + Send + Sync>> {
-    dotenv().ok();
-    let api_key = env::var("ANTHROPIC_API_KEY").map_err(|_| GeneralError { message: "ANTHROPIC API KEY not found".into() })?;
-    let url = "https://api.anthropic.com/v1/messages";
-
-    let mut headers = HeaderMap::new();
-    headers.insert("x-api-key", HeaderValue::from_str(&api_key)?);
-    headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
-    headers.insert("content-type", HeaderValue::from_static("application/json"));
-
-    let client = Client::builder().default_headers(headers).build()?;
+) -> Result<BoxStream<'static, Result<LlmChunk, LlmError>>, LlmError> {
+    let client = LlmClient::from_env(LLM::Anthropic)?;
+    call_anthropic_stream_with_client(&client, messages, model, config).await
+}
 
-    let anthropic_messages = map_to_anthropic_messages(messages);
+pub async fn call_anthropic_stream_with_client(
+    client: &LlmClient,
+    messages: Vec<Message>,
+    model: Option<&str>,
+    config: Option<&LlmConfig>,
+) -> Result<BoxStream<'static, Result<LlmChunk, LlmError>>, LlmError> {
+    let (config, warnings) = check_config(config, &LLM::Anthropic, model)?;
+    let config = config.as_ref();
+    let messages = prepare_media(client, normalize_messages(messages, &LLM::Anthropic)?, config).await?;
+    let (system, messages) = split_system_prompt(messages);
+    let anthropic_messages = map_to_anthropic_messages(messages)?;
 
     let mut request = AnthropicRequest {
         model: model.unwrap_or(MODEL).to_string(),
         max_tokens: DEFAULT_MAX_TOKENS,
         messages: anthropic_messages,
         stream: true,
-        thinking: None,
-        temperature: None,
+        system,
+        ..Default::default()
     };
 
-    if let Some(cfg) = config {
-        if let Some(max_t) = cfg.max_tokens { request.max_tokens = max_t as usize; }
-        if let Some(budget) = cfg.thinking_budget {
-            let valid_budget = if budget < 1024 { 1024 } else { budget as usize };
-            request.thinking = Some(ThinkingConfig { r#type: "enabled".to_string(), budget_tokens: valid_budget });
-            if request.max_tokens <= valid_budget { request.max_tokens = valid_budget + 1024; }
-        } else if let Some(temp) = cfg.temperature {
-            request.temperature = Some(temp as f32);
-        }
-    }
+    let output_tool = config.and_then(|cfg| apply_config(&mut request, cfg));
 
-    let res = client.post(url).json(&request).send().await?;
-    if !res.status().is_success() {
+    let res = post_messages(client, &request, config)?.send().await?;
+    let status = res.status();
+    if !status.is_success() {
+        let retry_after = parse_retry_after(res.headers());
         let err_text = res.text().await?;
-        return Err(Box::new(GeneralError { message: format!("Anthropic Stream Error: {}", err_text) }));
+        return Err(LlmError::from_anthropic(status.as_u16(), &err_text, retry_after));
     }
     
     let byte_stream = res.bytes_stream();
@@ -319,6 +642,15 @@ pub async fn call_anthropic_stream(
     let output_stream = stream! {
         let mut buffer = String::new();
         let mut bytes_stream = byte_stream;
+        let mut tool_calls = ToolCallAssembler::default();
+        let mut model = None;
+        let mut usage: Option<AnthropicUsage> = None;
+        let mut finish_reason = None;
+        // Index of the structured-output tool block, streamed as text
+        let mut output_index = None;
+        let mut called_tools = false;
+        // Thinking blocks being streamed, by index, as (thinking, signature)
+        let mut thinking_blocks: HashMap<usize, (String, String)> = HashMap::new();
 
         while let Some(item) = bytes_stream.next().await {
             match item {
@@ -328,20 +660,79 @@ pub async fn call_anthropic_stream(
                         let line = buffer.drain(..newline_idx + 1).collect::<String>().trim().to_string();
                         if line.is_empty() { continue; }
                         if line == "data: [DONE]" {
-                            yield Ok(LlmChunk::Done);
+                            yield Ok(LlmChunk::Done { usage: usage.map(Usage::from), finish_reason, model, warnings: Vec::new() });
                             return; 
                         }
-                        if line.starts_with("data: ") {
-                            let json_str = &line[6..];
-
+                        if let Some(json_str) = line.strip_prefix("data: ") {
                             if let Ok(event) = serde_json::from_str::<AnthropicEvent>(json_str) {
                                 match event {
-                                    AnthropicEvent::ContentBlockDelta { delta } => {
+                                    AnthropicEvent::MessageStart { message } => {
+                                        model = message.model;
+                                        usage = message.usage;
+                                    }
+                                    AnthropicEvent::MessageDelta { delta, usage: delta_usage } => {
+                                        finish_reason = delta.stop_reason.as_deref().map(map_anthropic_stop_reason);
+                                        if output_index.is_some() && !called_tools {
+                                            finish_reason = Some(FinishReason::Stop);
+                                        }
+                                        // `message_delta` usage is cumulative; input counts are only repeated by newer API versions
+                                        if let Some(delta_usage) = delta_usage {
+                                            let current = usage.get_or_insert_with(AnthropicUsage::default);
+                                            current.output_tokens = delta_usage.output_tokens;
+                                            if delta_usage.input_tokens > 0 {
+                                                current.input_tokens = delta_usage.input_tokens;
+                                            }
+                                            if delta_usage.cache_read_input_tokens.is_some() {
+                                                current.cache_read_input_tokens = delta_usage.cache_read_input_tokens;
+                                            }
+                                            if delta_usage.cache_creation_input_tokens.is_some() {
+                                                current.cache_creation_input_tokens = delta_usage.cache_creation_input_tokens;
+                                            }
+                                        }
+                                    }
+                                    AnthropicEvent::ContentBlockStart { index, content_block: AnthropicStreamBlock::ToolUse { name, .. } }
+                                        if Some(&name) == output_tool.as_ref() =>
+                                    {
+                                        output_index = Some(index);
+                                    }
+                                    AnthropicEvent::ContentBlockStart { index, content_block: AnthropicStreamBlock::ToolUse { id, name } } => {
+                                        called_tools = true;
+                                        yield Ok(tool_calls.start(index, id, name, None));
+                                    }
+                                    AnthropicEvent::ContentBlockStart { index, content_block: AnthropicStreamBlock::Thinking } => {
+                                        thinking_blocks.insert(index, (String::new(), String::new()));
+                                    }
+                                    AnthropicEvent::ContentBlockStart { content_block: AnthropicStreamBlock::RedactedThinking { data }, .. } => {
+                                        yield Ok(LlmChunk::Thinking(ThinkingBlock::RedactedThinking { data }));
+                                    }
+                                    AnthropicEvent::ContentBlockDelta { index, delta } => {
                                         if let Some(t) = delta.text { yield Ok(LlmChunk::Text(t)); }
+                                        if let Some((thinking, signature)) = thinking_blocks.get_mut(&index) {
+                                            thinking.push_str(delta.thinking.as_deref().unwrap_or_default());
+                                            signature.push_str(delta.signature.as_deref().unwrap_or_default());
+                                        }
                                         if let Some(th) = delta.thinking { yield Ok(LlmChunk::Reasoning(th)); }
+                                        if let Some(json) = delta.partial_json {
+                                            if output_index == Some(index) {
+                                                yield Ok(LlmChunk::Text(json));
+                                            } else if let Some(chunk) = tool_calls.append(index, &json) {
+                                                yield Ok(chunk);
+                                            }
+                                        }
+                                    }
+                                    AnthropicEvent::ContentBlockStop { index } => {
+                                        if let Some(chunk) = tool_calls.end(index) { yield Ok(chunk); }
+                                        if let Some((thinking, signature)) = thinking_blocks.remove(&index) {
+                                            yield Ok(LlmChunk::Thinking(ThinkingBlock::Thinking { thinking, signature }));
+                                        }
+                                    }
+                                    AnthropicEvent::MessageStop => {
+                                        yield Ok(LlmChunk::Done { usage: usage.map(Usage::from), finish_reason, model, warnings: Vec::new() });
+                                        return;
                                     }
-                                    AnthropicEvent::MessageStop { .. } => {
-                                        yield Ok(LlmChunk::Done);
+                                    AnthropicEvent::Error { error } => {
+                                        // Mid-stream errors carry no HTTP status; Anthropic documents `api_error` as a 500
+                                        yield Err(LlmError::from_anthropic_detail(500, error, None));
                                         return;
                                     }
                                     _ => {}
@@ -352,10 +743,13 @@ pub async fn call_anthropic_stream(
                         }
                     }
                 }
-                Err(e) => yield Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>),
+                Err(e) => yield Err(LlmError::StreamInterrupted { message: e.to_string() }),
             }
         }
+        // The connection closed without `message_stop`: finish pending tool calls and end anyway
+        for chunk in tool_calls.finish() { yield Ok(chunk); }
+        yield Ok(LlmChunk::Done { usage: usage.map(Usage::from), finish_reason, model, warnings: Vec::new() });
     };
 
-    Ok(Box::pin(output_stream))
+    Ok(with_warnings(Box::pin(output_stream), warnings))
 }
\ No newline at end of file
@@ -0,0 +1,279 @@
+// src/client.rs
+use std::env;
+use std::sync::OnceLock;
+use std::time::Duration;
+use dotenv::dotenv;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, RequestBuilder};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::LlmConfig;
+use crate::errors::LlmError;
+use crate::llm::LLM;
+
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
+const GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+const LLAMA_SERVER_BASE_URL: &str = "http://192.168.0.91:8080";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+pub(crate) const ANTHROPIC_FILES_BETA: &str = "files-api-2025-04-14";
+
+/// Connection settings for one provider: credentials, endpoint, extra headers and timeouts.
+///
+/// Every field is optional; unset values fall back to the provider defaults. Use
+/// `ProviderConfig::from_env` to read the same environment variables the free functions use.
+#[derive(Debug, Clone, Default)]
+pub struct ProviderConfig {
+    pub api_key: Option<String>,
+    /// Overrides the provider's API root, e.g. `http://localhost:8080/v1` for a mock server.
+    pub base_url: Option<String>,
+    /// Extra headers sent with every request.
+    pub headers: Vec<(String, String)>,
+    /// Total time allowed for a request, including reading a streamed body.
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+}
+
+impl ProviderConfig {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Reads credentials from the environment (and `.env`):
+    /// `OPEN_AI_KEY`/`OPEN_AI_ORG`, `ANTHROPIC_API_KEY`, `GEMINI_API_KEY` or `LLAMA_SERVER_URL`.
+    /// `LLM::OpenAICompatible` contributes its own base URL, key variable and headers.
+    pub fn from_env(provider: &LLM) -> Self {
+        dotenv().ok();
+        let mut config = ProviderConfig::new();
+        match provider {
+            LLM::OpenAI => {
+                config.api_key = env::var("OPEN_AI_KEY").ok();
+                if let Ok(org) = env::var("OPEN_AI_ORG") {
+                    if !org.is_empty() {
+                        config = config.with_header("OpenAI-Organization", org);
+                    }
+                }
+            }
+            LLM::Anthropic => config.api_key = env::var("ANTHROPIC_API_KEY").ok(),
+            LLM::Gemini => config.api_key = env::var("GEMINI_API_KEY").ok(),
+            LLM::LlamaServer => config.base_url = env::var("LLAMA_SERVER_URL").ok(),
+            LLM::OpenAICompatible { base_url, api_key_env, headers } => {
+                config.base_url = Some(base_url.clone());
+                config.api_key = api_key_env.as_ref().and_then(|name| env::var(name).ok());
+                config.headers.extend(headers.iter().cloned());
+            }
+            // Custom providers manage their own credentials
+            LLM::Custom(_) => {}
+        }
+        config
+    }
+}
+
+/// The connection pool shared by every client without timeouts, including the ones built per call
+/// by `impl Access for LLM` and the free provider functions. Credentials and headers are added
+/// per request, so one pool serves every provider.
+pub(crate) fn shared_http() -> Client {
+    static HTTP: OnceLock<Client> = OnceLock::new();
+    HTTP.get_or_init(Client::new).clone()
+}
+
+/// A provider plus its connection settings and one pooled `reqwest::Client`.
+///
+/// Cloning is cheap and shares the connection pool. Clients without timeouts all share one
+/// process-wide pool; setting a timeout gives the client a pool of its own. `LlmClient` implements `Access`, so it can be
+/// used anywhere an `LLM` is, with credentials that do not come from the environment.
+#[derive(Debug, Clone)]
+pub struct LlmClient {
+    provider: LLM,
+    config: ProviderConfig,
+    /// `ProviderConfig::headers`, added to provider requests only so media downloads never carry them.
+    headers: HeaderMap,
+    http: Client,
+}
+
+impl LlmClient {
+    pub fn new(provider: LLM, config: ProviderConfig) -> Result<Self, LlmError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            headers.insert(header_name(name)?, HeaderValue::from_str(value)?);
+        }
+
+        let http = if config.timeout.is_none() && config.connect_timeout.is_none() {
+            shared_http()
+        } else {
+            let mut builder = Client::builder();
+            if let Some(timeout) = config.timeout {
+                builder = builder.timeout(timeout);
+            }
+            if let Some(connect_timeout) = config.connect_timeout {
+                builder = builder.connect_timeout(connect_timeout);
+            }
+            builder.build()?
+        };
+
+        Ok(LlmClient { provider, config, headers, http })
+    }
+
+    /// Builds a client from the environment, like the free provider functions do. The
+    /// environment is read on every call; the connection pool is shared process-wide.
+    pub fn from_env(provider: LLM) -> Result<Self, LlmError> {
+        let config = ProviderConfig::from_env(&provider);
+        Self::new(provider, config)
+    }
+
+    pub fn provider(&self) -> &LLM {
+        &self.provider
+    }
+
+    pub fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    /// The API root requests are sent to, without a trailing slash.
+    pub fn base_url(&self) -> &str {
+        let default = match &self.provider {
+            LLM::OpenAI => OPENAI_BASE_URL,
+            LLM::Anthropic => ANTHROPIC_BASE_URL,
+            LLM::Gemini => GEMINI_BASE_URL,
+            LLM::LlamaServer => LLAMA_SERVER_BASE_URL,
+            LLM::OpenAICompatible { base_url, .. } => base_url,
+            LLM::Custom(_) => "",
+        };
+        self.config.base_url.as_deref().unwrap_or(default).trim_end_matches('/')
+    }
+
+    pub(crate) fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url(), path)
+    }
+
+    /// Starts a POST to `path` (relative to `base_url`) with the provider's auth headers.
+    pub(crate) fn post(&self, path: &str) -> Result<RequestBuilder, LlmError> {
+        self.authorize(self.http.post(self.url(path)))
+    }
+
+    /// Starts a POST to an absolute URL with the provider's auth headers.
+    pub(crate) fn post_url(&self, url: &str) -> Result<RequestBuilder, LlmError> {
+        self.authorize(self.http.post(url))
+    }
+
+    /// Starts a GET to `path` (relative to `base_url`) with the provider's auth headers.
+    pub(crate) fn get(&self, path: &str) -> Result<RequestBuilder, LlmError> {
+        self.authorize(self.http.get(self.url(path)))
+    }
+
+    /// Starts a DELETE to `path` (relative to `base_url`) with the provider's auth headers.
+    pub(crate) fn delete(&self, path: &str) -> Result<RequestBuilder, LlmError> {
+        self.authorize(self.http.delete(self.url(path)))
+    }
+
+    /// Starts a GET to a third-party URL, such as media to inline, without the provider's
+    /// credentials or headers.
+    pub(crate) fn get_external(&self, url: &str) -> RequestBuilder {
+        self.http.get(url)
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> Result<RequestBuilder, LlmError> {
+        let request = request.headers(self.headers.clone());
+        let request = match &self.provider {
+            LLM::OpenAI => request.bearer_auth(self.require_api_key("OPEN_AI_KEY")?),
+            LLM::Anthropic => request
+                .header("x-api-key", HeaderValue::from_str(self.require_api_key("ANTHROPIC_API_KEY")?)?)
+                .header("anthropic-version", ANTHROPIC_VERSION),
+            LLM::Gemini => request.header("x-goog-api-key", HeaderValue::from_str(self.require_api_key("GEMINI_API_KEY")?)?),
+            LLM::OpenAICompatible { api_key_env: Some(name), .. } => request.bearer_auth(self.require_api_key(name)?),
+            // llama-server only checks a key when started with `--api-key`
+            LLM::LlamaServer | LLM::OpenAICompatible { .. } | LLM::Custom(_) => match &self.config.api_key {
+                Some(key) => request.bearer_auth(key),
+                None => request,
+            },
+        };
+        Ok(request)
+    }
+
+    fn require_api_key(&self, env_name: &str) -> Result<&str, LlmError> {
+        self.config.api_key.as_deref().ok_or_else(|| LlmError::Authentication {
+            message: format!("No API key configured ({} not found in environment variables)", env_name),
+        })
+    }
+}
+
+fn header_name(name: &str) -> Result<HeaderName, LlmError> {
+    HeaderName::from_bytes(name.as_bytes()).map_err(|e| LlmError::InvalidRequest {
+        message: format!("Invalid header name '{}': {}", name, e),
+    })
+}
+
+/// Sets a chat request's JSON body with `LlmConfig::extra_body` merged in and adds
+/// `LlmConfig::extra_headers`.
+pub(crate) trait RequestBuilderExt {
+    fn json_with_extras<T: Serialize>(self, body: &T, config: Option<&LlmConfig>) -> Result<RequestBuilder, LlmError>;
+}
+
+impl RequestBuilderExt for RequestBuilder {
+    fn json_with_extras<T: Serialize>(self, body: &T, config: Option<&LlmConfig>) -> Result<RequestBuilder, LlmError> {
+        let Some(cfg) = config else {
+            return Ok(self.json(body));
+        };
+        let mut request = self;
+        for (name, value) in &cfg.extra_headers {
+            request = request.header(header_name(name)?, HeaderValue::from_str(value)?);
+        }
+        match &cfg.extra_body {
+            Some(extra) => {
+                let mut body = serde_json::to_value(body)?;
+                for (key, value) in extra {
+                    merge_json(&mut body, key, value);
+                }
+                Ok(request.json(&body))
+            }
+            None => Ok(request.json(body)),
+        }
+    }
+}
+
+/// Merges `patch` into `target[key]`: objects key by key, `null` removes the key and anything
+/// else replaces it.
+fn merge_json(target: &mut Value, key: &str, patch: &Value) {
+    let Some(object) = target.as_object_mut() else {
+        return;
+    };
+    match (object.get_mut(key), patch) {
+        (_, Value::Null) => {
+            object.remove(key);
+        }
+        (Some(existing @ Value::Object(_)), Value::Object(fields)) => {
+            for (field, value) in fields {
+                merge_json(existing, field, value);
+            }
+        }
+        _ => {
+            object.insert(key.to_string(), patch.clone());
+        }
+    }
+}
@@ -1,10 +1,19 @@
 // src/config.rs
-use serde_json::Value;
+use std::fmt;
+use futures::stream::{BoxStream, StreamExt};
+use serde_json::{Map, Value};
+use crate::errors::LlmError;
+use crate::llm::LLM;
+use crate::openai::is_reasoning_model;
+use crate::options::{AnthropicOptions, GeminiOptions, LlamaOptions, OpenAiOptions};
+use crate::structs::general::{LlmChunk, ToolDefinition, ToolChoice};
 
 #[derive(Debug, Clone, Default)]
 pub struct LlmConfig {
     pub temperature: Option<f64>,
+    /// Shorthand for `ReasoningConfig::budget_tokens`, used when `reasoning` does not set one.
     pub thinking_budget: Option<i32>,
+    pub reasoning: Option<ReasoningConfig>,
     pub grounding_with_search: Option<bool>,
     pub server_url: Option<String>,
 
@@ -17,6 +26,393 @@ pub struct LlmConfig {
     pub json_schema: Option<Value>,    // For constrained output
     pub top_k: Option<u32>,
     pub top_p: Option<f32>,
+    /// Number of candidates to generate. Only the first is returned. Currently sent to Gemini only.
+    pub candidate_count: Option<u32>,
+    /// Sampling seed for more repeatable output. Not sent to Anthropic.
+    pub seed: Option<i64>,
+    /// Opaque end-user identifier for abuse detection. Sent to OpenAI as `user` and to Anthropic as
+    /// `metadata.user_id`.
+    pub user_id: Option<String>,
+    /// Capacity tier, such as OpenAI's `flex` or Anthropic's `standard_only`. Sent to OpenAI-style
+    /// servers and Anthropic.
+    pub service_tier: Option<String>,
+
+    // --- Tool / Function Calling ---
+
+    pub tools: Option<Vec<ToolDefinition>>,
+    pub tool_choice: Option<ToolChoice>,
+
+    // --- Media ---
+
+    /// Download `MediaSource::Url` parts and send them inline as base64, for endpoints
+    /// that cannot fetch URLs themselves.
+    pub inline_media_urls: Option<bool>,
+
+    /// Downscale and re-encode inline images that exceed the provider's size or pixel
+    /// limits. Needs the `image` feature.
+    pub fit_images: Option<bool>,
+
+    // --- Structured output ---
+
+    /// How many times `send_structured` re-asks the model after a reply that does not parse
+    /// or match the schema. Defaults to 2.
+    pub structured_retries: Option<u32>,
+
+    // --- Provider support ---
+
+    /// Fail with `LlmError::UnsupportedOptions` before sending when an option is set that the
+    /// provider cannot honor. Otherwise such options are dropped and listed in the warnings of
+    /// the response.
+    pub strict: Option<bool>,
+
+    // --- Passthrough ---
+
+    /// Provider parameters without a builder of their own (OpenAI `logit_bias`, Gemini `labels`,
+    /// llama-server `min_p`, ...). Deep-merged into the serialized chat request just before it is
+    /// sent: objects are merged key by key, `null` removes a key, and any other value replaces
+    /// what the crate set.
+    pub extra_body: Option<Map<String, Value>>,
+    /// Headers added to chat requests, such as beta flags.
+    pub extra_headers: Vec<(String, String)>,
+
+    // --- Provider-specific ---
+    // Each block is read only by its own provider, so one config can be reused across providers.
+
+    /// Read by OpenAI and OpenAI-compatible servers.
+    pub openai_options: Option<OpenAiOptions>,
+    pub anthropic_options: Option<AnthropicOptions>,
+    pub gemini_options: Option<GeminiOptions>,
+    pub llama_options: Option<LlamaOptions>,
+}
+
+impl LlmConfig {
+    /// `reasoning` with `thinking_budget` filled in as its budget.
+    pub fn reasoning_config(&self) -> Option<ReasoningConfig> {
+        if self.reasoning.is_none() && self.thinking_budget.is_none() {
+            return None;
+        }
+        let mut reasoning = self.reasoning.clone().unwrap_or_default();
+        reasoning.budget_tokens = reasoning.budget_tokens.or(self.thinking_budget);
+        Some(reasoning)
+    }
+
+    /// The thinking budget to request, if any: `0` means off and `-1` dynamic.
+    pub(crate) fn reasoning_budget(&self) -> Option<i32> {
+        self.reasoning_config().and_then(|reasoning| reasoning.budget())
+    }
+}
+
+/// How hard the model should think before answering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReasoningEffort {
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    /// The thinking budget used for this effort by providers that take a token count.
+    pub fn budget_tokens(&self) -> i32 {
+        match self {
+            ReasoningEffort::Off => 0,
+            ReasoningEffort::Low => 1024,
+            ReasoningEffort::Medium => 8192,
+            ReasoningEffort::High => 24576,
+        }
+    }
+}
+
+/// Reasoning settings, mapped to OpenAI `reasoning_effort`, Anthropic `thinking`, Gemini
+/// `thinkingConfig` and llama-server's `<|think|>` switch.
+///
+/// Values a provider cannot express are reported like unsupported options: as warnings on the
+/// response, or as an error in strict mode.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReasoningConfig {
+    pub effort: Option<ReasoningEffort>,
+    /// Exact thinking budget, overriding the one `effort` implies. `0` turns thinking off and
+    /// `-1` lets the model decide (Gemini only).
+    pub budget_tokens: Option<i32>,
+    /// Return the model's thoughts in `LlmResponse::reasoning`. Defaults to on when thinking.
+    pub include_thoughts: Option<bool>,
+}
+
+impl ReasoningConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_effort(mut self, effort: ReasoningEffort) -> Self {
+        self.effort = Some(effort);
+        self
+    }
+
+    pub fn with_budget_tokens(mut self, budget_tokens: i32) -> Self {
+        self.budget_tokens = Some(budget_tokens);
+        self
+    }
+
+    pub fn with_include_thoughts(mut self, include_thoughts: bool) -> Self {
+        self.include_thoughts = Some(include_thoughts);
+        self
+    }
+
+    /// `budget_tokens`, or the budget `effort` implies.
+    pub fn budget(&self) -> Option<i32> {
+        self.budget_tokens.or_else(|| self.effort.map(|effort| effort.budget_tokens()))
+    }
+}
+
+/// An `LlmConfig` option that only some providers honor. See `LLM::supports`.
+///
+/// Options every built-in provider honors (`max_tokens`, `stop`, tools and the
+/// client-side media and structured-output settings) are not listed. `Temperature`, `TopP`,
+/// `ToolChoice` and `JsonSchema` are honored everywhere but listed for the settings they
+/// conflict with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigOption {
+    ThinkingBudget,
+    GroundingWithSearch,
+    ServerUrl,
+    Stream,
+    CachePrompt,
+    TopK,
+    CandidateCount,
+    Seed,
+    UserId,
+    ServiceTier,
+    ReasoningEffort,
+    ReasoningBudget,
+    /// Only `Some(true)` counts as set: every provider can leave thoughts out.
+    IncludeThoughts,
+    Temperature,
+    TopP,
+    ToolChoice,
+    JsonSchema,
+}
+
+impl ConfigOption {
+    pub const ALL: [ConfigOption; 17] = [
+        ConfigOption::ThinkingBudget,
+        ConfigOption::GroundingWithSearch,
+        ConfigOption::ServerUrl,
+        ConfigOption::Stream,
+        ConfigOption::CachePrompt,
+        ConfigOption::TopK,
+        ConfigOption::CandidateCount,
+        ConfigOption::Seed,
+        ConfigOption::UserId,
+        ConfigOption::ServiceTier,
+        ConfigOption::ReasoningEffort,
+        ConfigOption::ReasoningBudget,
+        ConfigOption::IncludeThoughts,
+        ConfigOption::Temperature,
+        ConfigOption::TopP,
+        ConfigOption::ToolChoice,
+        ConfigOption::JsonSchema,
+    ];
+
+    /// The `LlmConfig` field path.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigOption::ThinkingBudget => "thinking_budget",
+            ConfigOption::GroundingWithSearch => "grounding_with_search",
+            ConfigOption::ServerUrl => "server_url",
+            ConfigOption::Stream => "stream",
+            ConfigOption::CachePrompt => "cache_prompt",
+            ConfigOption::TopK => "top_k",
+            ConfigOption::CandidateCount => "candidate_count",
+            ConfigOption::Seed => "seed",
+            ConfigOption::UserId => "user_id",
+            ConfigOption::ServiceTier => "service_tier",
+            ConfigOption::ReasoningEffort => "reasoning.effort",
+            ConfigOption::ReasoningBudget => "reasoning.budget_tokens",
+            ConfigOption::IncludeThoughts => "reasoning.include_thoughts",
+            ConfigOption::Temperature => "temperature",
+            ConfigOption::TopP => "top_p",
+            ConfigOption::ToolChoice => "tool_choice",
+            ConfigOption::JsonSchema => "json_schema",
+        }
+    }
+
+    fn is_set(&self, cfg: &LlmConfig) -> bool {
+        match self {
+            ConfigOption::ThinkingBudget => cfg.thinking_budget.is_some(),
+            // `false` asks for the default, which every provider can do
+            ConfigOption::GroundingWithSearch => cfg.grounding_with_search == Some(true),
+            ConfigOption::ServerUrl => cfg.server_url.is_some(),
+            ConfigOption::Stream => cfg.stream.is_some(),
+            ConfigOption::CachePrompt => cfg.cache_prompt.is_some(),
+            ConfigOption::TopK => cfg.top_k.is_some(),
+            ConfigOption::CandidateCount => cfg.candidate_count.is_some(),
+            ConfigOption::Seed => cfg.seed.is_some(),
+            ConfigOption::UserId => cfg.user_id.is_some(),
+            ConfigOption::ServiceTier => cfg.service_tier.is_some(),
+            ConfigOption::ReasoningEffort => cfg.reasoning.as_ref().is_some_and(|r| r.effort.is_some()),
+            ConfigOption::ReasoningBudget => cfg.reasoning.as_ref().is_some_and(|r| r.budget_tokens.is_some()),
+            ConfigOption::IncludeThoughts => cfg.reasoning.as_ref().is_some_and(|r| r.include_thoughts == Some(true)),
+            ConfigOption::Temperature => cfg.temperature.is_some(),
+            ConfigOption::TopP => cfg.top_p.is_some(),
+            ConfigOption::ToolChoice => cfg.tool_choice.is_some(),
+            ConfigOption::JsonSchema => cfg.json_schema.is_some(),
+        }
+    }
+
+    fn clear(&self, cfg: &mut LlmConfig) {
+        match self {
+            ConfigOption::ThinkingBudget => cfg.thinking_budget = None,
+            ConfigOption::GroundingWithSearch => cfg.grounding_with_search = None,
+            ConfigOption::ServerUrl => cfg.server_url = None,
+            ConfigOption::Stream => cfg.stream = None,
+            ConfigOption::CachePrompt => cfg.cache_prompt = None,
+            ConfigOption::TopK => cfg.top_k = None,
+            ConfigOption::CandidateCount => cfg.candidate_count = None,
+            ConfigOption::Seed => cfg.seed = None,
+            ConfigOption::UserId => cfg.user_id = None,
+            ConfigOption::ServiceTier => cfg.service_tier = None,
+            ConfigOption::ReasoningEffort => if let Some(r) = &mut cfg.reasoning { r.effort = None },
+            ConfigOption::ReasoningBudget => if let Some(r) = &mut cfg.reasoning { r.budget_tokens = None },
+            ConfigOption::IncludeThoughts => if let Some(r) = &mut cfg.reasoning { r.include_thoughts = None },
+            ConfigOption::Temperature => cfg.temperature = None,
+            ConfigOption::TopP => cfg.top_p = None,
+            ConfigOption::ToolChoice => cfg.tool_choice = None,
+            ConfigOption::JsonSchema => cfg.json_schema = None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Applies the strict/lenient policy before a call: returns the config without the options
+/// `provider` cannot honor, and a warning for each one dropped or sent differently. Every
+/// provider entry point runs this first, with the model it is about to call.
+pub(crate) fn check_config(
+    config: Option<&LlmConfig>,
+    provider: &LLM,
+    model: Option<&str>,
+) -> Result<(Option<LlmConfig>, Vec<String>), LlmError> {
+    let Some(cfg) = config else {
+        return Ok((None, Vec::new()));
+    };
+
+    let mut cfg = cfg.clone();
+    let mut issues: Vec<(ConfigOption, String)> = Vec::new();
+    for option in ConfigOption::ALL {
+        if option.is_set(&cfg) && !provider.supports(option) {
+            option.clear(&mut cfg);
+            issues.push((option, format!("LlmConfig::{} is not supported by {} and was ignored", option, provider.name())));
+        }
+    }
+    issues.extend(conflicts(&cfg, provider));
+    // o-series models only take the default temperature, so any other is left out
+    if matches!(provider, LLM::OpenAI | LLM::OpenAICompatible { .. }) {
+        if let (Some(model), Some(temperature)) = (model.filter(|model| is_reasoning_model(model)), cfg.temperature) {
+            if (temperature - 1.0).abs() >= f64::EPSILON {
+                issues.push((ConfigOption::Temperature, format!("{} only accepts a temperature of 1; {} was dropped", model, temperature)));
+            }
+        }
+    }
+
+    if !issues.is_empty() && cfg.strict.unwrap_or(false) {
+        let (options, reasons) = issues.into_iter().unzip();
+        return Err(LlmError::UnsupportedOptions { provider: provider.name().to_string(), options, reasons });
+    }
+    let warnings = issues.into_iter().map(|(_, reason)| reason).collect();
+    Ok((Some(cfg), warnings))
+}
+
+/// Adds `warnings` to the final `LlmChunk::Done` of a stream.
+pub(crate) fn with_warnings(
+    stream: BoxStream<'static, Result<LlmChunk, LlmError>>,
+    warnings: Vec<String>,
+) -> BoxStream<'static, Result<LlmChunk, LlmError>> {
+    if warnings.is_empty() {
+        return stream;
+    }
+    stream.map(move |chunk| match chunk {
+        Ok(LlmChunk::Done { usage, finish_reason, model, warnings: mut provider_warnings }) => {
+            provider_warnings.extend(warnings.iter().cloned());
+            Ok(LlmChunk::Done { usage, finish_reason, model, warnings: provider_warnings })
+        }
+        other => other,
+    }).boxed()
+}
+
+/// Values the provider takes but cannot express exactly, with what is sent instead.
+fn conflicts(cfg: &LlmConfig, provider: &LLM) -> Vec<(ConfigOption, String)> {
+    let Some(reasoning) = cfg.reasoning_config() else {
+        return Vec::new();
+    };
+    let thinking = reasoning.budget().is_some_and(|budget| budget != 0);
+    // The option the thinking budget came from
+    let budget_option = match (&cfg.reasoning, cfg.thinking_budget) {
+        (Some(ReasoningConfig { budget_tokens: Some(_), .. }), _) => ConfigOption::ReasoningBudget,
+        (_, Some(_)) => ConfigOption::ThinkingBudget,
+        _ => ConfigOption::ReasoningEffort,
+    };
+    let name = provider.name();
+    let mut issues = Vec::new();
+    match provider {
+        LLM::OpenAI | LLM::OpenAICompatible { .. } => {
+            if reasoning.effort == Some(ReasoningEffort::Off) {
+                issues.push((ConfigOption::ReasoningEffort, format!("{} cannot turn reasoning off; reasoning.effort Off was not sent", name)));
+            }
+        }
+        LLM::Anthropic => {
+            match reasoning.budget() {
+                Some(budget) if budget < 0 => issues.push((budget_option, format!(
+                    "{} has no dynamic thinking budget; the Medium budget of {} tokens was sent instead",
+                    name,
+                    ReasoningEffort::Medium.budget_tokens()
+                ))),
+                Some(budget @ 1..=1023) => issues.push((
+                    budget_option,
+                    format!("{} needs a thinking budget of at least 1024 tokens; {} was raised to 1024", name, budget),
+                )),
+                _ => {}
+            }
+            if reasoning.include_thoughts == Some(false) && thinking {
+                issues.push((ConfigOption::IncludeThoughts, format!("{} always returns thinking; reasoning.include_thoughts false was ignored", name)));
+            }
+            // Extended thinking rejects these, so `apply_config` adjusts them
+            if thinking {
+                if cfg.temperature.is_some() {
+                    issues.push((ConfigOption::Temperature, format!("{} extended thinking does not accept temperature; it was dropped", name)));
+                }
+                if cfg.top_k.is_some() {
+                    issues.push((ConfigOption::TopK, format!("{} extended thinking does not accept top_k; it was dropped", name)));
+                }
+                if let Some(top_p) = cfg.top_p.filter(|top_p| *top_p < 0.95) {
+                    issues.push((ConfigOption::TopP, format!("{} extended thinking needs top_p of at least 0.95; {} was raised to 0.95", name, top_p)));
+                }
+                if matches!(cfg.tool_choice, Some(ToolChoice::Required | ToolChoice::Tool(_))) {
+                    issues.push((ConfigOption::ToolChoice, format!("{} extended thinking cannot force tool use; tool_choice was sent as auto", name)));
+                }
+                if cfg.json_schema.is_some() {
+                    issues.push((ConfigOption::JsonSchema, format!(
+                        "{} extended thinking cannot force the json_schema tool; the model may answer in plain text", name
+                    )));
+                }
+            }
+        }
+        LLM::LlamaServer => {
+            let levelled = matches!(reasoning.effort, Some(effort) if effort != ReasoningEffort::Off)
+                || reasoning.budget_tokens.is_some_and(|budget| budget != 0);
+            if levelled {
+                issues.push((budget_option, format!("{} can only switch thinking on or off; it was switched on", name)));
+            }
+            if reasoning.include_thoughts == Some(false) && thinking {
+                issues.push((ConfigOption::IncludeThoughts, format!("{} always returns thinking; reasoning.include_thoughts false was ignored", name)));
+            }
+        }
+        LLM::Gemini | LLM::Custom(_) => {}
+    }
+    issues
 }
 
 impl LlmConfig {
@@ -34,6 +430,11 @@ impl LlmConfig {
         self
     }
 
+    pub fn with_reasoning(mut self, reasoning: ReasoningConfig) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
     pub fn with_grounding_with_search(mut self, grounding_with_search: bool) -> Self {
         self.grounding_with_search = Some(grounding_with_search);
         self
@@ -80,4 +481,90 @@ impl LlmConfig {
         self.top_p = Some(top_p);
         self
     }
+
+    pub fn with_candidate_count(mut self, candidate_count: u32) -> Self {
+        self.candidate_count = Some(candidate_count);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_user_id(mut self, user_id: impl Into<String>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    pub fn with_service_tier(mut self, service_tier: impl Into<String>) -> Self {
+        self.service_tier = Some(service_tier.into());
+        self
+    }
+
+    pub fn with_tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    pub fn with_inline_media_urls(mut self, inline_media_urls: bool) -> Self {
+        self.inline_media_urls = Some(inline_media_urls);
+        self
+    }
+
+    pub fn with_fit_images(mut self, fit_images: bool) -> Self {
+        self.fit_images = Some(fit_images);
+        self
+    }
+
+    pub fn with_structured_retries(mut self, structured_retries: u32) -> Self {
+        self.structured_retries = Some(structured_retries);
+        self
+    }
+
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = Some(strict);
+        self
+    }
+
+    pub fn with_extra_body(mut self, extra_body: Map<String, Value>) -> Self {
+        self.extra_body = Some(extra_body);
+        self
+    }
+
+    /// Sets one top-level `extra_body` key.
+    pub fn with_extra_param(mut self, key: impl Into<String>, value: Value) -> Self {
+        self.extra_body.get_or_insert_with(Map::new).insert(key.into(), value);
+        self
+    }
+
+    pub fn with_extra_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_openai_options(mut self, options: OpenAiOptions) -> Self {
+        self.openai_options = Some(options);
+        self
+    }
+
+    pub fn with_anthropic_options(mut self, options: AnthropicOptions) -> Self {
+        self.anthropic_options = Some(options);
+        self
+    }
+
+    pub fn with_gemini_options(mut self, options: GeminiOptions) -> Self {
+        self.gemini_options = Some(options);
+        self
+    }
+
+    pub fn with_llama_options(mut self, options: LlamaOptions) -> Self {
+        self.llama_options = Some(options);
+        self
+    }
 }
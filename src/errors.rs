@@ -3,19 +3,225 @@ use std::fmt;
 use std::time::Duration;
 use tokio::time::sleep;
 use std::future::Future;
+use reqwest::header::HeaderMap;
 
-#[derive(Debug)]
-pub struct GeneralError {
-    pub message: String,
+use crate::config::ConfigOption;
+use crate::models::openai::ErrorResponse;
+use crate::gemini::types::GeminiErrorResponse;
+use crate::anthropic::{AnthropicErrorResponse, AnthropicErrorDetail};
+
+/// The error type returned by every provider call in this crate.
+///
+/// Provider error bodies (OpenAI / llama-server `error` objects, Anthropic `error` events and
+/// Google RPC errors) are classified into these variants so callers can branch on the kind of
+/// failure instead of matching on message strings.
+#[derive(Debug, Clone)]
+pub enum LlmError {
+    /// The API key is missing, malformed or was rejected by the provider.
+    Authentication { message: String },
+    /// The account is out of credit or over its billing quota; retrying will not help.
+    QuotaExceeded { message: String },
+    /// The provider is throttling requests. `retry_after` is filled from the `retry-after`
+    /// header or the Gemini `RetryInfo` detail when the provider supplies one.
+    RateLimited { retry_after: Option<Duration>, message: String },
+    /// The prompt (plus requested output) does not fit in the model's context window.
+    ContextLengthExceeded { message: String },
+    /// The prompt or the generated output was blocked by the provider's safety filters.
+    ContentFiltered { message: String },
+    /// The provider rejected the request as malformed (bad parameters, unknown model, ...).
+    InvalidRequest { message: String },
+    /// The provider is temporarily overloaded or unavailable.
+    ServerOverloaded { message: String },
+    /// The operation or input is not supported by the selected provider.
+    Unsupported { message: String },
+    /// `LlmConfig::strict` is set and the config has options the provider cannot honor, or
+    /// values it cannot express. `reasons` holds the warning lenient mode would give for each
+    /// entry of `options`.
+    UnsupportedOptions { provider: String, options: Vec<ConfigOption>, reasons: Vec<String> },
+    /// Any other non-success HTTP response.
+    Http { status: u16, body: String },
+    /// The response body could not be parsed into the expected shape.
+    Parse { message: String },
+    /// The request could not be sent or the response could not be read.
+    Network { message: String },
+    /// A streaming response failed after it had started.
+    StreamInterrupted { message: String },
 }
 
-impl fmt::Display for GeneralError {
+impl fmt::Display for LlmError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.message)
+        match self {
+            LlmError::Authentication { message } => write!(f, "Authentication error: {}", message),
+            LlmError::RateLimited { retry_after: Some(delay), message } => {
+                write!(f, "Rate limited (retry after {:?}): {}", delay, message)
+            }
+            LlmError::RateLimited { retry_after: None, message } => write!(f, "Rate limited: {}", message),
+            LlmError::QuotaExceeded { message } => write!(f, "Quota exceeded: {}", message),
+            LlmError::ContextLengthExceeded { message } => write!(f, "Context length exceeded: {}", message),
+            LlmError::ContentFiltered { message } => write!(f, "Content filtered: {}", message),
+            LlmError::InvalidRequest { message } => write!(f, "Invalid request: {}", message),
+            LlmError::ServerOverloaded { message } => write!(f, "Server overloaded: {}", message),
+            LlmError::Unsupported { message } => write!(f, "Unsupported: {}", message),
+            LlmError::UnsupportedOptions { provider, reasons, .. } => {
+                write!(f, "Unsupported by {}: {}", provider, reasons.join("; "))
+            }
+            LlmError::Http { status, body } => write!(f, "HTTP {}: {}", status, body),
+            LlmError::Parse { message } => write!(f, "Parse error: {}", message),
+            LlmError::Network { message } => write!(f, "Network error: {}", message),
+            LlmError::StreamInterrupted { message } => write!(f, "Stream interrupted: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for LlmError {}
+
+impl From<reqwest::Error> for LlmError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            LlmError::Parse { message: e.to_string() }
+        } else {
+            LlmError::Network { message: e.to_string() }
+        }
+    }
+}
+
+impl From<serde_json::Error> for LlmError {
+    fn from(e: serde_json::Error) -> Self {
+        LlmError::Parse { message: e.to_string() }
+    }
+}
+
+impl From<reqwest::header::InvalidHeaderValue> for LlmError {
+    fn from(e: reqwest::header::InvalidHeaderValue) -> Self {
+        LlmError::InvalidRequest { message: format!("Invalid header value: {}", e) }
+    }
+}
+
+impl LlmError {
+    /// Returns true when repeating the same request later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LlmError::RateLimited { .. }
+            | LlmError::ServerOverloaded { .. }
+            | LlmError::Network { .. }
+            | LlmError::StreamInterrupted { .. } => true,
+            LlmError::Http { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// The delay suggested by the provider before retrying, if any.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            LlmError::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Classifies an error response from OpenAI or an OpenAI-compatible server (llama-server).
+    pub fn from_openai(status: u16, body: &str, retry_after: Option<Duration>) -> Self {
+        let details = match serde_json::from_str::<ErrorResponse>(body) {
+            Ok(err) => err.error,
+            Err(_) => return classify_status(status, body.to_string(), body, retry_after),
+        };
+
+        let code = details.code.as_ref().and_then(|c| c.as_str()).unwrap_or_default();
+        let kind = details.r#type.as_deref().unwrap_or_default();
+        let message = details.message;
+
+        match (code, kind) {
+            ("context_length_exceeded", _) | (_, "exceed_context_size_error") => {
+                LlmError::ContextLengthExceeded { message }
+            }
+            ("content_filter", _) | ("content_policy_violation", _) => LlmError::ContentFiltered { message },
+            ("invalid_api_key", _) | (_, "authentication_error") => LlmError::Authentication { message },
+            ("rate_limit_exceeded", _) => LlmError::RateLimited { retry_after, message },
+            ("insufficient_quota", _) | (_, "insufficient_quota") => LlmError::QuotaExceeded { message },
+            _ => classify_status(status, message, body, retry_after),
+        }
+    }
+
+    /// Classifies an error response from the Anthropic Messages API.
+    pub fn from_anthropic(status: u16, body: &str, retry_after: Option<Duration>) -> Self {
+        match serde_json::from_str::<AnthropicErrorResponse>(body) {
+            Ok(err) => Self::from_anthropic_detail(status, err.error, retry_after),
+            Err(_) => classify_status(status, body.to_string(), body, retry_after),
+        }
+    }
+
+    /// Classifies an already-parsed Anthropic error object (also sent as an `error` stream event).
+    pub fn from_anthropic_detail(status: u16, details: AnthropicErrorDetail, retry_after: Option<Duration>) -> Self {
+        let message = details.message;
+        match details.r#type.as_str() {
+            "authentication_error" | "permission_error" => LlmError::Authentication { message },
+            "rate_limit_error" => LlmError::RateLimited { retry_after, message },
+            "overloaded_error" => LlmError::ServerOverloaded { message },
+            "request_too_large" => LlmError::ContextLengthExceeded { message },
+            "invalid_request_error" if message.contains("prompt is too long") => {
+                LlmError::ContextLengthExceeded { message }
+            }
+            "invalid_request_error" | "not_found_error" => LlmError::InvalidRequest { message },
+            _ => {
+                let body = format!("{}: {}", details.r#type, message);
+                classify_status(status, message, &body, retry_after)
+            }
+        }
+    }
+
+    /// Classifies a Google RPC error response from the Gemini API.
+    pub fn from_gemini(status: u16, body: &str) -> Self {
+        let details = match serde_json::from_str::<GeminiErrorResponse>(body) {
+            Ok(err) => err.error,
+            Err(_) => return classify_status(status, body.to_string(), body, None),
+        };
+
+        let retry_after = details.details.iter().flatten()
+            .filter_map(|d| d.retry_delay.as_deref())
+            .find_map(parse_delay_secs);
+        let api_key_invalid = details.details.iter().flatten()
+            .any(|d| d.reason.as_deref() == Some("API_KEY_INVALID"));
+
+        let message = details.message;
+        match details.status.as_str() {
+            _ if api_key_invalid => LlmError::Authentication { message },
+            "UNAUTHENTICATED" | "PERMISSION_DENIED" => LlmError::Authentication { message },
+            "RESOURCE_EXHAUSTED" => LlmError::RateLimited { retry_after, message },
+            "UNAVAILABLE" => LlmError::ServerOverloaded { message },
+            "INVALID_ARGUMENT" if message.contains("exceeds the maximum number of tokens") => {
+                LlmError::ContextLengthExceeded { message }
+            }
+            "INVALID_ARGUMENT" | "FAILED_PRECONDITION" | "NOT_FOUND" => LlmError::InvalidRequest { message },
+            _ => classify_status(status, message, body, retry_after),
+        }
+    }
+}
+
+/// Fallback classification by HTTP status when the body carries no recognizable error code.
+fn classify_status(status: u16, message: String, body: &str, retry_after: Option<Duration>) -> LlmError {
+    match status {
+        401 | 403 => LlmError::Authentication { message },
+        429 => LlmError::RateLimited { retry_after, message },
+        413 => LlmError::ContextLengthExceeded { message },
+        400 | 404 | 422 => LlmError::InvalidRequest { message },
+        503 | 529 => LlmError::ServerOverloaded { message },
+        _ => LlmError::Http { status, body: body.to_string() },
     }
 }
 
-impl std::error::Error for GeneralError {}
+/// Reads a `retry-after` header expressed in seconds.
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get("retry-after")
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_delay_secs)
+}
+
+/// Parses delays like "16", "16s" or "16.747818564s" into a `Duration`.
+fn parse_delay_secs(value: &str) -> Option<Duration> {
+    value.trim().trim_end_matches('s').parse::<f64>().ok()
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+}
 
 /// Represents the decision made by a `RetryPolicy` after encountering an error.
 #[derive(Debug, Clone)]
@@ -50,9 +256,26 @@ impl<E: std::fmt::Display> RetryPolicy<E> for ExponentialBackoffPolicy {
     }
 }
 
+/// Exponential backoff for provider calls: gives up at once on errors that
+/// `LlmError::is_retryable` rejects and waits for the provider's `retry_after` when it sends one.
+pub struct LlmRetryPolicy {
+    pub max_retries: usize,
+    pub initial_delay: Duration,
+}
+
+impl RetryPolicy<LlmError> for LlmRetryPolicy {
+    fn should_retry(&self, error: &LlmError, attempt: usize) -> RetryDecision {
+        if attempt >= self.max_retries || !error.is_retryable() {
+            return RetryDecision::Abort;
+        }
+        let backoff = self.initial_delay.saturating_mul(2u32.saturating_pow((attempt - 1) as u32));
+        RetryDecision::RetryAfter(error.retry_after().unwrap_or(backoff))
+    }
+}
+
 /// A generic retry wrapper for async operations with exponential backoff.
 pub async fn with_retry<F, Fut, T, E>(
-    mut action: F,
+    action: F,
     max_retries: usize,
     initial_delay: Duration,
 ) -> Result<T, E>
@@ -96,4 +319,4 @@ where
             }
         }
     }
-}
\ No newline at end of file
+}
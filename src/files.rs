@@ -0,0 +1,243 @@
+// src/files.rs
+use reqwest::multipart::{Form, Part};
+use reqwest::{RequestBuilder, Response};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::client::{LlmClient, ANTHROPIC_FILES_BETA};
+use crate::errors::{parse_retry_after, LlmError};
+use crate::gemini::{
+    delete_gemini_file_with_client, get_gemini_file_with_client, list_gemini_files_with_client,
+    upload_gemini_file_with_client,
+};
+use crate::llm::LLM;
+use crate::media::{mime_type_from_extension, part_for_source, sniff_mime_type};
+use crate::models::gemini::GeminiFile;
+use crate::structs::general::{MediaSource, MessagePart};
+
+/// A file stored with a provider's Files API, usable in messages through `to_part`.
+///
+/// Uploaded files only work with the provider (and API key) that stored them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileHandle {
+    /// Passed to `get_file` and `delete_file`: `files/abc123` for Gemini, `file_...` for
+    /// Anthropic and `file-...` for OpenAI.
+    pub id: String,
+    /// The URI Gemini messages reference the file by.
+    pub uri: Option<String>,
+    pub filename: Option<String>,
+    pub media_type: Option<String>,
+    pub size_bytes: Option<u64>,
+    /// Gemini's processing state: `PROCESSING`, `ACTIVE` or `FAILED`. Videos can only be
+    /// used once they are `ACTIVE`; poll `get_file` until then.
+    pub state: Option<String>,
+}
+
+impl FileHandle {
+    /// The source that references this file in a message part.
+    pub fn source(&self) -> MediaSource {
+        MediaSource::File {
+            file_id: self.uri.clone().unwrap_or_else(|| self.id.clone()),
+            media_type: self.media_type.clone(),
+        }
+    }
+
+    /// An image, audio, video or document part for the file, picked by its MIME type.
+    pub fn to_part(&self) -> MessagePart {
+        part_for_source(self.media_type.as_deref().unwrap_or_default(), self.source())
+    }
+}
+
+impl From<FileHandle> for MessagePart {
+    fn from(file: FileHandle) -> Self {
+        file.to_part()
+    }
+}
+
+impl From<GeminiFile> for FileHandle {
+    fn from(file: GeminiFile) -> Self {
+        FileHandle {
+            id: file.name,
+            uri: file.uri,
+            filename: file.display_name,
+            media_type: file.mime_type,
+            size_bytes: file.size_bytes.and_then(|size| size.parse().ok()),
+            state: file.state,
+        }
+    }
+}
+
+/// File object shared by the Anthropic and OpenAI Files APIs.
+#[derive(Debug, Deserialize)]
+struct ApiFile {
+    id: String,
+    filename: Option<String>,
+    /// Anthropic only; OpenAI's type is guessed from the file name.
+    mime_type: Option<String>,
+    /// Anthropic's name for the size.
+    size_bytes: Option<u64>,
+    /// OpenAI's name for the size.
+    bytes: Option<u64>,
+}
+
+impl From<ApiFile> for FileHandle {
+    fn from(file: ApiFile) -> Self {
+        let media_type = file.mime_type.or_else(|| {
+            file.filename.as_deref().and_then(mime_type_from_extension).map(str::to_string)
+        });
+        FileHandle {
+            id: file.id,
+            uri: None,
+            filename: file.filename,
+            media_type,
+            size_bytes: file.size_bytes.or(file.bytes),
+            state: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiFileList {
+    data: Vec<ApiFile>,
+    #[serde(default)]
+    has_more: bool,
+    last_id: Option<String>,
+}
+
+/// Files API operations. Gemini, Anthropic, OpenAI and OpenAI-compatible providers are
+/// supported; other providers return `LlmError::Unsupported`.
+impl LlmClient {
+    /// Uploads `data` under `filename`. The MIME type is detected from the magic bytes or the
+    /// file name when `media_type` is `None`.
+    pub async fn upload_file(&self, data: Vec<u8>, filename: &str, media_type: Option<&str>) -> Result<FileHandle, LlmError> {
+        let media_type = media_type
+            .or_else(|| sniff_mime_type(&data))
+            .or_else(|| mime_type_from_extension(filename))
+            .ok_or_else(|| LlmError::InvalidRequest {
+                message: format!("Could not detect the MIME type of {}", filename),
+            })?
+            .to_string();
+
+        match self.provider() {
+            LLM::Gemini => Ok(upload_gemini_file_with_client(self, data, &media_type, Some(filename)).await?.into()),
+            LLM::Anthropic | LLM::OpenAI | LLM::OpenAICompatible { .. } => {
+                let part = Part::bytes(data).file_name(filename.to_string()).mime_str(&media_type)?;
+                let mut form = Form::new().part("file", part);
+                if !matches!(self.provider(), LLM::Anthropic) {
+                    form = form.text("purpose", "user_data");
+                }
+                let res = send(self.files_request(self.post("/files")?).multipart(form)).await?;
+                let mut file: FileHandle = parse_file_body::<ApiFile>(self, res).await?.into();
+                file.media_type.get_or_insert(media_type);
+                Ok(file)
+            }
+            _ => Err(self.files_unsupported()),
+        }
+    }
+
+    /// Reads a file from disk and uploads it under its file name.
+    pub async fn upload_file_from_path(&self, path: impl AsRef<Path>) -> Result<FileHandle, LlmError> {
+        let path = path.as_ref();
+        let data = tokio::fs::read(path).await.map_err(|e| LlmError::InvalidRequest {
+            message: format!("Failed to read {}: {}", path.display(), e),
+        })?;
+        let filename = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        self.upload_file(data, &filename, None).await
+    }
+
+    /// Lists every stored file, following pagination.
+    pub async fn list_files(&self) -> Result<Vec<FileHandle>, LlmError> {
+        match self.provider() {
+            LLM::Gemini => Ok(list_gemini_files_with_client(self).await?.into_iter().map(FileHandle::from).collect()),
+            LLM::Anthropic | LLM::OpenAI | LLM::OpenAICompatible { .. } => {
+                let cursor = if matches!(self.provider(), LLM::Anthropic) { "after_id" } else { "after" };
+                let mut files = Vec::new();
+                let mut after: Option<String> = None;
+                loop {
+                    let mut request = self.files_request(self.get("/files")?).query(&[("limit", "100")]);
+                    if let Some(after) = &after {
+                        request = request.query(&[(cursor, after)]);
+                    }
+                    let page: ApiFileList = parse_file_body(self, send(request).await?).await?;
+                    let last_id = page.last_id.or_else(|| page.data.last().map(|f| f.id.clone()));
+                    files.extend(page.data.into_iter().map(FileHandle::from));
+
+                    match (page.has_more, last_id) {
+                        (true, Some(id)) => after = Some(id),
+                        _ => return Ok(files),
+                    }
+                }
+            }
+            _ => Err(self.files_unsupported()),
+        }
+    }
+
+    pub async fn get_file(&self, id: &str) -> Result<FileHandle, LlmError> {
+        match self.provider() {
+            LLM::Gemini => Ok(get_gemini_file_with_client(self, id).await?.into()),
+            LLM::Anthropic | LLM::OpenAI | LLM::OpenAICompatible { .. } => {
+                let res = send(self.files_request(self.get(&format!("/files/{}", id))?)).await?;
+                Ok(parse_file_body::<ApiFile>(self, res).await?.into())
+            }
+            _ => Err(self.files_unsupported()),
+        }
+    }
+
+    pub async fn delete_file(&self, id: &str) -> Result<(), LlmError> {
+        match self.provider() {
+            LLM::Gemini => delete_gemini_file_with_client(self, id).await,
+            LLM::Anthropic | LLM::OpenAI | LLM::OpenAICompatible { .. } => {
+                let res = send(self.files_request(self.delete(&format!("/files/{}", id))?)).await?;
+                check_status(self, res).await?;
+                Ok(())
+            }
+            _ => Err(self.files_unsupported()),
+        }
+    }
+
+    /// Anthropic's Files API is in beta and needs its header on every request.
+    fn files_request(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.provider() {
+            LLM::Anthropic => request.header("anthropic-beta", ANTHROPIC_FILES_BETA),
+            _ => request,
+        }
+    }
+
+    fn files_unsupported(&self) -> LlmError {
+        let name = match self.provider() {
+            LLM::Custom(provider) => provider.name().to_string(),
+            _ => "llama-server".to_string(),
+        };
+        LlmError::Unsupported {
+            message: format!("{} does not support the Files API", name),
+        }
+    }
+}
+
+async fn send(request: RequestBuilder) -> Result<Response, LlmError> {
+    request.send().await.map_err(|e| LlmError::Network {
+        message: format!("Failed to send request to the Files API: {}", e),
+    })
+}
+
+async fn check_status(client: &LlmClient, res: Response) -> Result<String, LlmError> {
+    let status = res.status();
+    let retry_after = parse_retry_after(res.headers());
+    let body = res.text().await.map_err(|e| LlmError::Network {
+        message: format!("Failed to read response from the Files API: {}", e),
+    })?;
+    if status.is_success() {
+        return Ok(body);
+    }
+    Err(match client.provider() {
+        LLM::Anthropic => LlmError::from_anthropic(status.as_u16(), &body, retry_after),
+        _ => LlmError::from_openai(status.as_u16(), &body, retry_after),
+    })
+}
+
+async fn parse_file_body<T: serde::de::DeserializeOwned>(client: &LlmClient, res: Response) -> Result<T, LlmError> {
+    let body = check_status(client, res).await?;
+    serde_json::from_str(&body).map_err(|e| LlmError::Parse {
+        message: format!("Failed to parse response from the Files API: {} | Raw: {}", e, body),
+    })
+}
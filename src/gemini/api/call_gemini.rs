@@ -2,16 +2,22 @@
 use std::env;
 use std::time::Duration;
 use dotenv::dotenv;
-use serde_json::json;
+use serde_json::{json, Value};
 
 use futures::stream::{BoxStream, StreamExt};
 use async_stream::stream;
-use crate::errors::{GeneralError, RetryPolicy, RetryDecision, with_policy_retry};
-use crate::structs::general::{Message, Content, Part, LlmChunk, MessagePart, ImageSource, GeminiInlineData};
-use crate::gemini::types::{GeminiRequest, GenerationConfig, Tool, GeminiResponse, GeminiErrorResponse};
+use crate::errors::{LlmError, RetryPolicy, RetryDecision, with_policy_retry};
+use crate::structs::general::{Message, Content, Part, LlmChunk, ThoughtContent, ToolCallAssembler, Usage, FinishReason, MessagePart, MediaSource, DocumentPart, ImagePart, GeminiInlineData, GeminiFileData, GeminiFunctionCall, GeminiFunctionResponse, ToolChoice};
+use crate::gemini::types::{GeminiRequest, GenerationConfig, ThinkingConfig, Tool, FunctionDeclaration, ToolConfig, FunctionCallingConfig, GeminiResponse};
 use crate::gemini::request::gemini_request;
-use crate::gemini::response::parse_gemini_response;
-use crate::config::LlmConfig;
+use crate::gemini::response::{parse_gemini_response, map_gemini_finish_reason};
+use crate::options::GeminiSafetySetting;
+use crate::config::{check_config, with_warnings, LlmConfig, ReasoningConfig};
+use crate::client::{LlmClient, RequestBuilderExt};
+use crate::normalize::normalize_messages;
+use crate::media::{prepare_media, mime_type_from_extension};
+use crate::llm::LLM;
+use crate::structured::output_schema;
 
 /// Gemini-aware retry policy for non-conversational Gemini API calls.
 pub struct GeminiCallRetryPolicy {
@@ -19,30 +25,14 @@ pub struct GeminiCallRetryPolicy {
     pub safety_padding: Duration,
 }
 
-impl RetryPolicy<Box<dyn std::error::Error + Send + Sync>> for GeminiCallRetryPolicy {
-    fn should_retry(&self, error: &Box<dyn std::error::Error + Send + Sync>, attempt: usize) -> RetryDecision {
-        if attempt >= self.max_retries {
+impl RetryPolicy<LlmError> for GeminiCallRetryPolicy {
+    fn should_retry(&self, error: &LlmError, attempt: usize) -> RetryDecision {
+        if attempt >= self.max_retries || !error.is_retryable() {
             return RetryDecision::Abort;
         }
 
-        let err_str = error.to_string();
-
-        if let Some(json_start) = err_str.find('{') {
-            let json_part = &err_str[json_start..];
-            if let Ok(error_resp) = serde_json::from_str::<GeminiErrorResponse>(json_part) {
-                if let Some(details) = error_resp.error.details {
-                    for detail in details {
-                        if let Some(delay_str) = detail.retry_delay {
-                            let clean_delay = delay_str.trim_end_matches('s');
-                            if let Ok(secs) = clean_delay.parse::<f64>() {
-                                let suggested_duration = Duration::from_secs_f64(secs);
-                                let total_delay = suggested_duration + self.safety_padding;
-                                return RetryDecision::RetryAfter(total_delay);
-                            }
-                        }
-                    }
-                }
-            }
+        if let Some(suggested_duration) = error.retry_after() {
+            return RetryDecision::RetryAfter(suggested_duration + self.safety_padding);
         }
 
         let fallback_delay = Duration::from_secs(1) * 2u32.saturating_pow((attempt - 1) as u32);
@@ -50,85 +40,223 @@ impl RetryPolicy<Box<dyn std::error::Error + Send + Sync>> for GeminiCallRetryPo
     }
 }
 
-pub fn map_message_parts_to_gemini(parts: Vec<MessagePart>) -> Vec<Part> {
+pub fn map_message_parts_to_gemini(parts: Vec<MessagePart>) -> Result<Vec<Part>, LlmError> {
     parts.into_iter().map(|p| {
-        if p.r#type == "image_url" {
-            if let Some(ImageSource::Base64 { media_type, data }) = p.image_url {
-                // Apply the shared strip utility
-                let clean_data = ImageSource::strip_base64_prefix(&data).to_string();
-                return Part { 
-                    text: None, 
-                    inline_data: Some(GeminiInlineData { mime_type: media_type, data: clean_data }),
-                    thought: None 
+        Ok(match p {
+            MessagePart::Text(text) => Part { text: Some(text), ..Default::default() },
+            // Gemini takes every modality through the same inline_data field
+            MessagePart::Image(ImagePart { source: MediaSource::Base64 { media_type, data }, .. })
+            | MessagePart::Audio(MediaSource::Base64 { media_type, data })
+            | MessagePart::Video(MediaSource::Base64 { media_type, data })
+            | MessagePart::Document(DocumentPart { source: MediaSource::Base64 { media_type, data }, .. }) => Part {
+                inline_data: Some(GeminiInlineData {
+                    mime_type: media_type,
+                    data: MediaSource::strip_base64_prefix(&data).to_string(),
+                }),
+                ..Default::default()
+            },
+            MessagePart::Image(ImagePart { source: MediaSource::Url { url }, .. })
+            | MessagePart::Audio(MediaSource::Url { url })
+            | MessagePart::Video(MediaSource::Url { url })
+            | MessagePart::Document(DocumentPart { source: MediaSource::Url { url }, .. }) => Part {
+                file_data: Some(GeminiFileData { mime_type: mime_type_from_extension(&url).map(str::to_string), file_uri: url }),
+                ..Default::default()
+            },
+            MessagePart::Image(ImagePart { source: MediaSource::File { file_id, media_type }, .. })
+            | MessagePart::Audio(MediaSource::File { file_id, media_type })
+            | MessagePart::Video(MediaSource::File { file_id, media_type })
+            | MessagePart::Document(DocumentPart { source: MediaSource::File { file_id, media_type }, .. }) => Part {
+                file_data: Some(GeminiFileData { mime_type: media_type, file_uri: file_id }),
+                ..Default::default()
+            },
+            MessagePart::ToolCall(call) => Part {
+                function_call: Some(GeminiFunctionCall { id: None, name: call.name, args: call.arguments }),
+                thought_signature: call.signature,
+                ..Default::default()
+            },
+            MessagePart::ToolResult(result) => {
+                // functionResponse.response must be an object; wrap plain-text results
+                let response = match serde_json::from_str::<Value>(&result.content) {
+                    Ok(Value::Object(obj)) => Value::Object(obj),
+                    _ if result.is_error => json!({ "error": result.content }),
+                    _ => json!({ "result": result.content }),
                 };
+                Part {
+                    function_response: Some(GeminiFunctionResponse { id: None, name: result.name, response }),
+                    ..Default::default()
+                }
             }
-        }
-        Part { text: p.text, inline_data: None, thought: None }
+            // `normalize_messages` removes these before Gemini requests are built
+            other @ MessagePart::Thinking(_) => return Err(other.unsupported("Gemini")),
+        })
     }).collect()
 }
 
+/// Converts unified messages into Gemini `contents`.
+pub fn map_messages_to_gemini(messages: Vec<Message>) -> Result<Vec<Content>, LlmError> {
+    messages
+        .into_iter()
+        .map(|msg| Ok(Content {
+            // Function responses are sent back to Gemini in a user turn
+            role: if msg.role == "tool" { "user".to_string() } else { msg.role },
+            parts: map_message_parts_to_gemini(msg.content.as_parts())?,
+        }))
+        .collect()
+}
+
+/// Pulls `system` (and OpenAI-style `developer`) turns out of `contents` into a single
+/// `systemInstruction`, since Gemini only accepts `user` and `model` roles in the conversation.
+pub fn split_gemini_system_instruction(contents: Vec<Content>) -> (Option<Content>, Vec<Content>) {
+    let (system, conversation): (Vec<Content>, Vec<Content>) = contents
+        .into_iter()
+        .partition(|c| c.role == "system" || c.role == "developer");
+
+    let parts: Vec<Part> = system.into_iter().flat_map(|c| c.parts).collect();
+    let instruction = if parts.is_empty() { None } else { Some(Content { role: String::new(), parts }) };
+    (instruction, conversation)
+}
+
+/// Builds the Gemini `tools` array from Google Search grounding and any declared functions.
+pub fn gemini_tools_from_config(cfg: &LlmConfig) -> Option<Vec<Tool>> {
+    let mut tools = Vec::new();
+
+    if cfg.grounding_with_search.unwrap_or(false) {
+        tools.push(Tool { google_search: Some(json!({})), ..Default::default() });
+    }
+
+    if let Some(definitions) = &cfg.tools {
+        let declarations = definitions.iter().map(|t| FunctionDeclaration {
+            name: t.name.clone(),
+            description: t.description.clone(),
+            parameters_json_schema: t.parameters.clone(),
+        }).collect();
+        tools.push(Tool { function_declarations: Some(declarations), ..Default::default() });
+    }
+
+    if tools.is_empty() { None } else { Some(tools) }
+}
+
+/// Maps `ReasoningConfig` to `thinkingConfig`: a budget of -1 lets the model decide and 0
+/// disables thinking.
+fn thinking_config_from_reasoning(reasoning: &ReasoningConfig) -> Option<ThinkingConfig> {
+    let thinking_budget = reasoning.budget();
+    // Thought summaries are only returned when asked for, so ask unless thinking is off
+    let include_thoughts = reasoning.include_thoughts.or_else(|| (thinking_budget != Some(0)).then_some(true));
+    if thinking_budget.is_none() && reasoning.include_thoughts.is_none() {
+        return None;
+    }
+    Some(ThinkingConfig { thinking_budget, include_thoughts })
+}
+
+/// Builds `generationConfig` from the sampling, thinking and structured-output settings.
+pub fn gemini_generation_config_from_config(cfg: &LlmConfig) -> Option<GenerationConfig> {
+    let mut generation_config = GenerationConfig {
+        temperature: cfg.temperature,
+        max_output_tokens: cfg.max_tokens,
+        stop_sequences: cfg.stop.clone(),
+        top_k: cfg.top_k,
+        top_p: cfg.top_p,
+        candidate_count: cfg.candidate_count,
+        seed: cfg.seed,
+        thinking_config: cfg.reasoning_config().and_then(|reasoning| thinking_config_from_reasoning(&reasoning)),
+        ..Default::default()
+    };
+
+    if let Some(output) = cfg.json_schema.as_ref().and_then(output_schema) {
+        generation_config.response_mime_type = Some("application/json".to_string());
+        generation_config.response_json_schema = output.schema;
+    }
+
+    if let Some(options) = &cfg.gemini_options {
+        if !options.response_modalities.is_empty() {
+            generation_config.response_modalities = Some(options.response_modalities.clone());
+        }
+        generation_config.media_resolution = options.media_resolution.clone();
+    }
+
+    let is_empty = serde_json::to_value(&generation_config).map_or(true, |v| v == json!({}));
+    if is_empty { None } else { Some(generation_config) }
+}
+
+/// `safetySettings` from `GeminiOptions`.
+pub fn gemini_safety_settings_from_config(cfg: &LlmConfig) -> Option<Vec<GeminiSafetySetting>> {
+    cfg.gemini_options.as_ref()
+        .map(|options| options.safety_settings.clone())
+        .filter(|settings| !settings.is_empty())
+}
+
+/// Maps `LlmConfig::tool_choice` to Gemini's `toolConfig.functionCallingConfig`.
+pub fn gemini_tool_config_from_config(cfg: &LlmConfig) -> Option<ToolConfig> {
+    let (mode, allowed_function_names) = match cfg.tool_choice.as_ref()? {
+        ToolChoice::Auto => ("AUTO", None),
+        ToolChoice::None => ("NONE", None),
+        ToolChoice::Required => ("ANY", None),
+        ToolChoice::Tool(name) => ("ANY", Some(vec![name.clone()])),
+    };
+    Some(ToolConfig {
+        function_calling_config: FunctionCallingConfig { mode: mode.to_string(), allowed_function_names },
+    })
+}
+
 pub async fn call_gemini(
     messages: Vec<Message>,
     model: Option<&str>,
     config: Option<&LlmConfig>,
-) -> Result<GeminiResponse, Box<dyn std::error::Error + Send + Sync>> {
+) -> Result<GeminiResponse, LlmError> {
+    let client = LlmClient::from_env(LLM::Gemini)?;
+    call_gemini_with_client(&client, messages, model, config).await
+}
+
+/// Options Gemini cannot honor are dropped, or rejected in strict mode, but `GeminiResponse` has
+/// no room for the warnings; `Access::send_convo_message` returns them in `LlmResponse::warnings`.
+pub async fn call_gemini_with_client(
+    client: &LlmClient,
+    messages: Vec<Message>,
+    model: Option<&str>,
+    config: Option<&LlmConfig>,
+) -> Result<GeminiResponse, LlmError> {
+    send_gemini(client, messages, model, config).await.map(|(response, _)| response)
+}
+
+/// `call_gemini_with_client`, with the warnings from checking `config`.
+pub(crate) async fn send_gemini(
+    client: &LlmClient,
+    messages: Vec<Message>,
+    model: Option<&str>,
+    config: Option<&LlmConfig>,
+) -> Result<(GeminiResponse, Vec<String>), LlmError> {
+    let (config, warnings) = check_config(config, &LLM::Gemini, model)?;
+    let config = config.as_ref();
     dotenv().ok();
     // Fixed snake_case warning
     let default_gemini_model: String = env::var("DEFAULT_GEMINI_MODEL").unwrap_or_else(|_| "gemini-2.5-flash".to_string());
 
-    let api_key: String = env::var("GEMINI_API_KEY").map_err(|_| GeneralError {
-        message: "GEMINI API KEY not found in environment variables".to_string(),
-    })?;
-
     // Updated reference to snake_case variable
     let model_name = model.unwrap_or(&default_gemini_model);
-    let url: String = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
-        model_name
-    );
+    let path = format!("/models/{}:generateContent", model_name);
  
-    let contents: Vec<Content> = messages
-        .into_iter()
-        .map(|msg| Content {
-            role: msg.role,
-            parts: map_message_parts_to_gemini(msg.content.as_parts()),
-        })
-        .collect();
+    let messages = prepare_media(client, normalize_messages(messages, &LLM::Gemini)?, config).await?;
+    let (system_instruction, contents) = split_gemini_system_instruction(map_messages_to_gemini(messages)?);
 
     let mut generation_config_option: Option<GenerationConfig> = None;
     let mut tools_option: Option<Vec<Tool>> = None;
+    let mut tool_config_option: Option<ToolConfig> = None;
+    let mut safety_settings_option = None;
 
     if let Some(cfg) = config {
-        let mut current_gen_config = GenerationConfig {
-            temperature: None,
-            thinking_budget: None,
-        };
-
-        let mut config_has_options = false;
-
-        if let Some(thinking_budget) = cfg.thinking_budget {
-            current_gen_config.thinking_budget = Some(thinking_budget);
-            config_has_options = true;
-        }
-
-        if let Some(temp) = cfg.temperature {
-            current_gen_config.temperature = Some(temp);
-            config_has_options = true;
-        }
-
-        if config_has_options {
-            generation_config_option = Some(current_gen_config);
-        }
-
-        if cfg.grounding_with_search.unwrap_or(false) {
-            tools_option = Some(vec![Tool { google_search: Some(json!({})) }]);
-        }
+        generation_config_option = gemini_generation_config_from_config(cfg);
+        tools_option = gemini_tools_from_config(cfg);
+        tool_config_option = gemini_tool_config_from_config(cfg);
+        safety_settings_option = gemini_safety_settings_from_config(cfg);
     }
 
     let request = GeminiRequest {
         contents,
         generation_config: generation_config_option,
         tools: tools_option,
+        tool_config: tool_config_option,
+        system_instruction,
+        safety_settings: safety_settings_option,
     };
 
     let policy = GeminiCallRetryPolicy {
@@ -137,60 +265,59 @@ pub async fn call_gemini(
     };
 
     // Wrap the request and parsing logic in policy-driven retry
-    with_policy_retry(|| async {
-        let response = gemini_request(&url, &api_key, &request, None).await?;
-        
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(Box::new(GeneralError {
-                message: format!("Gemini API returned error {}: {}", status, body),
-            }) as Box<dyn std::error::Error + Send + Sync>);
-        }
-
+    let response = with_policy_retry(|| async {
+        let response = gemini_request(client, &path, &request, config).await?;
         let gemini_response: GeminiResponse = parse_gemini_response(response).await?;
         Ok(gemini_response)
-    }, policy).await
+    }, policy).await?;
+    Ok((response, warnings))
 }
 
 pub async fn call_gemini_stream(
     messages: Vec<Message>,
     model: Option<&str>,
     config: Option<&LlmConfig>,
-) -> Result<BoxStream<'static, Result<LlmChunk, Box<dyn std::error::Error + Send + Sync>>>, Box<dyn std::error::Error + Send + Sync>> {
+) -> Result<BoxStream<'static, Result<LlmChunk, LlmError>>, LlmError> {
+    let client = LlmClient::from_env(LLM::Gemini)?;
+    call_gemini_stream_with_client(&client, messages, model, config).await
+}
+
+pub async fn call_gemini_stream_with_client(
+    client: &LlmClient,
+    messages: Vec<Message>,
+    model: Option<&str>,
+    config: Option<&LlmConfig>,
+) -> Result<BoxStream<'static, Result<LlmChunk, LlmError>>, LlmError> {
+    let (config, warnings) = check_config(config, &LLM::Gemini, model)?;
+    let config = config.as_ref();
     dotenv().ok();
     let default_gemini_model = env::var("DEFAULT_GEMINI_MODEL").unwrap_or_else(|_| "gemini-2.0-flash".to_string());
-    let api_key = env::var("GEMINI_API_KEY").map_err(|_| GeneralError { message: "GEMINI API KEY not found".into() })?;
     let model_name = model.unwrap_or(&default_gemini_model);
     
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/{}:streamGenerateContent?alt=sse", 
-        model_name
-    );
+    let path = format!("/models/{}:streamGenerateContent?alt=sse", model_name);
 
-    let contents: Vec<Content> = messages.into_iter().map(|msg| Content {
-        role: msg.role,
-        parts: map_message_parts_to_gemini(msg.content.as_parts()),
-    }).collect();
+    let messages = prepare_media(client, normalize_messages(messages, &LLM::Gemini)?, config).await?;
+    let (system_instruction, contents) = split_gemini_system_instruction(map_messages_to_gemini(messages)?);
 
     let mut generation_config_option = None;
     let mut tools_option = None;
+    let mut tool_config_option = None;
+    let mut safety_settings_option = None;
     if let Some(cfg) = config {
-        let current_gen_config = GenerationConfig { temperature: cfg.temperature, thinking_budget: cfg.thinking_budget };
-        generation_config_option = Some(current_gen_config);
-        if cfg.grounding_with_search.unwrap_or(false) {
-            tools_option = Some(vec![Tool { google_search: Some(json!({})) }]);
-        }
+        generation_config_option = gemini_generation_config_from_config(cfg);
+        tools_option = gemini_tools_from_config(cfg);
+        tool_config_option = gemini_tool_config_from_config(cfg);
+        safety_settings_option = gemini_safety_settings_from_config(cfg);
     }
 
-    let request = GeminiRequest { contents, generation_config: generation_config_option, tools: tools_option };
+    let request = GeminiRequest { contents, generation_config: generation_config_option, tools: tools_option, tool_config: tool_config_option, system_instruction, safety_settings: safety_settings_option };
     
-    let client = reqwest::Client::new();
-    let res = client.post(&url).header("x-goog-api-key", &api_key).json(&request).send().await?;
+    let res = client.post(&path)?.json_with_extras(&request, config)?.send().await?;
 
-    if !res.status().is_success() {
+    let status = res.status();
+    if !status.is_success() {
         let err_text = res.text().await?;
-        return Err(Box::new(GeneralError { message: format!("Gemini Stream Error: {}", err_text) }));
+        return Err(LlmError::from_gemini(status.as_u16(), &err_text));
     }
 
     let byte_stream = res.bytes_stream();
@@ -198,6 +325,11 @@ pub async fn call_gemini_stream(
     let output_stream = stream! {
         let mut buffer = String::new();
         let mut bytes_stream = byte_stream;
+        let mut tool_calls = ToolCallAssembler::default();
+        let mut tool_call_index = 0;
+        let mut usage = None;
+        let mut finish_reason = None;
+        let mut model = None;
 
         while let Some(item) = bytes_stream.next().await {
             match item {
@@ -206,28 +338,56 @@ pub async fn call_gemini_stream(
                     while let Some(newline_idx) = buffer.find('\n') {
                         let line = buffer.drain(..newline_idx + 1).collect::<String>().trim().to_string();
                         if line.is_empty() { continue; }
-                        if line.starts_with("data: ") {
-                            let json_str = &line[6..];
+                        if let Some(json_str) = line.strip_prefix("data: ") {
                             if let Ok(response) = serde_json::from_str::<GeminiResponse>(json_str) {
                                 if let Some(candidate) = response.candidates.first() {
                                     for part in &candidate.content.parts {
-                                        if let Some(text) = &part.text { yield Ok(LlmChunk::Text(text.clone())); }
-                                        if let Some(thought) = &part.thought {
-                                            if let crate::structs::general::ThoughtContent::String(s) = thought {
-                                                yield Ok(LlmChunk::Reasoning(s.clone()));
+                                        // Gemini streams each functionCall whole, so start, arguments and end arrive together
+                                        if let Some(call) = &part.function_call {
+                                            let index = tool_call_index;
+                                            tool_call_index += 1;
+                                            let id = call.id.clone().unwrap_or_else(|| format!("{}_{}", call.name, index));
+                                            yield Ok(tool_calls.start(index, id, call.name.clone(), part.thought_signature.clone()));
+                                            if !call.args.is_null() {
+                                                if let Some(chunk) = tool_calls.append(index, &call.args.to_string()) { yield Ok(chunk); }
+                                            }
+                                            if let Some(chunk) = tool_calls.end(index) { yield Ok(chunk); }
+                                            continue;
+                                        }
+                                        match (&part.thought, &part.text) {
+                                            // Thought summaries carry the reasoning in `text`
+                                            (Some(ThoughtContent::Boolean(true)), Some(text)) => yield Ok(LlmChunk::Reasoning(text.clone())),
+                                            (Some(ThoughtContent::Boolean(true)), None) => {}
+                                            (thought, text) => {
+                                                if let Some(text) = text { yield Ok(LlmChunk::Text(text.clone())); }
+                                                if let Some(ThoughtContent::String(s)) = thought { yield Ok(LlmChunk::Reasoning(s.clone())); }
                                             }
                                         }
                                     }
+                                    if let Some(reason) = candidate.finish_reason.as_deref() {
+                                        finish_reason = Some(map_gemini_finish_reason(reason));
+                                    }
+                                }
+                                // Every chunk repeats the running totals, so the last one wins
+                                if let Some(metadata) = response.usage_metadata {
+                                    usage = Some(Usage::from(metadata));
+                                }
+                                if response.model_version.is_some() {
+                                    model = response.model_version;
                                 }
                             }
                         }
                     }
                 }
-                Err(e) => yield Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>),
+                Err(e) => yield Err(LlmError::StreamInterrupted { message: e.to_string() }),
             }
         }
-        yield Ok(LlmChunk::Done);
+        // Gemini reports STOP even when the turn ended with function calls
+        if tool_call_index > 0 && finish_reason == Some(FinishReason::Stop) {
+            finish_reason = Some(FinishReason::ToolCalls);
+        }
+        yield Ok(LlmChunk::Done { usage, finish_reason, model, warnings: Vec::new() });
     };
 
-    Ok(Box::pin(output_stream))
+    Ok(with_warnings(Box::pin(output_stream), warnings))
 }
\ No newline at end of file
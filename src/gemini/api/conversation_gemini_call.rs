@@ -1,16 +1,17 @@
 // src/gemini/api/conversation_gemini_call.rs
-use reqwest::header::{HeaderMap, HeaderValue};
 use std::env;
 use std::time::Duration;
 use dotenv::dotenv;
-use serde_json::json; // Removed Map, Value
 
-use crate::errors::{GeneralError, RetryPolicy, RetryDecision, with_policy_retry};
+use crate::errors::{LlmError, RetryPolicy, RetryDecision, with_policy_retry};
 use crate::structs::general::Content;
-use crate::gemini::types::{GeminiRequest, GenerationConfig, Tool, GeminiResponse, GeminiErrorResponse};
+use crate::gemini::types::{GeminiRequest, GenerationConfig, Tool, ToolConfig, GeminiResponse};
+use crate::gemini::api::call_gemini::{gemini_generation_config_from_config, gemini_tools_from_config, gemini_tool_config_from_config, gemini_safety_settings_from_config, split_gemini_system_instruction};
 use crate::gemini::request::gemini_request;
 use crate::gemini::response::parse_gemini_response;
-use crate::config::LlmConfig;
+use crate::config::{check_config, LlmConfig};
+use crate::client::LlmClient;
+use crate::llm::LLM;
 
 /// Gemini-aware retry policy that parses Google RPC error details (specifically `retryDelay`)
 /// and adds a safety padding (+2s) to prevent immediate secondary quota rejections.
@@ -19,33 +20,15 @@ pub struct GeminiRetryPolicy {
     pub safety_padding: Duration,
 }
 
-impl RetryPolicy<Box<dyn std::error::Error + Send + Sync>> for GeminiRetryPolicy {
-    fn should_retry(&self, error: &Box<dyn std::error::Error + Send + Sync>, attempt: usize) -> RetryDecision {
-        if attempt >= self.max_retries {
+impl RetryPolicy<LlmError> for GeminiRetryPolicy {
+    fn should_retry(&self, error: &LlmError, attempt: usize) -> RetryDecision {
+        if attempt >= self.max_retries || !error.is_retryable() {
             return RetryDecision::Abort;
         }
 
-        let err_str = error.to_string();
-
-        // Attempt to extract structured Gemini error JSON from the error message string
-        // Error messages typically follow the format: "Gemini API returned error 429 Too Many Requests: { ... }"
-        if let Some(json_start) = err_str.find('{') {
-            let json_part = &err_str[json_start..];
-            if let Ok(error_resp) = serde_json::from_str::<GeminiErrorResponse>(json_part) {
-                if let Some(details) = error_resp.error.details {
-                    for detail in details {
-                        if let Some(delay_str) = detail.retry_delay {
-                            // Parse strings like "16.747818564s" or "16s"
-                            let clean_delay = delay_str.trim_end_matches('s');
-                            if let Ok(secs) = clean_delay.parse::<f64>() {
-                                let suggested_duration = Duration::from_secs_f64(secs);
-                                let total_delay = suggested_duration + self.safety_padding;
-                                return RetryDecision::RetryAfter(total_delay);
-                            }
-                        }
-                    }
-                }
-            }
+        // `LlmError::from_gemini` lifts the Google RPC `retryDelay` detail into `retry_after`
+        if let Some(suggested_duration) = error.retry_after() {
+            return RetryDecision::RetryAfter(suggested_duration + self.safety_padding);
         }
 
         // Fallback to standard exponential backoff if no structured retryDelay was found
@@ -58,60 +41,49 @@ pub async fn conversation_gemini_call(
     messages: Vec<Content>,
     model: Option<&str>,
     config: Option<&LlmConfig>,
-) -> Result<GeminiResponse, Box<dyn std::error::Error + Send + Sync>> {
+) -> Result<GeminiResponse, LlmError> {
+    let client = LlmClient::from_env(LLM::Gemini)?;
+    conversation_gemini_call_with_client(&client, messages, model, config).await
+}
+
+/// Options Gemini cannot honor are dropped without a warning, or rejected in strict mode.
+pub async fn conversation_gemini_call_with_client(
+    client: &LlmClient,
+    messages: Vec<Content>,
+    model: Option<&str>,
+    config: Option<&LlmConfig>,
+) -> Result<GeminiResponse, LlmError> {
+    let (config, _) = check_config(config, &LLM::Gemini, model)?;
+    let config = config.as_ref();
     dotenv().ok();
     // Fixed snake_case warning
     let default_gemini_model: String = env::var("DEFAULT_GEMINI_MODEL").unwrap_or_else(|_| "gemini-2.5-flash".to_string());
 
-    let api_key: String = env::var("GEMINI_API_KEY").map_err(|_| GeneralError {
-        message: "GOOGLE API KEY not found in environment variables".to_string(),
-    })?;
-
     // Updated reference to snake_case variable
     let model_name = model.unwrap_or(&default_gemini_model);
-    let url: String = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
-        model_name
-    );
+    let path = format!("/models/{}:generateContent", model_name);
+    let (system_instruction, contents) = split_gemini_system_instruction(messages);
 
     let mut generation_config_option: Option<GenerationConfig> = None;
     let mut tools_option: Option<Vec<Tool>> = None;
+    let mut tool_config_option: Option<ToolConfig> = None;
+    let mut safety_settings_option = None;
 
     if let Some(cfg) = config {
-        let mut current_gen_config = GenerationConfig {
-            temperature: None,
-            thinking_budget: None,
-        };
-
-        let mut config_has_options = false;
-
-        if let Some(thinking_budget) = cfg.thinking_budget {
-            current_gen_config.thinking_budget = Some(thinking_budget);
-            config_has_options = true;
-        }
-
-        if let Some(temp) = cfg.temperature {
-            current_gen_config.temperature = Some(temp);
-            config_has_options = true;
-        }
-
-        if config_has_options {
-            generation_config_option = Some(current_gen_config);
-        }
-
-        if cfg.grounding_with_search.unwrap_or(false) {
-            tools_option = Some(vec![Tool { google_search: Some(json!({})) }]);
-        }
+        generation_config_option = gemini_generation_config_from_config(cfg);
+        tools_option = gemini_tools_from_config(cfg);
+        tool_config_option = gemini_tool_config_from_config(cfg);
+        safety_settings_option = gemini_safety_settings_from_config(cfg);
     }
 
     let request = GeminiRequest {
-        contents: messages,
+        contents,
         generation_config: generation_config_option,
         tools: tools_option,
+        tool_config: tool_config_option,
+        system_instruction,
+        safety_settings: safety_settings_option,
     };
-    
-    let mut headers = HeaderMap::new();
-    headers.insert("Content-Type", HeaderValue::from_static("application/json"));
 
     let policy = GeminiRetryPolicy {
         max_retries: 3,
@@ -120,16 +92,7 @@ pub async fn conversation_gemini_call(
 
     // Wrap the request and parsing logic in policy-driven retry
     with_policy_retry(|| async {
-        let response = gemini_request(&url, &api_key, &request, Some(headers.clone())).await?;
-        
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(Box::new(GeneralError {
-                message: format!("Gemini API returned error {}: {}", status, body),
-            }) as Box<dyn std::error::Error + Send + Sync>);
-        }
-
+        let response = gemini_request(client, &path, &request, config).await?;
         let gemini_response: GeminiResponse = parse_gemini_response(response).await?;
         Ok(gemini_response)
     }, policy).await
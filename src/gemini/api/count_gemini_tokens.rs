@@ -1,25 +1,25 @@
 // src/gemini/api/count_gemini_tokens.rs
-use reqwest::header::{HeaderMap, HeaderValue};
-use std::env;
-use dotenv::dotenv;
-
-use crate::errors::GeneralError;
+use crate::errors::LlmError;
 use crate::token_count::{CountTokensRequest, CountTokensResponse, TokenCountContent, TokenCountPart};
 use crate::gemini::request::gemini_request;
+use crate::client::LlmClient;
+use crate::llm::LLM;
 
 
 pub async fn count_gemini_tokens(
     text: &str,
     model: &str,
-) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
-    dotenv().ok();
-
-    let api_key: String = env::var("GEMINI_API_KEY").map_err(|_| GeneralError {
-        message: "GOOGLE API KEY not found in environment variables".to_string(),
-    })?;
+) -> Result<u32, LlmError> {
+    let client = LlmClient::from_env(LLM::Gemini)?;
+    count_gemini_tokens_with_client(&client, text, model).await
+}
 
-    let url =
-        format!("https://generativelanguage.googleapis.com/v1beta/{}:countTokens", model);
+pub async fn count_gemini_tokens_with_client(
+    client: &LlmClient,
+    text: &str,
+    model: &str,
+) -> Result<u32, LlmError> {
+    let path = format!("/{}:countTokens", model);
 
     let request = CountTokensRequest {
         contents: vec![TokenCountContent {
@@ -29,19 +29,19 @@ pub async fn count_gemini_tokens(
         }],
     };
 
-    let mut headers = HeaderMap::new();
-    headers.insert("Content-Type", HeaderValue::from_static("application/json"));
-
-    let res = gemini_request(&url, &api_key, &request, Some(headers)).await?;
+    let res = gemini_request(client, &path, &request, None).await?;
 
-    let rspns_strng = res.text().await.map_err(|e: reqwest::Error| {
-        // eprintln!("------------d------------------{:?}", e);
-        Box::new(GeneralError {
-            message: format!("Failed to read response from Gemini API: {}", e.to_string()),
-        }) as Box<dyn std::error::Error + Send + Sync>
+    let status = res.status();
+    let rspns_strng = res.text().await.map_err(|e| LlmError::Network {
+        message: format!("Failed to read response from Gemini API: {}", e),
     })?;
+
+    if !status.is_success() {
+        return Err(LlmError::from_gemini(status.as_u16(), &rspns_strng));
+    }
+
     let tok_resp: CountTokensResponse = serde_json::from_str(&rspns_strng)
-        .map_err(|e| GeneralError {
+        .map_err(|e| LlmError::Parse {
             message: format!("Failed to parse token count response: {}", e),
         })?;
     Ok(tok_resp.totalTokens)
@@ -0,0 +1,219 @@
+// src/gemini/api/files.rs
+use reqwest::Response;
+
+use crate::errors::LlmError;
+use crate::models::gemini::{GeminiFile, ListFilesResponse, UploadFileResponse};
+use crate::client::LlmClient;
+use crate::llm::LLM;
+
+/// Bytes sent per upload request; the protocol needs a multiple of 256 KiB.
+const UPLOAD_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+/// How many times a failed chunk is resumed before the upload gives up.
+const MAX_CHUNK_RETRIES: u32 = 3;
+
+pub async fn upload_gemini_file(
+    data: Vec<u8>,
+    media_type: &str,
+    display_name: Option<&str>,
+) -> Result<GeminiFile, LlmError> {
+    let client = LlmClient::from_env(LLM::Gemini)?;
+    upload_gemini_file_with_client(&client, data, media_type, display_name).await
+}
+
+/// Uploads with Gemini's resumable protocol. The data is sent in chunks; when a chunk fails,
+/// the server is asked how much it received and the upload continues from there.
+pub async fn upload_gemini_file_with_client(
+    client: &LlmClient,
+    data: Vec<u8>,
+    media_type: &str,
+    display_name: Option<&str>,
+) -> Result<GeminiFile, LlmError> {
+    if data.is_empty() {
+        return Err(LlmError::InvalidRequest {
+            message: "Cannot upload an empty file".to_string(),
+        });
+    }
+
+    let metadata = match display_name {
+        Some(name) => serde_json::json!({ "file": { "display_name": name } }),
+        None => serde_json::json!({ "file": {} }),
+    };
+    let start = client
+        .post_url(&format!("{}/files", upload_base_url(client.base_url())))?
+        .header("X-Goog-Upload-Protocol", "resumable")
+        .header("X-Goog-Upload-Command", "start")
+        .header("X-Goog-Upload-Header-Content-Length", data.len())
+        .header("X-Goog-Upload-Header-Content-Type", media_type)
+        .json(&metadata)
+        .send()
+        .await
+        .map_err(|e| LlmError::Network {
+            message: format!("Failed to start upload to Gemini API: {}", e),
+        })?;
+    let start = check_status(start).await?;
+    let session_url = start
+        .headers()
+        .get("x-goog-upload-url")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .ok_or_else(|| LlmError::Parse {
+            message: "Gemini API did not return an upload URL".to_string(),
+        })?;
+
+    let mut offset = 0;
+    let mut retries = 0;
+    loop {
+        let end = (offset + UPLOAD_CHUNK_SIZE).min(data.len());
+        let command = if end == data.len() { "upload, finalize" } else { "upload" };
+        let sent = client
+            .post_url(&session_url)?
+            .header("X-Goog-Upload-Command", command)
+            .header("X-Goog-Upload-Offset", offset)
+            .body(data[offset..end].to_vec())
+            .send()
+            .await;
+
+        match sent {
+            Ok(res) if res.status().is_success() => {
+                if end == data.len() {
+                    return parse_upload(res).await;
+                }
+                offset = end;
+                retries = 0;
+            }
+            Ok(res) if !res.status().is_server_error() => return Err(error_from(res).await),
+            failed => {
+                retries += 1;
+                if retries > MAX_CHUNK_RETRIES {
+                    return Err(match failed {
+                        Ok(res) => error_from(res).await,
+                        Err(e) => LlmError::Network { message: format!("Failed to upload to Gemini API: {}", e) },
+                    });
+                }
+                let status = client
+                    .post_url(&session_url)?
+                    .header("X-Goog-Upload-Command", "query")
+                    .send()
+                    .await
+                    .map_err(|e| LlmError::Network {
+                        message: format!("Failed to query upload status from Gemini API: {}", e),
+                    })?;
+                let status = check_status(status).await?;
+                if upload_header(&status, "x-goog-upload-status").as_deref() == Some("final") {
+                    return parse_upload(status).await;
+                }
+                offset = upload_header(&status, "x-goog-upload-size-received")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(offset);
+            }
+        }
+    }
+}
+
+pub async fn list_gemini_files() -> Result<Vec<GeminiFile>, LlmError> {
+    let client = LlmClient::from_env(LLM::Gemini)?;
+    list_gemini_files_with_client(&client).await
+}
+
+/// Lists every file, following page tokens.
+pub async fn list_gemini_files_with_client(client: &LlmClient) -> Result<Vec<GeminiFile>, LlmError> {
+    let mut files = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut request = client.get("/files")?.query(&[("pageSize", "100")]);
+        if let Some(token) = &page_token {
+            request = request.query(&[("pageToken", token)]);
+        }
+        let res = request.send().await.map_err(|e| LlmError::Network {
+            message: format!("Failed to send request to Gemini API: {}", e),
+        })?;
+        let page: ListFilesResponse = parse_body(check_status(res).await?).await?;
+        files.extend(page.files);
+
+        match page.next_page_token {
+            Some(token) if !token.is_empty() => page_token = Some(token),
+            _ => return Ok(files),
+        }
+    }
+}
+
+pub async fn get_gemini_file(name: &str) -> Result<GeminiFile, LlmError> {
+    let client = LlmClient::from_env(LLM::Gemini)?;
+    get_gemini_file_with_client(&client, name).await
+}
+
+/// `name` is the resource name ("files/abc123") or just the ID.
+pub async fn get_gemini_file_with_client(client: &LlmClient, name: &str) -> Result<GeminiFile, LlmError> {
+    let res = client
+        .get(&file_path(name))?
+        .send()
+        .await
+        .map_err(|e| LlmError::Network {
+            message: format!("Failed to send request to Gemini API: {}", e),
+        })?;
+    parse_body(check_status(res).await?).await
+}
+
+pub async fn delete_gemini_file(name: &str) -> Result<(), LlmError> {
+    let client = LlmClient::from_env(LLM::Gemini)?;
+    delete_gemini_file_with_client(&client, name).await
+}
+
+pub async fn delete_gemini_file_with_client(client: &LlmClient, name: &str) -> Result<(), LlmError> {
+    let res = client
+        .delete(&file_path(name))?
+        .send()
+        .await
+        .map_err(|e| LlmError::Network {
+            message: format!("Failed to send request to Gemini API: {}", e),
+        })?;
+    check_status(res).await?;
+    Ok(())
+}
+
+/// Uploads go to the same API under an `/upload` prefix:
+/// `https://host/v1beta` becomes `https://host/upload/v1beta`.
+fn upload_base_url(base_url: &str) -> String {
+    let path_start = base_url
+        .find("://")
+        .and_then(|scheme_end| base_url[scheme_end + 3..].find('/').map(|i| scheme_end + 3 + i))
+        .unwrap_or(base_url.len());
+    format!("{}/upload{}", &base_url[..path_start], &base_url[path_start..])
+}
+
+fn file_path(name: &str) -> String {
+    format!("/files/{}", name.trim_start_matches("files/"))
+}
+
+fn upload_header(res: &Response, name: &str) -> Option<String> {
+    res.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+}
+
+async fn check_status(res: Response) -> Result<Response, LlmError> {
+    match res.status().is_success() {
+        true => Ok(res),
+        false => Err(error_from(res).await),
+    }
+}
+
+async fn error_from(res: Response) -> LlmError {
+    let status = res.status().as_u16();
+    match res.text().await {
+        Ok(body) => LlmError::from_gemini(status, &body),
+        Err(e) => LlmError::Network { message: format!("Failed to read response body: {}", e) },
+    }
+}
+
+async fn parse_upload(res: Response) -> Result<GeminiFile, LlmError> {
+    let upload: UploadFileResponse = parse_body(res).await?;
+    Ok(upload.file)
+}
+
+async fn parse_body<T: serde::de::DeserializeOwned>(res: Response) -> Result<T, LlmError> {
+    let body = res.text().await.map_err(|e| LlmError::Network {
+        message: format!("Failed to read response body: {}", e),
+    })?;
+    serde_json::from_str(&body).map_err(|e| LlmError::Parse {
+        message: format!("Failed to parse response from Gemini API: {}", e),
+    })
+}
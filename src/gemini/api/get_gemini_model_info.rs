@@ -1,69 +1,47 @@
 // src/gemini/api/get_gemini_model_info.rs
-use std::env;
-use dotenv::dotenv;
-
-use crate::errors::GeneralError;
+use crate::errors::LlmError;
 use crate::models::gemini::ModelInfo;
-use reqwest::header::{HeaderMap, HeaderValue};
+use crate::client::LlmClient;
+use crate::llm::LLM;
 
 
 pub async fn get_gemini_model_info(
     model: &str,
-) -> Result<ModelInfo, Box<dyn std::error::Error + Send + Sync>> {
-    dotenv().ok();
-
-    let api_key: String = env::var("GEMINI_API_KEY").map_err(|_| {
-        Box::new(GeneralError {
-            message: "GEMINI_API_KEY not found in environment variables".to_string(),
-        }) as Box<dyn std::error::Error + Send + Sync>
-    })?;
-
-    let url: String = format!(
-        "https://generativelanguage.googleapis.com/v1beta/{}",
-        model
-    );
-
-    let client = reqwest::Client::new();
-
-    // Create a HeaderMap and add the API key
-    let mut headers = HeaderMap::new();
-    let api_key_value = HeaderValue::from_str(&api_key).map_err(|e| {
-         Box::new(GeneralError {
-            message: format!("Invalid API key format: {}", e.to_string()),
-        }) as Box<dyn std::error::Error + Send + Sync>
-    })?;
-    headers.insert("x-goog-api-key", api_key_value);
+) -> Result<ModelInfo, LlmError> {
+    let client = LlmClient::from_env(LLM::Gemini)?;
+    get_gemini_model_info_with_client(&client, model).await
+}
 
+pub async fn get_gemini_model_info_with_client(
+    client: &LlmClient,
+    model: &str,
+) -> Result<ModelInfo, LlmError> {
+    // `model` is the full resource name, e.g. "models/gemini-2.5-flash"
     let response = client
-        .get(&url) // Use the URL without the key
-        .headers(headers) // Add the headers here
+        .get(&format!("/{}", model))?
         .send()
         .await
-        .map_err(|e| {
-            Box::new(GeneralError {
-                message: format!("Failed to send request to Gemini API: {}", e.to_string()),
-            }) as Box<dyn std::error::Error + Send + Sync>
+        .map_err(|e| LlmError::Network {
+            message: format!("Failed to send request to Gemini API: {}", e),
         })?;
 
-    let response_body = response.text().await.map_err(|e| {
-        Box::new(GeneralError {
-            message: format!("Failed to read response body: {}", e.to_string()),
-        }) as Box<dyn std::error::Error + Send + Sync>
+    let status = response.status();
+    let response_body = response.text().await.map_err(|e| LlmError::Network {
+        message: format!("Failed to read response body: {}", e),
     })?;
 
+    if !status.is_success() {
+        return Err(LlmError::from_gemini(status.as_u16(), &response_body));
+    }
+
     if response_body.is_empty() {
-        return Err(Box::new(GeneralError {
+        return Err(LlmError::Parse {
             message: "Response body is empty".to_string(),
-        }));
+        });
     }
 
-    let res: ModelInfo = serde_json::from_str(&response_body).map_err(|e| {
-        Box::new(GeneralError {
-            message: format!(
-                "Failed to parse response from Gemini API: {}",
-                e.to_string()
-            ),
-        }) as Box<dyn std::error::Error + Send + Sync>
+    let res: ModelInfo = serde_json::from_str(&response_body).map_err(|e| LlmError::Parse {
+        message: format!("Failed to parse response from Gemini API: {}", e),
     })?;
 
     Ok(res)
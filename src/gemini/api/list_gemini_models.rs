@@ -1,56 +1,38 @@
 // src/gemini/api/list_gemini_models.rs
-use std::env;
-use dotenv::dotenv;
-use reqwest::header::{HeaderMap, HeaderValue};
-
-use crate::errors::GeneralError;
+use crate::errors::LlmError;
 use crate::models::gemini::{ListModelsResponse, ModelInfo};
+use crate::client::LlmClient;
+use crate::llm::LLM;
 
 
-pub async fn list_gemini_models() -> Result<Vec<ModelInfo>, Box<dyn std::error::Error + Send + Sync>>
+pub async fn list_gemini_models() -> Result<Vec<ModelInfo>, LlmError>
 {
-    dotenv().ok();
-
-    let api_key: String = env::var("GEMINI_API_KEY").map_err(|_| {
-        Box::new(GeneralError {
-            message: "GEMINI_API_KEY not found in environment variables".to_string(),
-        }) as Box<dyn std::error::Error + Send + Sync>
-    })?;
-
-    let url: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+    let client = LlmClient::from_env(LLM::Gemini)?;
+    list_gemini_models_with_client(&client).await
+}
 
-    let client = reqwest::Client::new();
+pub async fn list_gemini_models_with_client(client: &LlmClient) -> Result<Vec<ModelInfo>, LlmError>
+{
+    let response = client
+        .get("/models")?
+        .send()
+        .await
+        .map_err(|e| LlmError::Network {
+            message: format!("Failed to send request to Gemini API: {}", e),
+        })?;
 
-    // Create a HeaderMap and add the API key
-    let mut headers = HeaderMap::new();
-    let api_key_value = HeaderValue::from_str(&api_key).map_err(|e| {
-        Box::new(GeneralError {
-            message: format!("Invalid API key format: {}", e.to_string()),
-        }) as Box<dyn std::error::Error + Send + Sync>
+    let status = response.status();
+    let response_body = response.text().await.map_err(|e| LlmError::Network {
+        message: format!("Failed to read response body: {}", e),
     })?;
-    headers.insert("x-goog-api-key", api_key_value);
 
+    if !status.is_success() {
+        return Err(LlmError::from_gemini(status.as_u16(), &response_body));
+    }
 
-    let res: ListModelsResponse = client
-        .get(url) // Removed the key from the URL
-        .headers(headers) // Add the headers here
-        .send()
-        .await
-        .map_err(|e| {
-            Box::new(GeneralError {
-                message: format!("Failed to send request to Gemini API: {}", e.to_string()),
-            }) as Box<dyn std::error::Error + Send + Sync>
-        })?
-        .json()
-        .await
-        .map_err(|e| {
-            Box::new(GeneralError {
-                message: format!(
-                    "Failed to parse response from Gemini API: {}",
-                    e.to_string()
-                ),
-            }) as Box<dyn std::error::Error + Send + Sync>
-        })?;
+    let res: ListModelsResponse = serde_json::from_str(&response_body).map_err(|e| LlmError::Parse {
+        message: format!("Failed to parse response from Gemini API: {}", e),
+    })?;
 
     Ok(res.models)
 }
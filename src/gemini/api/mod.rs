@@ -5,10 +5,12 @@ pub mod get_gemini_model_info;
 pub mod list_gemini_models;
 pub mod count_gemini_tokens;
 pub mod embeddings;
+pub mod files;
 
 pub use call_gemini::*;
 pub use conversation_gemini_call::*;
 pub use get_gemini_model_info::*;
 pub use list_gemini_models::*;
 pub use count_gemini_tokens::*;
-pub use embeddings::*;
\ No newline at end of file
+pub use embeddings::*;
+pub use files::*;
\ No newline at end of file
@@ -1,37 +1,18 @@
 // src/gemini/request.rs
-use reqwest::header::{HeaderMap, HeaderValue};
-use reqwest::Client;
-
-use crate::errors::GeneralError;
+use crate::client::{LlmClient, RequestBuilderExt};
+use crate::config::LlmConfig;
+use crate::errors::LlmError;
 
+/// POSTs `request` as JSON to `path` (relative to the client's base URL, e.g. `/models/x:generateContent`).
+/// `config` supplies `extra_body` and `extra_headers` for chat requests.
 pub async fn gemini_request<T: serde::Serialize>(
-    url: &str,
-    api_key: &str,
+    client: &LlmClient,
+    path: &str,
     request: &T,
-    headers: Option<HeaderMap>, // Make headers mutable
-) -> Result<reqwest::Response, Box<dyn std::error::Error + Send + Sync>> {
-    let client = Client::new();
-
-    // Create or get the HeaderMap
-    let mut final_headers = headers.unwrap_or_else(HeaderMap::new);
-
-    // Add the API key to the headers
-    let api_key_value = HeaderValue::from_str(api_key).map_err(|e| {
-         Box::new(GeneralError {
-            message: format!("Invalid API key format: {}", e.to_string()),
-        }) as Box<dyn std::error::Error + Send + Sync>
-    })?; // Handle potential invalid header value characters
-    final_headers.insert("x-goog-api-key", api_key_value);
-
-    // Build the request without the key in the URL
-    let req = client
-        .post(url) // Removed the key from the URL format
-        .json(request)
-        .headers(final_headers); // Add the constructed headers
-
-    req.send().await.map_err(|e| {
-        Box::new(GeneralError {
-            message: format!("Failed to send request to Gemini API: {}", e.to_string()),
-        }) as Box<dyn std::error::Error + Send + Sync>
+    config: Option<&LlmConfig>,
+) -> Result<reqwest::Response, LlmError> {
+    // The API key travels in the `x-goog-api-key` header, never in the URL
+    client.post(path)?.json_with_extras(request, config)?.send().await.map_err(|e| LlmError::Network {
+        message: format!("Failed to send request to Gemini API: {}", e),
     })
-}
\ No newline at end of file
+}
@@ -1,23 +1,48 @@
 // src/gemini/response.rs
-use crate::errors::GeneralError;
+use crate::errors::LlmError;
 use serde::de::DeserializeOwned;
-use crate::gemini::types::{GeminiResponse, GeminiErrorResponse};
-use crate::structs::general::{LlmResponse, ThoughtContent}; // Added ThoughtContent import
+use crate::gemini::types::{GeminiResponse, UsageMetadata};
+use crate::structs::general::{LlmResponse, ThoughtContent, ToolCall, Usage, FinishReason}; // Added ThoughtContent import
+
+/// Finish reasons Gemini uses when a candidate was withheld by a safety or policy filter.
+const BLOCKED_FINISH_REASONS: &[&str] = &["SAFETY", "RECITATION", "BLOCKLIST", "PROHIBITED_CONTENT", "SPII", "IMAGE_SAFETY"];
+
+impl From<UsageMetadata> for Usage {
+    fn from(usage: UsageMetadata) -> Self {
+        // Gemini counts thinking tokens separately from the candidate tokens
+        let thoughts = usage.thoughts_token_count.unwrap_or_default();
+        Usage {
+            prompt_tokens: usage.prompt_token_count,
+            completion_tokens: usage.candidates_token_count + thoughts,
+            reasoning_tokens: usage.thoughts_token_count,
+            cached_input_tokens: usage.cached_content_token_count,
+        }
+    }
+}
+
+pub(crate) fn map_gemini_finish_reason(reason: &str) -> FinishReason {
+    match reason {
+        "STOP" => FinishReason::Stop,
+        "MAX_TOKENS" => FinishReason::Length,
+        r if BLOCKED_FINISH_REASONS.contains(&r) => FinishReason::ContentFilter,
+        other => FinishReason::Other(other.to_string()),
+    }
+}
 
 pub async fn parse_gemini_response<T: DeserializeOwned>(
     response: reqwest::Response,
-) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
-    let response_body = response.text().await.map_err(|e| {
-        Box::new(GeneralError {
-            message: format!("Failed to read response from Gemini API: {}", e.to_string()),
-        }) as Box<dyn std::error::Error + Send + Sync>
+) -> Result<T, LlmError> {
+    let status = response.status();
+    let response_body = response.text().await.map_err(|e| LlmError::Network {
+        message: format!("Failed to read response from Gemini API: {}", e),
     })?;
 
-    serde_json::from_str(&response_body).map_err(|e| {
-        eprintln!("Failed to parse response body: {}", response_body);
-        Box::new(GeneralError {
-            message: format!("Failed to parse response from Gemini API 1: {}", e.to_string()),
-        }) as Box<dyn std::error::Error + Send + Sync>
+    if !status.is_success() {
+        return Err(LlmError::from_gemini(status.as_u16(), &response_body));
+    }
+
+    serde_json::from_str(&response_body).map_err(|e| LlmError::Parse {
+        message: format!("Failed to parse response from Gemini API: {} - Body: {}", e, response_body),
     })
 }
 
@@ -25,28 +50,57 @@ pub async fn parse_gemini_response<T: DeserializeOwned>(
 /// Handles the ThoughtContent enum to extract reasoning as a String.
 pub fn gemini_to_llm_response(
     gemini_response: GeminiResponse,
-) -> Result<LlmResponse, Box<dyn std::error::Error + Send + Sync>> {
-    let candidate = gemini_response.candidates.into_iter().next()
-        .ok_or_else(|| Box::new(GeneralError { message: "No Gemini candidates".into() }) as Box<dyn std::error::Error + Send + Sync>)?;
+) -> Result<LlmResponse, LlmError> {
+    let block_reason = gemini_response.prompt_feedback.as_ref().and_then(|f| f.block_reason.clone());
+    let usage = gemini_response.usage_metadata.map(Usage::from);
+    let candidate = match gemini_response.candidates.into_iter().next() {
+        Some(candidate) => candidate,
+        None => {
+            return Err(match block_reason {
+                Some(reason) => LlmError::ContentFiltered { message: format!("Gemini blocked the prompt: {}", reason) },
+                None => LlmError::Parse { message: "No Gemini candidates".into() },
+            });
+        }
+    };
+
+    if candidate.content.parts.is_empty() {
+        if let Some(reason) = candidate.finish_reason.as_deref() {
+            if BLOCKED_FINISH_REASONS.contains(&reason) {
+                return Err(LlmError::ContentFiltered {
+                    message: format!("Gemini withheld the response: {}", reason),
+                });
+            }
+        }
+    }
+
+    let finish_reason = candidate.finish_reason.as_deref().map(map_gemini_finish_reason);
 
     let mut text = String::new();
     // FIX: Explicitly define reasoning as Option<String> to prevent type inference errors
     let mut reasoning: Option<String> = None;
+    let mut tool_calls: Vec<ToolCall> = Vec::new();
 
     for part in candidate.content.parts {
+        if let Some(call) = part.function_call {
+            // Gemini only assigns call ids on some models; synthesize a stable one otherwise
+            let id = call.id.unwrap_or_else(|| format!("{}_{}", call.name, tool_calls.len()));
+            tool_calls.push(ToolCall { id, name: call.name, arguments: call.args, signature: part.thought_signature });
+            continue;
+        }
+
         match part.thought {
             // Case 1: Standard Gemini - thought is a string containing the reasoning
             Some(ThoughtContent::String(s)) => {
-                reasoning = Some(s);
+                reasoning.get_or_insert_with(String::new).push_str(&s);
                 if let Some(t) = part.text {
                     text.push_str(&t);
                 }
             }
-            // Case 2: Gemma - thought is a boolean flag. 
+            // Case 2: Thought summaries and Gemma - thought is a boolean flag.
             // If true, the text in this part IS the reasoning content.
             Some(ThoughtContent::Boolean(true)) => {
                 if let Some(t) = part.text {
-                    reasoning = Some(t);
+                    reasoning.get_or_insert_with(String::new).push_str(&t);
                 }
             }
             // Case 3: Thought is false, or no thought field present - treat as normal text
@@ -60,31 +114,18 @@ pub fn gemini_to_llm_response(
 
     // eprintln!("Extracted text from Gemini response: {}", text);
     // eprintln!("Extracted reasoning from Gemini response: {:#?}", reasoning);
-    Ok(LlmResponse { text, reasoning })
-}
-
-pub fn handle_gemini_error(response_body: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-    let gemini_response: Result<GeminiResponse, _> = serde_json::from_str(&response_body);
+    // Gemini reports STOP even when the turn ended with function calls
+    let finish_reason = match finish_reason {
+        Some(FinishReason::Stop) if !tool_calls.is_empty() => Some(FinishReason::ToolCalls),
+        other => other,
+    };
 
-    match gemini_response {
-        Ok(response) => Ok(response.candidates[0].content.parts[0].text.clone().unwrap()),
-        Err(_) => {
-            let error_response: Result<GeminiErrorResponse, _> =
-                serde_json::from_str(&response_body).map_err(|e| {
-                    Box::new(GeneralError {
-                        message: format!(
-                            "Failed to parse error response from Gemini API 2: {}",
-                            e.to_string()
-                        ),
-                    }) as Box<dyn std::error::Error + Send + Sync>
-                });
+    Ok(LlmResponse { text, reasoning, tool_calls, usage, finish_reason, warnings: Vec::new(), thinking: Vec::new() })
+}
 
-            match error_response {
-                Ok(err) => Err(Box::new(GeneralError {
-                    message: format!("Gemini API Error: {}", err.error.message),
-                }) as Box<dyn std::error::Error + Send + Sync>),
-                Err(e) => Err(e),
-            }
-        }
+pub fn handle_gemini_error(response_body: &str) -> Result<String, LlmError> {
+    match serde_json::from_str::<GeminiResponse>(response_body) {
+        Ok(response) => gemini_to_llm_response(response).map(|r| r.text),
+        Err(_) => Err(LlmError::from_gemini(0, response_body)),
     }
 }
@@ -2,25 +2,82 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::options::GeminiSafetySetting;
 use crate::structs::general::Content;
 
 // New: Struct for generation configuration
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
 pub struct GenerationConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f64>,
-    #[serde(rename = "thinkingBudget", skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub candidate_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_config: Option<ThinkingConfig>,
+    /// `application/json` for structured output.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_mime_type: Option<String>,
+    // Standard JSON Schema; the older `responseSchema` only takes the OpenAPI subset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_json_schema: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_modalities: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_resolution: Option<String>,
+}
+
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ThinkingConfig {
+    /// `0` turns thinking off and `-1` lets the model decide.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub thinking_budget: Option<i32>,
-    // Add other generation config options here if needed, e.g., top_p, top_k, candidate_count
+    /// Returns thought summaries, which end up in `LlmResponse::reasoning`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_thoughts: Option<bool>,
 }
 
 // New: Struct for tools (like google_search)
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Default)]
 pub struct Tool {
     // google_search tool is an empty object, so use serde_json::Value to represent {}
     #[serde(skip_serializing_if = "Option::is_none")]
     pub google_search: Option<serde_json::Value>, 
-    // Add other tool types here if needed
+    #[serde(rename = "functionDeclarations", skip_serializing_if = "Option::is_none")]
+    pub function_declarations: Option<Vec<FunctionDeclaration>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FunctionDeclaration {
+    pub name: String,
+    pub description: String,
+    // Standard JSON Schema; the older `parameters` field only takes the OpenAPI subset
+    #[serde(rename = "parametersJsonSchema")]
+    pub parameters_json_schema: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolConfig {
+    #[serde(rename = "functionCallingConfig")]
+    pub function_calling_config: FunctionCallingConfig,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FunctionCallingConfig {
+    pub mode: String, // "AUTO", "ANY" or "NONE"
+    #[serde(rename = "allowedFunctionNames", skip_serializing_if = "Option::is_none")]
+    pub allowed_function_names: Option<Vec<String>>,
 }
 
 // Updated: GeminiRequest now includes generationConfig and tools
@@ -31,24 +88,40 @@ pub struct GeminiRequest {
     pub generation_config: Option<GenerationConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Tool>>,
+    #[serde(rename = "toolConfig", skip_serializing_if = "Option::is_none")]
+    pub tool_config: Option<ToolConfig>,
+    #[serde(rename = "systemInstruction", skip_serializing_if = "Option::is_none")]
+    pub system_instruction: Option<Content>,
+    #[serde(rename = "safetySettings", skip_serializing_if = "Option::is_none")]
+    pub safety_settings: Option<Vec<GeminiSafetySetting>>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct GeminiResponse {
+    // Omitted entirely when the prompt itself is blocked
+    #[serde(default)]
     pub candidates: Vec<Candidate>,
+    #[serde(rename = "promptFeedback")]
     pub prompt_feedback: Option<PromptFeedback>,
+    #[serde(rename = "usageMetadata")]
     pub usage_metadata: Option<UsageMetadata>,
+    #[serde(rename = "modelVersion")]
     pub model_version: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UsageMetadata {
-    #[serde(rename = "promptTokenCount")]
+    #[serde(rename = "promptTokenCount", default)]
     pub prompt_token_count: u32,
-    #[serde(rename = "candidatesTokenCount")]
+    // Omitted when the candidate is empty (e.g. blocked)
+    #[serde(rename = "candidatesTokenCount", default)]
     pub candidates_token_count: u32,
-    #[serde(rename = "totalTokenCount")]
+    #[serde(rename = "totalTokenCount", default)]
     pub total_token_count: u32,
+    #[serde(rename = "thoughtsTokenCount")]
+    pub thoughts_token_count: Option<u32>,
+    #[serde(rename = "cachedContentTokenCount")]
+    pub cached_content_token_count: Option<u32>,
     #[serde(rename = "promptTokensDetails")]
     pub prompt_tokens_details: Option<Vec<TokenDetails>>,
     #[serde(rename = "candidatesTokensDetails")]
@@ -81,12 +154,15 @@ pub struct SearchEntryPoint {
 
 #[derive(Debug, Deserialize)]
 pub struct Candidate {
+    // Safety-blocked candidates come back without content
+    #[serde(default)]
     pub content: Content,
     #[serde(rename = "finishReason")]
     pub finish_reason: Option<String>,
     #[serde(rename = "avgLogprobs")]
     pub avg_log_probs: Option<f64>,
     pub index: Option<usize>,
+    #[serde(rename = "safetyRatings")]
     pub safety_ratings: Option<Vec<SafetyRating>>,
     #[serde(rename = "groundingMetadata")]
     pub grounding_metadata: Option<GroundingMetadata>,
@@ -94,6 +170,9 @@ pub struct Candidate {
 
 #[derive(Debug, Deserialize)]
 pub struct PromptFeedback {
+    #[serde(rename = "blockReason")]
+    pub block_reason: Option<String>,
+    #[serde(rename = "safetyRatings", default)]
     pub safety_ratings: Vec<SafetyRating>,
 }
 
@@ -7,9 +7,21 @@ pub mod errors;
 pub mod models;
 pub mod token_count;
 pub mod structs;
+#[cfg(test)]
 pub mod tests;
 pub mod config;
 pub mod llama_server;
+pub mod client;
+pub mod provider;
+pub mod normalize;
+pub mod media;
+pub mod files;
+pub mod structured;
+pub mod options;
+
+/// Re-exported so `#[derive(JsonSchema)]` types match the version `send_structured` expects.
+#[cfg(feature = "structured")]
+pub use schemars;
 
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
@@ -18,7 +30,7 @@ use crate::llm::{LLM, Access};
 #[cfg(feature = "python")]
 use crate::config::LlmConfig;
 #[cfg(feature = "python")]
-use crate::structs::general::{Message, MessageContent, MessagePart, ImageSource, LlmResponse};
+use crate::structs::general::{Message, MessageContent, MessagePart, ImagePart, ImageSource, LlmResponse};
 
 /// Python-exposed wrapper for LlmConfig
 #[cfg(feature = "python")]
@@ -95,32 +107,19 @@ impl PyMessage {
         let mut parts = Vec::new();
 
         if let Some(t) = text {
-            parts.push(MessagePart {
-                r#type: "text".to_string(),
-                text: Some(t),
-                image_url: None,
-            });
+            parts.push(MessagePart::Text(t));
         }
 
         if let Some(b64) = image_base64 {
             let media_type = image_media_type.unwrap_or_else(|| "image/jpeg".to_string());
-            parts.push(MessagePart {
-                r#type: "image_url".to_string(),
-                text: None,
-                image_url: Some(ImageSource::Base64 { media_type, data: b64 }),
-            });
+            parts.push(MessagePart::Image(ImageSource::Base64 { media_type, data: b64 }.into()));
         } else if let Some(url) = image_url {
-            parts.push(MessagePart {
-                r#type: "image_url".to_string(),
-                text: None,
-                image_url: Some(ImageSource::Url { url }),
-            });
+            parts.push(MessagePart::Image(ImageSource::Url { url }.into()));
         }
 
-        let content = if parts.len() == 1 && parts[0].r#type == "text" {
-            MessageContent::Text(parts[0].text.clone().unwrap_or_default())
-        } else {
-            MessageContent::Array(parts)
+        let content = match parts.as_slice() {
+            [MessagePart::Text(t)] => MessageContent::Text(t.clone()),
+            _ => MessageContent::Array(parts),
         };
 
         Self {
@@ -137,15 +136,7 @@ impl PyMessage {
     /// Expose 'text' attribute to Python (extracts text from Text or Array variants)
     #[getter]
     fn text(&self) -> String {
-        match &self.inner.content {
-            MessageContent::Text(t) => t.clone(),
-            MessageContent::Array(parts) => {
-                parts.iter()
-                    .filter_map(|p| p.text.clone())
-                    .collect::<Vec<String>>()
-                    .join("\n")
-            }
-        }
+        self.inner.content.extract_text()
     }
 
     /// Expose whether the message has multimodal parts
@@ -160,10 +151,10 @@ impl PyMessage {
         match &self.inner.content {
             MessageContent::Array(parts) => {
                 parts.iter().filter_map(|p| {
-                    match &p.image_url {
-                        Some(ImageSource::Url { url }) => Some(format!("url:{}", url)),
-                        Some(ImageSource::Base64 { media_type, .. }) => Some(format!("base64:{}", media_type)),
-                        None => None,
+                    match p {
+                        MessagePart::Image(ImagePart { source: ImageSource::Url { url }, .. }) => Some(format!("url:{}", url)),
+                        MessagePart::Image(ImagePart { source: ImageSource::Base64 { media_type, .. }, .. }) => Some(format!("base64:{}", media_type)),
+                        _ => None,
                     }
                 }).collect()
             }
@@ -179,6 +170,14 @@ pub struct PyLLMClient {
     provider: LLM,
 }
 
+#[cfg(feature = "python")]
+impl PyLLMClient {
+    /// Lets a Rust extension module hand Python a client for any `LLM`, including `LLM::custom`.
+    pub fn from_llm(provider: LLM) -> Self {
+        Self { provider }
+    }
+}
+
 #[cfg(feature = "python")]
 #[pymethods]
 impl PyLLMClient {
@@ -1,23 +1,39 @@
 // src/llama_server.rs
-use reqwest::Client;
-use std::env;
 use std::time::Duration;
-use dotenv::dotenv;
 
 use futures::stream::{BoxStream, StreamExt};
 use async_stream::stream;
-use crate::errors::{GeneralError, with_retry};
-use crate::structs::general::{ Message, MessageContent, LlmResponse, LlmChunk };
-use crate::structs::openai::{ChatCompletion, EmbeddingRequest};
-use crate::openai::StreamResponse;
-use crate::models::openai::{APIResponse, ErrorResponse, EmbeddingResponse};
+use serde_json::{json, Value};
+use crate::errors::{LlmError, LlmRetryPolicy, with_policy_retry};
+use crate::structs::general::{ Message, MessageContent, LlmResponse, LlmChunk, ToolCall, ToolCallAssembler, Usage, FinishReason };
+use crate::structs::openai::{ChatCompletion, EmbeddingRequest, OpenAITool, map_tool_choice_to_openai};
+use crate::openai::{StreamResponse, StreamSummary, stream_choice_to_chunks, map_openai_finish_reason, check_chat_completion_parts};
+use crate::models::openai::{APIResponse, EmbeddingResponse};
 use crate::structs::llama_server::{LlamaCompletionRequest, LlamaCompletionResponse};
-    
-use crate::config::LlmConfig;
+use crate::config::{check_config, with_warnings, LlmConfig};
+use crate::client::{LlmClient, RequestBuilderExt};
+use crate::normalize::normalize_messages;
+use crate::media::prepare_media;
+use crate::llm::LLM;
+use crate::structured::openai_response_format;
+
+/// Resolves the endpoint URL; `LlmConfig::server_url` overrides the client's base URL for one call.
+fn endpoint(client: &LlmClient, config: Option<&LlmConfig>, path: &str) -> String {
+    match config.and_then(|cfg| cfg.server_url.as_deref()) {
+        Some(server_url) => format!("{}{}", server_url.trim_end_matches('/'), path),
+        None => client.url(path),
+    }
+}
+
+/// Whether to prepend the `<|think|>` switch: llama-server templates only know on and off.
+fn thinking_enabled(cfg: &LlmConfig) -> bool {
+    cfg.reasoning_budget().is_some_and(|budget| budget != 0)
+}
 
-fn get_server_url() -> String {
-    dotenv().ok(); 
-    env::var("LLAMA_SERVER_URL").unwrap_or_else(|_| "http://192.168.0.91:8080".to_string())
+/// The chat template's own thinking switch, sent whenever reasoning is configured so that
+/// models which think by default can be turned off.
+fn chat_template_kwargs(cfg: &LlmConfig) -> Option<Value> {
+    cfg.reasoning_budget().map(|budget| json!({ "enable_thinking": budget != 0 }))
 }
 
 /// Helper function to manually extract Gemma 4 or DeepSeek reasoning tags 
@@ -59,20 +75,28 @@ pub async fn call_llama_openai_compat(
     messages: Vec<Message>,
     model: Option<&str>, 
     config: Option<&LlmConfig>,
-) -> Result<LlmResponse, Box<dyn std::error::Error + Send + Sync>> {
-    let base_url = if let Some(cfg) = config {
-        cfg.server_url.clone().unwrap_or_else(get_server_url)
-    } else {
-        get_server_url()
-    };
-    let url = format!("{}/v1/chat/completions", base_url);
+) -> Result<LlmResponse, LlmError> {
+    let client = LlmClient::from_env(LLM::LlamaServer)?;
+    call_llama_openai_compat_with_client(&client, messages, model, config).await
+}
+
+pub async fn call_llama_openai_compat_with_client(
+    client: &LlmClient,
+    messages: Vec<Message>,
+    model: Option<&str>, 
+    config: Option<&LlmConfig>,
+) -> Result<LlmResponse, LlmError> {
+    let (config, warnings) = check_config(config, &LLM::LlamaServer, model)?;
+    let config = config.as_ref();
+    let url = endpoint(client, config, "/v1/chat/completions");
     let model_name = model.unwrap_or("gemma-4-26b").to_string();
 
-    let mut processed_messages = messages;
+    let mut processed_messages = prepare_media(client, normalize_messages(messages, &LLM::LlamaServer)?, config).await?;
+    check_chat_completion_parts(&processed_messages, "llama-server", false)?;
 
     // --- Inject <|think|> trigger for Gemma 4 ---
     if let Some(cfg) = config {
-        if cfg.thinking_budget.is_some() {
+        if thinking_enabled(cfg) {
             let has_system = processed_messages.first().map(|m| m.role == "system").unwrap_or(false);
             if has_system {
                 // Prepend to existing system message
@@ -103,8 +127,17 @@ pub async fn call_llama_openai_compat(
         top_k: None,
         top_p: None,
         cache_prompt: None,
+        seed: None,
+        user: None,
+        service_tier: None,
         response_format: None,
         max_completion_tokens: None,
+        tools: None,
+        tool_choice: None,
+        stream_options: None,
+        chat_template_kwargs: None,
+        openai_options: None,
+        llama_options: None,
     };
 
     if let Some(cfg) = config {
@@ -114,84 +147,91 @@ pub async fn call_llama_openai_compat(
         request_body.stop = cfg.stop.clone();
         request_body.top_k = cfg.top_k;
         request_body.top_p = cfg.top_p;
+        request_body.seed = cfg.seed;
         request_body.cache_prompt = cfg.cache_prompt;
-        request_body.response_format = cfg.json_schema.clone();
+        request_body.response_format = cfg.json_schema.as_ref().map(openai_response_format);
+        // Tool calling requires llama-server to be started with `--jinja`
+        request_body.tools = cfg.tools.as_ref().map(|tools| tools.iter().map(OpenAITool::from).collect());
+        request_body.tool_choice = cfg.tool_choice.as_ref().map(map_tool_choice_to_openai);
+        request_body.chat_template_kwargs = chat_template_kwargs(cfg);
+        request_body.llama_options = cfg.llama_options.clone();
     }
 
-    let client = Client::new();
-
-    with_retry(|| async {
+    with_policy_retry(|| async {
         let res = client
-            .post(&url)
-            .json(&request_body)
+            .post_url(&url)?
+            .json_with_extras(&request_body, config)?
             .send()
             .await
-            .map_err(|e| {
-                Box::new(GeneralError {
-                    message: format!("Failed to send request to Llama Server: {}", e),
-                }) as Box<dyn std::error::Error + Send + Sync>
+            .map_err(|e| LlmError::Network {
+                message: format!("Failed to send request to Llama Server: {}", e),
             })?;
 
         let status = res.status();
         let rspns_strng = res.text().await.unwrap_or_default();
 
         if !status.is_success() {
-            return Err(Box::new(GeneralError {
-                message: format!("Llama Server returned HTTP {}: {}", status, rspns_strng),
-            }) as Box<dyn std::error::Error + Send + Sync>);
+            return Err(LlmError::from_openai(status.as_u16(), &rspns_strng, None));
         }
 
         // eprintln!("Raw Llama Server Response: {}", rspns_strng);
 
         match serde_json::from_str::<APIResponse>(&rspns_strng) {
             Ok(api_response) => {
-                let message = &api_response.choices[0].message;
-                let raw_text = &message.content;
+                let choice = &api_response.choices[0];
+                let message = &choice.message;
+                let raw_text = message.content.clone().unwrap_or_default();
                 
                 // If the server native-parsed it, use it. Otherwise, run our manual fallback parser!
                 let (final_text, final_reasoning) = if let Some(reasoning) = &message.reasoning_content {
-                    (raw_text.clone(), Some(reasoning.clone()))
+                    (raw_text, Some(reasoning.clone()))
                 } else {
-                    parse_raw_reasoning(raw_text)
+                    parse_raw_reasoning(&raw_text)
                 };
 
                 Ok(LlmResponse { 
                     text: final_text, 
-                    reasoning: final_reasoning 
+                    reasoning: final_reasoning,
+                    tool_calls: message.tool_calls.clone().unwrap_or_default().into_iter().map(ToolCall::from).collect(),
+                    usage: api_response.usage.map(Usage::from),
+                    finish_reason: choice.finish_reason.as_deref().map(map_openai_finish_reason),
+                    warnings: warnings.clone(),
+                    thinking: Vec::new(),
                 })
             },
-            Err(_) => {
-                match serde_json::from_str::<ErrorResponse>(&rspns_strng) {
-                    Ok(err) => Err(Box::new(GeneralError {
-                        message: format!("Llama Server API Error: {}", err.error.message),
-                    }) as Box<dyn std::error::Error + Send + Sync>),
-                    Err(e) => Err(Box::new(GeneralError {
-                        message: format!("Failed to parse JSON response: {} - Raw: {}", e, rspns_strng),
-                    }) as Box<dyn std::error::Error + Send + Sync>),
-                }
-            }
+            Err(e) => Err(LlmError::Parse {
+                message: format!("Failed to parse JSON response: {} - Raw: {}", e, rspns_strng),
+            }),
         }
-    }, 3, Duration::from_secs(1)).await
+    }, LlmRetryPolicy { max_retries: 3, initial_delay: Duration::from_secs(1) }).await
 }
 
 pub async fn call_llama_stream(
     messages: Vec<Message>,
     model: Option<&str>,
     config: Option<&LlmConfig>,
-) -> Result<BoxStream<'static, Result<LlmChunk, Box<dyn std::error::Error + Send + Sync>>>, Box<dyn std::error::Error + Send + Sync>> {
-    let base_url = if let Some(cfg) = config {
-        cfg.server_url.clone().unwrap_or_else(get_server_url)
-    } else {
-        get_server_url()
-    };
-    let url = format!("{}/v1/chat/completions", base_url);
+) -> Result<BoxStream<'static, Result<LlmChunk, LlmError>>, LlmError> {
+    let client = LlmClient::from_env(LLM::LlamaServer)?;
+    call_llama_stream_with_client(&client, messages, model, config).await
+}
+
+pub async fn call_llama_stream_with_client(
+    client: &LlmClient,
+    messages: Vec<Message>,
+    model: Option<&str>,
+    config: Option<&LlmConfig>,
+) -> Result<BoxStream<'static, Result<LlmChunk, LlmError>>, LlmError> {
+    let (config, warnings) = check_config(config, &LLM::LlamaServer, model)?;
+    let config = config.as_ref();
+    let url = endpoint(client, config, "/v1/chat/completions");
     let model_name = model.unwrap_or("gemma-4-26b").to_string();
 
-    let mut processed_messages = messages;
+    let mut processed_messages = prepare_media(client, normalize_messages(messages, &LLM::LlamaServer)?, config).await?;
+    check_chat_completion_parts(&processed_messages, "llama-server", false)?;
 
     // Inject <|think|> trigger for Gemma 4 if budget is set
     if let Some(cfg) = config {
-        if cfg.thinking_budget.is_some() {
+        if thinking_enabled(cfg) {
             let has_system = processed_messages.first().map(|m| m.role == "system").unwrap_or(false);
             if has_system {
                 let first = processed_messages.first_mut().unwrap();
@@ -213,6 +253,7 @@ pub async fn call_llama_stream(
         model: model_name, 
         messages: processed_messages,
         stream: Some(true), // MUST be true
+        stream_options: Some(json!({ "include_usage": true })),
         ..Default::default() // Ensure ChatCompletion derives Default or fill fields
     };
 
@@ -222,15 +263,20 @@ pub async fn call_llama_stream(
         request_body.stop = cfg.stop.clone();
         request_body.top_k = cfg.top_k;
         request_body.top_p = cfg.top_p;
-        request_body.response_format = cfg.json_schema.clone();
+        request_body.seed = cfg.seed;
+        request_body.response_format = cfg.json_schema.as_ref().map(openai_response_format);
+        request_body.tools = cfg.tools.as_ref().map(|tools| tools.iter().map(OpenAITool::from).collect());
+        request_body.tool_choice = cfg.tool_choice.as_ref().map(map_tool_choice_to_openai);
+        request_body.chat_template_kwargs = chat_template_kwargs(cfg);
+        request_body.llama_options = cfg.llama_options.clone();
     }
 
-    let client = Client::new();
-    let res = client.post(&url).json(&request_body).send().await?;
+    let res = client.post_url(&url)?.json_with_extras(&request_body, config)?.send().await?;
 
-    if !res.status().is_success() {
+    let status = res.status();
+    if !status.is_success() {
         let err_text = res.text().await?;
-        return Err(Box::new(GeneralError { message: format!("Llama Server Stream Error: {}", err_text) }));
+        return Err(LlmError::from_openai(status.as_u16(), &err_text, None));
     }
 
     let byte_stream = res.bytes_stream();
@@ -238,6 +284,8 @@ pub async fn call_llama_stream(
     let output_stream = stream! {
         let mut buffer = String::new();
         let mut bytes_stream = byte_stream;
+        let mut tool_calls = ToolCallAssembler::default();
+        let mut summary = StreamSummary::default();
 
         while let Some(item) = bytes_stream.next().await {
             match item {
@@ -247,48 +295,58 @@ pub async fn call_llama_stream(
                         let line = buffer.drain(..newline_idx + 1).collect::<String>().trim().to_string();
                         if line.is_empty() { continue; }
                         if line == "data: [DONE]" {
-                            yield Ok(LlmChunk::Done);
+                            for chunk in tool_calls.finish() { yield Ok(chunk); }
+                            yield Ok(summary.into_chunk());
                             return; 
                         }
-                        if line.starts_with("data: ") {
-                            let json_str = &line[6..];
+                        if let Some(json_str) = line.strip_prefix("data: ") {
                             if let Ok(parsed) = serde_json::from_str::<StreamResponse>(json_str) {
                                 if let Some(choice) = parsed.choices.first() {
-                                    if let Some(content) = &choice.delta.content {
-                                        yield Ok(LlmChunk::Text(content.clone()));
-                                    }
-                                    if let Some(reasoning) = &choice.delta.reasoning_content {
-                                        yield Ok(LlmChunk::Reasoning(reasoning.clone()));
+                                    for chunk in stream_choice_to_chunks(choice, &mut tool_calls) {
+                                        yield Ok(chunk);
                                     }
                                 }
+                                summary.observe(parsed);
                             }
                         }
                     }
                 }
-                Err(e) => yield Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>),
+                Err(e) => yield Err(LlmError::StreamInterrupted { message: e.to_string() }),
             }
         }
+        // The connection closed without `[DONE]`: finish pending tool calls and end anyway
+        for chunk in tool_calls.finish() { yield Ok(chunk); }
+        yield Ok(summary.into_chunk());
     };
 
-    Ok(Box::pin(output_stream))
+    Ok(with_warnings(Box::pin(output_stream), warnings))
 }
 
 pub async fn call_llama_legacy(
     prompt: String,
     model: Option<&str>,
     config: Option<&LlmConfig>,
-) -> Result<LlmResponse, Box<dyn std::error::Error + Send + Sync>> { // <-- RETURN TYPE UPDATED
-    let base_url = get_server_url();
-    let url = format!("{}/completion", base_url);
+) -> Result<LlmResponse, LlmError> { // <-- RETURN TYPE UPDATED
+    let client = LlmClient::from_env(LLM::LlamaServer)?;
+    call_llama_legacy_with_client(&client, prompt, model, config).await
+}
+
+pub async fn call_llama_legacy_with_client(
+    client: &LlmClient,
+    prompt: String,
+    model: Option<&str>,
+    config: Option<&LlmConfig>,
+) -> Result<LlmResponse, LlmError> {
+    let (config, warnings) = check_config(config, &LLM::LlamaServer, model)?;
+    let config = config.as_ref();
+    let url = endpoint(client, config, "/completion");
 
     let mut processed_prompt = prompt;
 
     // --- Inject <|think|> trigger for Gemma 4 ---
     if let Some(cfg) = config {
-        if cfg.thinking_budget.is_some() {
-            if !processed_prompt.starts_with("<|think|>") {
-                processed_prompt = format!("<|think|>\n{}", processed_prompt);
-            }
+        if thinking_enabled(cfg) && !processed_prompt.starts_with("<|think|>") {
+            processed_prompt = format!("<|think|>\n{}", processed_prompt);
         }
     }
 
@@ -302,7 +360,9 @@ pub async fn call_llama_legacy(
         stream: None,
         stop: None,
         cache_prompt: None,
+        seed: None,
         image_data: None,
+        llama_options: None,
     };
 
     // Extract images if MessageContent is present (for future-proofing/compatibility)
@@ -313,38 +373,32 @@ pub async fn call_llama_legacy(
         request_body.temperature = cfg.temperature;
         request_body.top_k = cfg.top_k;
         request_body.top_p = cfg.top_p;
+        request_body.seed = cfg.seed;
         request_body.stream = cfg.stream;
         request_body.stop = cfg.stop.clone();
         request_body.cache_prompt = cfg.cache_prompt;
+        request_body.llama_options = cfg.llama_options.clone();
     }
 
-    let client = Client::new();
-
-    with_retry(|| async {
+    with_policy_retry(|| async {
         let res = client
-            .post(&url)
-            .json(&request_body)
+            .post_url(&url)?
+            .json_with_extras(&request_body, config)?
             .send()
             .await
-            .map_err(|e| {
-                Box::new(GeneralError {
-                    message: format!("Failed to send request to Llama Server (Legacy): {}", e),
-                }) as Box<dyn std::error::Error + Send + Sync>
+            .map_err(|e| LlmError::Network {
+                message: format!("Failed to send request to Llama Server (Legacy): {}", e),
             })?;
 
         let status = res.status();
         let rspns_strng = res.text().await.unwrap_or_default();
 
         if !status.is_success() {
-            return Err(Box::new(GeneralError {
-                message: format!("Llama Server returned HTTP {}: {}", status, rspns_strng),
-            }) as Box<dyn std::error::Error + Send + Sync>);
+            return Err(LlmError::from_openai(status.as_u16(), &rspns_strng, None));
         }
 
-        let parsed: LlamaCompletionResponse = serde_json::from_str(&rspns_strng).map_err(|e| {
-            Box::new(GeneralError {
-                message: format!("Failed to parse legacy JSON: {} - Raw: {}", e, rspns_strng),
-            }) as Box<dyn std::error::Error + Send + Sync>
+        let parsed: LlamaCompletionResponse = serde_json::from_str(&rspns_strng).map_err(|e| LlmError::Parse {
+            message: format!("Failed to parse legacy JSON: {} - Raw: {}", e, rspns_strng),
         })?;
 
         // --- Run the manual fallback parser on legacy output! ---
@@ -353,8 +407,22 @@ pub async fn call_llama_legacy(
         Ok(LlmResponse {
             text: final_text,
             reasoning: final_reasoning,
+            tool_calls: Vec::new(),
+            usage: parsed.tokens_evaluated.map(|prompt_tokens| Usage {
+                prompt_tokens,
+                completion_tokens: parsed.tokens_predicted.unwrap_or_default(),
+                reasoning_tokens: None,
+                cached_input_tokens: parsed.tokens_cached,
+            }),
+            finish_reason: parsed.stop_type.as_deref().map(|stop_type| match stop_type {
+                "eos" | "word" => FinishReason::Stop,
+                "limit" => FinishReason::Length,
+                other => FinishReason::Other(other.to_string()),
+            }),
+            warnings: warnings.clone(),
+            thinking: Vec::new(),
         })
-    }, 3, Duration::from_secs(1)).await
+    }, LlmRetryPolicy { max_retries: 3, initial_delay: Duration::from_secs(1) }).await
 }
 
 pub async fn call_llama_embeddings(
@@ -362,22 +430,20 @@ pub async fn call_llama_embeddings(
     model: Option<&str>,
     dimensions: Option<u32>,
     config: Option<&LlmConfig>, // Added this
-) -> Result<Vec<f32>, Box<dyn std::error::Error + Send + Sync>> {
-    
-    // IP OVERRIDE LOGIC:
-    // 1. Check if config provides a specific URL
-    // 2. If not, fall back to the default get_server_url()
-    let base_url = if let Some(cfg) = config {
-        // Assuming LlmConfig has a field 'server_url'
-        // If it doesn't, we'd use: cfg.server_url.clone().unwrap_or_else(|| get_server_url())
-        cfg.server_url.clone().unwrap_or_else(get_server_url)
-    } else {
-        get_server_url()
-    };
+) -> Result<Vec<f32>, LlmError> {
+    let client = LlmClient::from_env(LLM::LlamaServer)?;
+    call_llama_embeddings_with_client(&client, input, model, dimensions, config).await
+}
 
-    let url = format!("{}/v1/embeddings", base_url);
-    let client = Client::new();
-    
+pub async fn call_llama_embeddings_with_client(
+    client: &LlmClient,
+    input: String,
+    model: Option<&str>,
+    dimensions: Option<u32>,
+    config: Option<&LlmConfig>,
+) -> Result<Vec<f32>, LlmError> {
+    // `LlmConfig::server_url` overrides the client's base URL for this call
+    let url = endpoint(client, config, "/v1/embeddings");
 
     let embedding_request = EmbeddingRequest {
         model: model.unwrap_or("embedder").to_string(),
@@ -386,25 +452,21 @@ pub async fn call_llama_embeddings(
         encoding_format: "float".to_string(),
     };
 
-    with_retry(|| async {
+    with_policy_retry(|| async {
         let res = client
-            .post(&url)
+            .post_url(&url)?
             .json(&embedding_request)
             .send()
             .await
-            .map_err(|e| {
-                Box::new(GeneralError {
-                    message: format!("Failed to send request to Llama Server Embeddings: {}", e),
-                }) as Box<dyn std::error::Error + Send + Sync>
+            .map_err(|e| LlmError::Network {
+                message: format!("Failed to send request to Llama Server Embeddings: {}", e),
             })?;
 
         let status = res.status();
         let rspns_strng = res.text().await.unwrap_or_default();
 
         if !status.is_success() {
-            return Err(Box::new(GeneralError {
-                message: format!("Llama Server returned HTTP {}: {}", status, rspns_strng),
-            }) as Box<dyn std::error::Error + Send + Sync>);
+            return Err(LlmError::from_openai(status.as_u16(), &rspns_strng, None));
         }
 
         match serde_json::from_str::<EmbeddingResponse>(&rspns_strng) {
@@ -412,15 +474,15 @@ pub async fn call_llama_embeddings(
                 if let Some(data) = api_response.data.first() {
                     Ok(data.embedding.clone())
                 } else {
-                    Err(Box::new(GeneralError {
+                    Err(LlmError::Parse {
                         message: "No embedding data found in Llama Server response".to_string(),
-                    }) as Box<dyn std::error::Error + Send + Sync>)
+                    })
                 }
             },
-            Err(e) => Err(Box::new(GeneralError {
+            Err(e) => Err(LlmError::Parse {
                 message: format!("Failed to parse Llama embedding response: {} - Raw: {}", e, rspns_strng),
-            }) as Box<dyn std::error::Error + Send + Sync>),
+            }),
         }
-    }, 3, Duration::from_secs(1)).await
+    }, LlmRetryPolicy { max_retries: 3, initial_delay: Duration::from_secs(1) }).await
 }
     
@@ -1,19 +1,106 @@
 // src/llm.rs
 use async_trait::async_trait;
-use crate::openai::call_gpt;
-use crate::gemini::{call_gemini, conversation_gemini_call, get_gemini_model_info, list_gemini_models, count_gemini_tokens, gemini_to_llm_response};
-use crate::anthropic::call_anthropic;
+use crate::gemini::{get_gemini_model_info_with_client, list_gemini_models_with_client, count_gemini_tokens_with_client, gemini_to_llm_response};
 use crate::models::gemini::ModelInfo;
-use crate::errors::GeneralError;
+use crate::errors::LlmError;
 use futures::stream::BoxStream;
-use crate::structs::general::{Message, MessageContent, Content, Part, LlmResponse, LlmChunk}; 
-use crate::config::LlmConfig;
+use crate::structs::general::{Message, MessageContent, LlmResponse, LlmChunk};
+use crate::config::{check_config, with_warnings, ConfigOption, LlmConfig};
+use crate::client::LlmClient;
+use crate::provider::{Capabilities, Provider};
+use std::sync::Arc;
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum LLM {
     OpenAI,
     Gemini,
     Anthropic,
     LlamaServer,
+    /// Any server speaking the OpenAI chat-completions protocol (Groq, DeepSeek, Mistral,
+    /// OpenRouter, Together, vLLM, LM Studio, ...).
+    OpenAICompatible {
+        /// API root that `/chat/completions` and `/embeddings` are appended to,
+        /// e.g. `https://api.groq.com/openai/v1`.
+        base_url: String,
+        /// Environment variable holding the bearer token; `None` for servers without auth.
+        api_key_env: Option<String>,
+        /// Extra headers sent with every request (e.g. OpenRouter's `HTTP-Referer`).
+        headers: Vec<(String, String)>,
+    },
+    /// A backend supplied by another crate; build it with `LLM::custom`.
+    Custom(Arc<dyn Provider>),
+}
+
+impl LLM {
+    /// Wraps a user-implemented `Provider` so it can be used wherever an `LLM` is accepted.
+    pub fn custom(provider: Box<dyn Provider>) -> Self {
+        LLM::Custom(Arc::from(provider))
+    }
+
+    /// What this crate supports for the provider.
+    pub fn capabilities(&self) -> Capabilities {
+        match self {
+            LLM::OpenAI => Capabilities {
+                chat: true, streaming: true, embeddings: true, tools: true, vision: true, reasoning: true,
+                list_models: false, count_tokens: false,
+            },
+            LLM::Gemini => Capabilities {
+                chat: true, streaming: true, embeddings: true, tools: true, vision: true, reasoning: true,
+                list_models: true, count_tokens: true,
+            },
+            LLM::Anthropic => Capabilities {
+                chat: true, streaming: true, embeddings: false, tools: true, vision: true, reasoning: true,
+                list_models: false, count_tokens: false,
+            },
+            // Depends on the model loaded and the server's flags; assume the common case
+            LLM::LlamaServer | LLM::OpenAICompatible { .. } => Capabilities {
+                chat: true, streaming: true, embeddings: true, tools: true, vision: true, reasoning: true,
+                list_models: false, count_tokens: false,
+            },
+            LLM::Custom(provider) => provider.capabilities(),
+        }
+    }
+
+    /// Whether the provider honors `option`. Custom providers are assumed to handle every option.
+    pub fn supports(&self, option: ConfigOption) -> bool {
+        use ConfigOption::*;
+        match self {
+            // Honored everywhere; listed only for the settings they conflict with
+            _ if matches!(option, Temperature | TopP | ToolChoice | JsonSchema) => true,
+            // OpenAI keeps reasoning tokens hidden
+            LLM::OpenAI => matches!(option, Stream | Seed | UserId | ServiceTier | ReasoningEffort),
+            // Compatible servers commonly accept `top_k` and return `reasoning_content`
+            LLM::OpenAICompatible { .. } => matches!(
+                option,
+                Stream | TopK | Seed | UserId | ServiceTier | ReasoningEffort | IncludeThoughts
+            ),
+            LLM::Gemini => matches!(
+                option,
+                ThinkingBudget | GroundingWithSearch | TopK | CandidateCount | Seed | ReasoningEffort | ReasoningBudget | IncludeThoughts
+            ),
+            LLM::Anthropic => matches!(
+                option,
+                ThinkingBudget | TopK | UserId | ServiceTier | ReasoningEffort | ReasoningBudget | IncludeThoughts
+            ),
+            LLM::LlamaServer => matches!(
+                option,
+                ThinkingBudget | ServerUrl | Stream | CachePrompt | TopK | Seed | ReasoningEffort | ReasoningBudget | IncludeThoughts
+            ),
+            LLM::Custom(_) => true,
+        }
+    }
+
+    /// Name used in error messages and warnings.
+    pub fn name(&self) -> &str {
+        match self {
+            LLM::OpenAI => "OpenAI",
+            LLM::Gemini => "Gemini",
+            LLM::Anthropic => "Anthropic",
+            LLM::LlamaServer => "llama-server",
+            LLM::OpenAICompatible { .. } => "OpenAI-compatible server",
+            LLM::Custom(provider) => provider.name(),
+        }
+    }
 }
 
 #[async_trait]
@@ -23,50 +110,114 @@ pub trait Access {
         content: MessageContent,
         model: Option<&str>,
         config: Option<&LlmConfig>,
-    ) -> Result<LlmResponse, Box<dyn std::error::Error + Send + Sync>>;
+    ) -> Result<LlmResponse, LlmError>;
     async fn send_convo_message(
         &self,
         messages: Vec<Message>,
         model: Option<&str>,
         config: Option<&LlmConfig>,
-    ) -> Result<LlmResponse, Box<dyn std::error::Error + Send + Sync>>;
+    ) -> Result<LlmResponse, LlmError>;
 
     async fn send_streaming_convo_message(
         &self,
         messages: Vec<Message>,
         model: Option<&str>,
         config: Option<&LlmConfig>,
-    ) -> Result<BoxStream<'static, Result<LlmChunk, Box<dyn std::error::Error + Send + Sync>>>, Box<dyn std::error::Error + Send + Sync>>;
+    ) -> Result<BoxStream<'static, Result<LlmChunk, LlmError>>, LlmError>;
 
     async fn get_model_info(
         &self,
         model: &str,
-    ) -> Result<ModelInfo, Box<dyn std::error::Error + Send + Sync>>;
+    ) -> Result<ModelInfo, LlmError>;
     async fn list_models(&self)
-        -> Result<Vec<ModelInfo>, Box<dyn std::error::Error + Send + Sync>>;
+        -> Result<Vec<ModelInfo>, LlmError>;
     async fn count_tokens(
         &self,
         text: &str,
         model: &str,
-    ) -> Result<u32, Box<dyn std::error::Error + Send + Sync>>;
+    ) -> Result<u32, LlmError>;
     async fn embed(
         &self,
         text: &str,
         model: Option<&str>,
         dimensions: Option<u32>,
         config: Option<&LlmConfig>,
-    ) -> Result<Vec<f32>, Box<dyn std::error::Error + Send + Sync>>;
+    ) -> Result<Vec<f32>, LlmError>;
 }
 
+/// Each call builds an `LlmClient` from the environment, so credentials are re-read every time.
+/// These clients share one process-wide connection pool. Hold an `LlmClient` to supply
+/// credentials explicitly.
 #[async_trait]
 impl Access for LLM {
+    async fn send_single_message(
+        &self,
+        content: MessageContent,
+        model: Option<&str>,
+        config: Option<&LlmConfig>,
+    ) -> Result<LlmResponse, LlmError> {
+        LlmClient::from_env(self.clone())?.send_single_message(content, model, config).await
+    }
+
+    async fn send_convo_message(
+        &self,
+        messages: Vec<Message>,
+        model: Option<&str>,
+        config: Option<&LlmConfig>,
+    ) -> Result<LlmResponse, LlmError> {
+        LlmClient::from_env(self.clone())?.send_convo_message(messages, model, config).await
+    }
+
+    async fn send_streaming_convo_message(
+        &self,
+        messages: Vec<Message>,
+        model: Option<&str>,
+        config: Option<&LlmConfig>,
+    ) -> Result<BoxStream<'static, Result<LlmChunk, LlmError>>, LlmError> {
+        LlmClient::from_env(self.clone())?.send_streaming_convo_message(messages, model, config).await
+    }
+
+    async fn get_model_info(
+        &self,
+        model: &str,
+    ) -> Result<ModelInfo, LlmError> {
+        LlmClient::from_env(self.clone())?.get_model_info(model).await
+    }
+
+    async fn list_models(
+        &self,
+    ) -> Result<Vec<ModelInfo>, LlmError> {
+        LlmClient::from_env(self.clone())?.list_models().await
+    }
+
+    async fn count_tokens(
+        &self,
+        text: &str,
+        model: &str,
+    ) -> Result<u32, LlmError> {
+        LlmClient::from_env(self.clone())?.count_tokens(text, model).await
+    }
+
+    async fn embed(
+        &self,
+        text: &str,
+        model: Option<&str>,
+        dimensions: Option<u32>,
+        config: Option<&LlmConfig>,
+    ) -> Result<Vec<f32>, LlmError> {
+        LlmClient::from_env(self.clone())?.embed(text, model, dimensions, config).await
+    }
+}
+
+#[async_trait]
+impl Access for LlmClient {
     
     async fn send_single_message(
         &self,
         content: MessageContent,
         model: Option<&str>,
         config: Option<&LlmConfig>,
-    ) -> Result<LlmResponse, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<LlmResponse, LlmError> {
         let messages = vec![Message { role: "user".to_string(), content }];
         self.send_convo_message(messages, model, config).await
     }
@@ -76,23 +227,24 @@ impl Access for LLM {
         messages: Vec<Message>,
         model: Option<&str>,
         config: Option<&LlmConfig>,
-    ) -> Result<LlmResponse, Box<dyn std::error::Error + Send + Sync>> {
-        match self {
-            LLM::OpenAI => call_gpt(messages, model, config).await,
+    ) -> Result<LlmResponse, LlmError> {
+        match self.provider() {
+            LLM::OpenAI | LLM::OpenAICompatible { .. } => crate::openai::call_gpt_with_client(self, messages, model, config).await,
             LLM::Gemini => {
-                let gemini_messages: Vec<Content> = messages
-                    .into_iter()
-                    .map(|msg| Content {
-                        role: msg.role,
-                        parts: crate::gemini::api::call_gemini::map_message_parts_to_gemini(msg.content.as_parts()),
-                    })
-                    .collect();
-
-                let gemini_response = conversation_gemini_call(gemini_messages, model, config).await?;
-                gemini_to_llm_response(gemini_response)
+                let (gemini_response, warnings) = crate::gemini::api::send_gemini(self, messages, model, config).await?;
+                let mut response = gemini_to_llm_response(gemini_response)?;
+                response.warnings.extend(warnings);
+                Ok(response)
+            }
+            LLM::Anthropic => crate::anthropic::call_anthropic_with_client(self, messages, model, config).await,
+            LLM::LlamaServer => crate::llama_server::call_llama_openai_compat_with_client(self, messages, model, config).await,
+            // Built-in providers check the config themselves
+            LLM::Custom(provider) => {
+                let (config, warnings) = check_config(config, self.provider(), model)?;
+                let mut response = provider.chat(messages, model, config.as_ref()).await?;
+                response.warnings.extend(warnings);
+                Ok(response)
             }
-            LLM::Anthropic => call_anthropic(messages, model, config).await, 
-            LLM::LlamaServer => crate::llama_server::call_llama_openai_compat(messages, model, config).await,
         }
     }
 
@@ -101,35 +253,41 @@ impl Access for LLM {
         messages: Vec<Message>,
         model: Option<&str>,
         config: Option<&LlmConfig>,
-    ) -> Result<BoxStream<'static, Result<LlmChunk, Box<dyn std::error::Error + Send + Sync>>>, Box<dyn std::error::Error + Send + Sync>> {
-        match self {
-            LLM::OpenAI => crate::openai::call_gpt_stream(messages, model, config).await,
-            LLM::LlamaServer => crate::llama_server::call_llama_stream(messages, model, config).await,
-            LLM::Anthropic => crate::anthropic::call_anthropic_stream(messages, model, config).await,
-            LLM::Gemini => crate::gemini::api::call_gemini_stream(messages, model, config).await,
+    ) -> Result<BoxStream<'static, Result<LlmChunk, LlmError>>, LlmError> {
+        match self.provider() {
+            LLM::OpenAI | LLM::OpenAICompatible { .. } => crate::openai::call_gpt_stream_with_client(self, messages, model, config).await,
+            LLM::LlamaServer => crate::llama_server::call_llama_stream_with_client(self, messages, model, config).await,
+            LLM::Anthropic => crate::anthropic::call_anthropic_stream_with_client(self, messages, model, config).await,
+            LLM::Gemini => crate::gemini::api::call_gemini_stream_with_client(self, messages, model, config).await,
+            LLM::Custom(provider) => {
+                let (config, warnings) = check_config(config, self.provider(), model)?;
+                Ok(with_warnings(provider.stream(messages, model, config.as_ref()).await?, warnings))
+            }
         }
     }
 
     async fn get_model_info(
         &self,
         model: &str,
-        ) -> Result<ModelInfo, Box<dyn std::error::Error + Send + Sync>> {
-        match self {
-            LLM::Gemini => get_gemini_model_info(model).await,
-            _ => Err(Box::new(GeneralError {
-                message: format!("Currently only Gemini is implemented for get_model_info func"),
-            }) as Box<dyn std::error::Error + Send + Sync>),
+        ) -> Result<ModelInfo, LlmError> {
+        match self.provider() {
+            LLM::Gemini => get_gemini_model_info_with_client(self, model).await,
+            LLM::Custom(provider) => provider.get_model_info(model).await,
+            _ => Err(LlmError::Unsupported {
+                message: "Currently only Gemini is implemented for get_model_info func".to_string(),
+            }),
         }
     }
 
     async fn list_models(
         &self,
-    ) -> Result<Vec<ModelInfo>, Box<dyn std::error::Error + Send + Sync>> {
-        match self {
-            LLM::Gemini => list_gemini_models().await,
-            _ => Err(Box::new(GeneralError {
-                message: format!("Currently only Gemini is implemented for list_models func"),
-            }) as Box<dyn std::error::Error + Send + Sync>),
+    ) -> Result<Vec<ModelInfo>, LlmError> {
+        match self.provider() {
+            LLM::Gemini => list_gemini_models_with_client(self).await,
+            LLM::Custom(provider) => provider.list_models().await,
+            _ => Err(LlmError::Unsupported {
+                message: "Currently only Gemini is implemented for list_models func".to_string(),
+            }),
         }
     }
 
@@ -137,12 +295,13 @@ impl Access for LLM {
         &self,
         text: &str,
         model: &str,
-    ) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
-        match self {
-            LLM::Gemini => count_gemini_tokens(text, model).await,
-            _ => Err(Box::new(GeneralError {
-                message: format!("Currently only Gemini is implemented for count_tokens func"),
-            }) as Box<dyn std::error::Error + Send + Sync>),
+    ) -> Result<u32, LlmError> {
+        match self.provider() {
+            LLM::Gemini => count_gemini_tokens_with_client(self, text, model).await,
+            LLM::Custom(provider) => provider.count_tokens(text, model).await,
+            _ => Err(LlmError::Unsupported {
+                message: "Currently only Gemini is implemented for count_tokens func".to_string(),
+            }),
         }
     }
 
@@ -152,21 +311,22 @@ impl Access for LLM {
         model: Option<&str>,
         dimensions: Option<u32>,
         config: Option<&LlmConfig>,
-    ) -> Result<Vec<f32>, Box<dyn std::error::Error + Send + Sync>> {
-        match self {
-            LLM::OpenAI => {
-                crate::openai::get_embedding(text.to_string(), model, dimensions, config).await
+    ) -> Result<Vec<f32>, LlmError> {
+        match self.provider() {
+            LLM::OpenAI | LLM::OpenAICompatible { .. } => {
+                crate::openai::get_embedding_with_client(self, text.to_string(), model, dimensions, config).await
             }
             LLM::LlamaServer => {
-                crate::llama_server::call_llama_embeddings(text.to_string(), model, dimensions, config).await
+                crate::llama_server::call_llama_embeddings_with_client(self, text.to_string(), model, dimensions, config).await
             }
             LLM::Gemini => {
-                crate::gemini::call_gemini_embeddings(text.to_string(), model, dimensions, config).await
+                crate::gemini::call_gemini_embeddings_with_client(self, text.to_string(), model, dimensions, config).await
             }
+            LLM::Custom(provider) => provider.embed(text, model, dimensions, config).await,
             LLM::Anthropic => {
-                Err(Box::new(GeneralError {
+                Err(LlmError::Unsupported {
                     message: "Anthropic embeddings not yet implemented in Access trait".into(),
-                }) as Box<dyn std::error::Error + Send + Sync>)
+                })
             }
         }
     }
@@ -0,0 +1,384 @@
+// src/media.rs
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::header::CONTENT_TYPE;
+use std::path::Path;
+
+use crate::client::{shared_http, LlmClient};
+use crate::config::LlmConfig;
+use crate::errors::LlmError;
+use crate::llm::LLM;
+use crate::structs::general::{DocumentPart, ImagePart, MediaSource, Message, MessageContent, MessagePart};
+
+/// Detects a MIME type from the leading bytes of a file.
+pub fn sniff_mime_type(bytes: &[u8]) -> Option<&'static str> {
+    let mime = match bytes {
+        [0x89, b'P', b'N', b'G', ..] => "image/png",
+        [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+        [b'G', b'I', b'F', b'8', ..] => "image/gif",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => "audio/wav",
+        [b'%', b'P', b'D', b'F', ..] => "application/pdf",
+        [b'I', b'D', b'3', ..] | [0xFF, 0xFB | 0xF3 | 0xF2, ..] => "audio/mpeg",
+        [b'O', b'g', b'g', b'S', ..] => "audio/ogg",
+        [b'f', b'L', b'a', b'C', ..] => "audio/flac",
+        [0x1A, 0x45, 0xDF, 0xA3, ..] => "video/webm",
+        [_, _, _, _, b'f', b't', b'y', b'p', b0, b1, b2, b3, ..] => iso_media_type(&[*b0, *b1, *b2, *b3])?,
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// The MIME type for an ISO base media file (`ftyp` box) by its major brand. Unknown brands
+/// give `None`, so the caller falls back to the `Content-Type` header or the extension.
+fn iso_media_type(major_brand: &[u8; 4]) -> Option<&'static str> {
+    let mime = match major_brand {
+        b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" | b"mif1" | b"msf1" => "image/heic",
+        b"avif" | b"avis" => "image/avif",
+        b"M4A " | b"M4B " => "audio/mp4",
+        b"qt  " => "video/quicktime",
+        b"isom" | b"iso2" | b"iso4" | b"iso5" | b"iso6" | b"mp41" | b"mp42" | b"avc1" | b"dash" | b"M4V " => "video/mp4",
+        [b'3', b'g', b'p', _] => "video/3gpp",
+        [b'3', b'g', b'2', _] => "video/3gpp2",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// Guesses a MIME type from the extension of a file name or URL.
+pub fn mime_type_from_extension(path: &str) -> Option<&'static str> {
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let (_, extension) = file_name.rsplit_once('.')?;
+    let mime = match extension.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "heic" => "image/heic",
+        "avif" => "image/avif",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "csv" => "text/csv",
+        "wav" => "audio/wav",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "flac" => "audio/flac",
+        "m4a" => "audio/mp4",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        "3gp" => "video/3gpp",
+        "webm" => "video/webm",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+impl MessagePart {
+    /// Builds an image part from raw bytes, detecting the MIME type from the magic bytes.
+    pub fn image_from_bytes(bytes: &[u8]) -> Result<MessagePart, LlmError> {
+        match MessagePart::from_bytes(bytes)? {
+            image @ MessagePart::Image(_) => Ok(image),
+            other => Err(LlmError::InvalidRequest {
+                message: format!("Expected image data but found {}", other.kind()),
+            }),
+        }
+    }
+
+    /// Reads an image file into an inline image part.
+    pub fn image_from_path(path: impl AsRef<Path>) -> Result<MessagePart, LlmError> {
+        match MessagePart::from_path(path.as_ref())? {
+            image @ MessagePart::Image(_) => Ok(image),
+            other => Err(LlmError::InvalidRequest {
+                message: format!("Expected an image at {} but found {}", path.as_ref().display(), other.kind()),
+            }),
+        }
+    }
+
+    /// Builds an image, audio, video or document part from raw bytes, picked by the MIME type
+    /// detected from the magic bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<MessagePart, LlmError> {
+        let media_type = sniff_mime_type(bytes).ok_or_else(|| LlmError::InvalidRequest {
+            message: "Could not detect the MIME type of the data".to_string(),
+        })?;
+        Ok(part_for(media_type, bytes))
+    }
+
+    /// Reads a file into an inline part. The MIME type comes from the file's magic bytes,
+    /// then its extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<MessagePart, LlmError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| LlmError::InvalidRequest {
+            message: format!("Failed to read {}: {}", path.display(), e),
+        })?;
+        let media_type = sniff_mime_type(&bytes)
+            .or_else(|| mime_type_from_extension(&path.to_string_lossy()))
+            .ok_or_else(|| LlmError::InvalidRequest {
+                message: format!("Could not detect the MIME type of {}", path.display()),
+            })?;
+        Ok(part_for(media_type, &bytes))
+    }
+}
+
+fn part_for(media_type: &str, bytes: &[u8]) -> MessagePart {
+    let source = MediaSource::Base64 { media_type: media_type.to_string(), data: STANDARD.encode(bytes) };
+    part_for_source(media_type, source)
+}
+
+/// Wraps `source` in the part type its MIME type calls for; anything that is not an image,
+/// audio or video becomes a document.
+pub(crate) fn part_for_source(media_type: &str, source: MediaSource) -> MessagePart {
+    match media_type.split('/').next() {
+        Some("image") => MessagePart::Image(ImagePart::new(source)),
+        Some("audio") => MessagePart::Audio(source),
+        Some("video") => MessagePart::Video(source),
+        _ => MessagePart::Document(DocumentPart::new(source)),
+    }
+}
+
+/// The largest image a provider accepts without rejecting or downscaling it server-side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageLimits {
+    /// Longest allowed edge, in pixels.
+    pub max_dimension: u32,
+    /// Largest allowed width times height.
+    pub max_pixels: u64,
+    /// Largest allowed encoded size, in bytes.
+    pub max_bytes: usize,
+}
+
+impl ImageLimits {
+    pub fn for_provider(provider: &LLM) -> ImageLimits {
+        match provider {
+            // Anthropic downscales anything past ~1.15 megapixels and rejects files over 5 MB
+            LLM::Anthropic => ImageLimits { max_dimension: 1568, max_pixels: 1_150_000, max_bytes: 5 * 1024 * 1024 },
+            LLM::Gemini => ImageLimits { max_dimension: 3072, max_pixels: 3072 * 3072, max_bytes: 20 * 1024 * 1024 },
+            _ => ImageLimits { max_dimension: 2048, max_pixels: 2048 * 2048, max_bytes: 20 * 1024 * 1024 },
+        }
+    }
+}
+
+/// Downscales and re-encodes a base64 image until it fits within `limits`.
+///
+/// Images that already fit, and URL sources, are returned unchanged. Images with an alpha
+/// channel stay PNG; everything else becomes JPEG at the highest quality that fits.
+#[cfg(feature = "image")]
+pub fn fit_image(source: &MediaSource, limits: &ImageLimits) -> Result<MediaSource, LlmError> {
+    use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, GenericImageView, ImageFormat};
+    use std::io::Cursor;
+
+    let MediaSource::Base64 { data, .. } = source else {
+        return Ok(source.clone());
+    };
+    let bytes = STANDARD.decode(MediaSource::strip_base64_prefix(data)).map_err(|e| LlmError::InvalidRequest {
+        message: format!("Invalid base64 image data: {}", e),
+    })?;
+    let img = match image::load_from_memory(&bytes) {
+        Ok(img) => img,
+        // Formats the image crate cannot decode are left for the provider to judge
+        Err(_) if bytes.len() <= limits.max_bytes => return Ok(source.clone()),
+        Err(e) => return Err(LlmError::InvalidRequest { message: format!("Could not decode image to fit it: {}", e) }),
+    };
+
+    let (width, height) = img.dimensions();
+    let pixels = width as u64 * height as u64;
+    let mut scale = (limits.max_dimension as f64 / width.max(height) as f64)
+        .min((limits.max_pixels as f64 / pixels as f64).sqrt())
+        .min(1.0);
+    if scale == 1.0 && bytes.len() <= limits.max_bytes {
+        return Ok(source.clone());
+    }
+
+    let encode_err = |e: image::ImageError| LlmError::InvalidRequest { message: format!("Could not encode image: {}", e) };
+    let has_alpha = img.color().has_alpha();
+    for _ in 0..8 {
+        let resized = if scale < 1.0 {
+            let w = ((width as f64 * scale).floor() as u32).max(1);
+            let h = ((height as f64 * scale).floor() as u32).max(1);
+            img.resize(w, h, FilterType::Lanczos3)
+        } else {
+            img.clone()
+        };
+
+        if has_alpha {
+            let mut out = Vec::new();
+            resized.write_to(&mut Cursor::new(&mut out), ImageFormat::Png).map_err(encode_err)?;
+            if out.len() <= limits.max_bytes {
+                return Ok(MediaSource::Base64 { media_type: "image/png".to_string(), data: STANDARD.encode(&out) });
+            }
+        } else {
+            let rgb = DynamicImage::ImageRgb8(resized.to_rgb8());
+            for quality in [85, 70, 55, 40] {
+                let mut out = Vec::new();
+                rgb.write_with_encoder(JpegEncoder::new_with_quality(&mut out, quality)).map_err(encode_err)?;
+                if out.len() <= limits.max_bytes {
+                    return Ok(MediaSource::Base64 { media_type: "image/jpeg".to_string(), data: STANDARD.encode(&out) });
+                }
+            }
+        }
+        scale *= 0.75;
+    }
+
+    Err(LlmError::InvalidRequest {
+        message: format!("Could not shrink image below {} bytes", limits.max_bytes),
+    })
+}
+
+/// The largest file `inline_media_urls` downloads: Anthropic's 32 MB request limit, the highest
+/// of any provider that takes inline media.
+pub const MAX_INLINE_MEDIA_BYTES: usize = 32 * 1024 * 1024;
+
+/// Downloads every `MediaSource::Url` part and replaces it with inline base64 data, for
+/// endpoints that cannot fetch URLs themselves.
+///
+/// The MIME type comes from the file's magic bytes, then the `Content-Type` header, then the
+/// URL's extension. Files over `MAX_INLINE_MEDIA_BYTES` are rejected. This uses the crate's shared
+/// connection pool; `inline_media_urls_with_client` uses an `LlmClient`'s, with its timeouts.
+pub async fn inline_media_urls(messages: Vec<Message>) -> Result<Vec<Message>, LlmError> {
+    let http = shared_http();
+    inline_with(messages, |url| http.get(url)).await
+}
+
+/// Like `inline_media_urls`, on the client's pooled connections and with its timeouts.
+/// Downloads are sent without the client's credentials or headers.
+pub async fn inline_media_urls_with_client(client: &LlmClient, messages: Vec<Message>) -> Result<Vec<Message>, LlmError> {
+    inline_with(messages, |url| client.get_external(url)).await
+}
+
+async fn inline_with(messages: Vec<Message>, get: impl Fn(&str) -> reqwest::RequestBuilder) -> Result<Vec<Message>, LlmError> {
+    let mut inlined = Vec::with_capacity(messages.len());
+
+    for msg in messages {
+        let content = match msg.content {
+            MessageContent::Array(parts) => {
+                let mut new_parts = Vec::with_capacity(parts.len());
+                for part in parts {
+                    new_parts.push(match part {
+                        MessagePart::Image(ImagePart { source: MediaSource::Url { url }, detail }) => {
+                            MessagePart::Image(ImagePart { source: download(get(&url), &url, MAX_INLINE_MEDIA_BYTES).await?, detail })
+                        }
+                        MessagePart::Audio(MediaSource::Url { url }) => {
+                            MessagePart::Audio(download(get(&url), &url, MAX_INLINE_MEDIA_BYTES).await?)
+                        }
+                        MessagePart::Video(MediaSource::Url { url }) => {
+                            MessagePart::Video(download(get(&url), &url, MAX_INLINE_MEDIA_BYTES).await?)
+                        }
+                        MessagePart::Document(DocumentPart { source: MediaSource::Url { url }, title, context, citations }) => {
+                            let source = download(get(&url), &url, MAX_INLINE_MEDIA_BYTES).await?;
+                            MessagePart::Document(DocumentPart { source, title, context, citations })
+                        }
+                        other => other,
+                    });
+                }
+                MessageContent::Array(new_parts)
+            }
+            text => text,
+        };
+        inlined.push(Message { role: msg.role, content });
+    }
+
+    Ok(inlined)
+}
+
+/// Prepares media parts before a provider maps the messages: `data:` URIs become base64
+/// sources, then `LlmConfig::inline_media_urls` and `LlmConfig::fit_images` are applied.
+pub(crate) async fn prepare_media(client: &LlmClient, messages: Vec<Message>, config: Option<&LlmConfig>) -> Result<Vec<Message>, LlmError> {
+    let messages = map_sources(messages, |_, source| Ok(match source {
+        MediaSource::Url { url } if url.starts_with("data:") => MediaSource::from_data_uri(&url).ok_or_else(|| {
+            LlmError::InvalidRequest { message: "Only base64 data: URIs are supported".to_string() }
+        })?,
+        other => other,
+    }))?;
+
+    let messages = match config.and_then(|c| c.inline_media_urls) {
+        Some(true) => inline_media_urls_with_client(client, messages).await?,
+        _ => messages,
+    };
+
+    match config.and_then(|c| c.fit_images) {
+        Some(true) => fit_images(messages, client.provider()),
+        _ => Ok(messages),
+    }
+}
+
+#[cfg(feature = "image")]
+fn fit_images(messages: Vec<Message>, provider: &LLM) -> Result<Vec<Message>, LlmError> {
+    let limits = ImageLimits::for_provider(provider);
+    map_sources(messages, |is_image, source| match is_image {
+        true => fit_image(&source, &limits),
+        false => Ok(source),
+    })
+}
+
+#[cfg(not(feature = "image"))]
+fn fit_images(_messages: Vec<Message>, _provider: &LLM) -> Result<Vec<Message>, LlmError> {
+    Err(LlmError::Unsupported {
+        message: "LlmConfig::fit_images needs the `image` feature".to_string(),
+    })
+}
+
+/// Applies `f` to the source of every media part; the flag is true for images.
+fn map_sources(
+    messages: Vec<Message>,
+    mut f: impl FnMut(bool, MediaSource) -> Result<MediaSource, LlmError>,
+) -> Result<Vec<Message>, LlmError> {
+    messages.into_iter().map(|msg| {
+        let content = match msg.content {
+            MessageContent::Array(parts) => MessageContent::Array(parts.into_iter().map(|part| Ok(match part {
+                MessagePart::Image(ImagePart { source, detail }) => MessagePart::Image(ImagePart { source: f(true, source)?, detail }),
+                MessagePart::Audio(source) => MessagePart::Audio(f(false, source)?),
+                MessagePart::Video(source) => MessagePart::Video(f(false, source)?),
+                MessagePart::Document(document) => MessagePart::Document(DocumentPart { source: f(false, document.source)?, ..document }),
+                other => other,
+            })).collect::<Result<Vec<_>, LlmError>>()?),
+            text => text,
+        };
+        Ok(Message { role: msg.role, content })
+    }).collect()
+}
+
+/// Fetches `url` through `request` as an inline source, failing once the body passes `max_bytes`.
+pub(crate) async fn download(request: reqwest::RequestBuilder, url: &str, max_bytes: usize) -> Result<MediaSource, LlmError> {
+    let too_large = || LlmError::InvalidRequest {
+        message: format!("{} is larger than the {} byte limit for inline media", url, max_bytes),
+    };
+    let mut res = request.send().await.map_err(|e| LlmError::Network {
+        message: format!("Failed to download {}: {}", url, e),
+    })?;
+
+    let status = res.status();
+    if !status.is_success() {
+        return Err(LlmError::InvalidRequest {
+            message: format!("Failed to download {}: HTTP {}", url, status.as_u16()),
+        });
+    }
+    if res.content_length().is_some_and(|length| length > max_bytes as u64) {
+        return Err(too_large());
+    }
+
+    let header_type = res.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(';').next().unwrap_or(v).trim().to_string())
+        .filter(|v| !v.is_empty() && v != "application/octet-stream");
+    // The length header is optional, so count while reading too
+    let mut bytes = Vec::new();
+    while let Some(chunk) = res.chunk().await? {
+        if bytes.len() + chunk.len() > max_bytes {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    let media_type = sniff_mime_type(&bytes)
+        .map(str::to_string)
+        .or(header_type)
+        .or_else(|| mime_type_from_extension(url).map(str::to_string))
+        .ok_or_else(|| LlmError::InvalidRequest {
+            message: format!("Could not detect the MIME type of {}", url),
+        })?;
+
+    Ok(MediaSource::Base64 { media_type, data: STANDARD.encode(&bytes) })
+}
@@ -18,4 +18,34 @@ pub struct ModelInfo {
 #[derive(Deserialize, Serialize, Debug)]
 pub struct ListModelsResponse {
     pub models: Vec<ModelInfo>,
-}
\ No newline at end of file
+}
+/// A file stored with the Gemini Files API.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiFile {
+    /// Resource name, e.g. "files/abc123".
+    pub name: String,
+    pub display_name: Option<String>,
+    pub mime_type: Option<String>,
+    /// int64 encoded as a string.
+    pub size_bytes: Option<String>,
+    pub create_time: Option<String>,
+    pub expiration_time: Option<String>,
+    /// The URI that `fileData.fileUri` references.
+    pub uri: Option<String>,
+    /// `PROCESSING`, `ACTIVE` or `FAILED`.
+    pub state: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ListFilesResponse {
+    #[serde(default)]
+    pub files: Vec<GeminiFile>,
+    pub next_page_token: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct UploadFileResponse {
+    pub file: GeminiFile,
+}
@@ -1,20 +1,58 @@
 // src/models/openai.rs
 use serde::Deserialize;
+use crate::structs::openai::OpenAIToolCall;
+use crate::structs::general::Usage;
 
 #[derive(Debug, Deserialize)]
 pub struct APIMessage {
-    pub content: String,
+    // null when the model only returned tool calls
+    #[serde(default)]
+    pub content: Option<String>,
     pub reasoning_content: Option<String>, // Added for reasoning support
+    pub reasoning: Option<String>, // OpenRouter (and newer vLLM) name for `reasoning_content`
+    pub tool_calls: Option<Vec<OpenAIToolCall>>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct APIChoice {
     pub message: APIMessage,
+    pub finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct APIResponse {
     pub choices: Vec<APIChoice>,
+    pub usage: Option<APIUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct APIUsage {
+    pub prompt_tokens: u32,
+    #[serde(default)]
+    pub completion_tokens: u32,
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PromptTokensDetails {
+    pub cached_tokens: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompletionTokensDetails {
+    pub reasoning_tokens: Option<u32>,
+}
+
+impl From<APIUsage> for Usage {
+    fn from(usage: APIUsage) -> Self {
+        Usage {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            reasoning_tokens: usage.completion_tokens_details.and_then(|d| d.reasoning_tokens),
+            cached_input_tokens: usage.prompt_tokens_details.and_then(|d| d.cached_tokens),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,11 +81,11 @@ pub struct ErrorResponse {
     pub error: ErrorDetails,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct ErrorDetails {
     pub message: String,
-    r#type: String,
-    param: Option<String>,
-    code: String,
+    pub r#type: Option<String>,
+    pub param: Option<String>,
+    // OpenAI sends a string (or null); llama-server sends the numeric HTTP status
+    pub code: Option<serde_json::Value>,
 }
\ No newline at end of file
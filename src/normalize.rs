@@ -0,0 +1,125 @@
+// src/normalize.rs
+use crate::errors::LlmError;
+use crate::llm::LLM;
+use crate::structs::general::{Message, MessageContent, MessagePart};
+
+/// Rewrites a conversation into the shape `provider` accepts. Every provider call runs this
+/// first, so one `Vec<Message>` can be sent anywhere.
+///
+/// - OpenAI-style providers get `model` renamed to `assistant`, and a message carrying several
+///   tool results is split into one `tool` message per result.
+/// - Anthropic and Gemini get their own assistant role name (`assistant` / `model`). Tool
+///   results move into user turns and consecutive turns with the same role are merged into
+///   one multi-part message. System messages are moved to the front for the provider to lift
+///   into its dedicated field. A conversation that starts with an assistant turn is rejected.
+/// - Signed thinking parts are only kept for Anthropic, which checks them; everyone else has
+///   them removed.
+/// - `LLM::Custom` providers receive the messages unchanged.
+pub fn normalize_messages(messages: Vec<Message>, provider: &LLM) -> Result<Vec<Message>, LlmError> {
+    match provider {
+        LLM::Anthropic => alternate(messages, "assistant"),
+        LLM::Gemini => alternate(messages.into_iter().map(drop_thinking).collect(), "model"),
+        LLM::OpenAI | LLM::LlamaServer | LLM::OpenAICompatible { .. } => Ok(messages
+            .into_iter()
+            .map(drop_thinking)
+            .map(|msg| match msg.role.as_str() {
+                "model" => Message { role: "assistant".to_string(), ..msg },
+                _ => msg,
+            })
+            .flat_map(split_tool_results)
+            .collect()),
+        LLM::Custom(_) => Ok(messages),
+    }
+}
+
+fn drop_thinking(msg: Message) -> Message {
+    match msg.content {
+        MessageContent::Array(parts) if parts.iter().any(|part| matches!(part, MessagePart::Thinking(_))) => {
+            let parts = parts.into_iter().filter(|part| !matches!(part, MessagePart::Thinking(_))).collect();
+            Message { content: MessageContent::Array(parts), ..msg }
+        }
+        _ => msg,
+    }
+}
+
+/// One `tool` message per tool result, followed by the message's other parts, if any.
+fn split_tool_results(msg: Message) -> Vec<Message> {
+    let MessageContent::Array(parts) = &msg.content else {
+        return vec![msg];
+    };
+    let is_result = |part: &MessagePart| matches!(part, MessagePart::ToolResult(_));
+    if parts.len() == 1 || !parts.iter().any(is_result) {
+        return vec![msg];
+    }
+
+    let (results, rest): (Vec<MessagePart>, Vec<MessagePart>) = msg.content.as_parts().into_iter().partition(is_result);
+    let mut split: Vec<Message> = results
+        .into_iter()
+        .map(|result| Message { role: "tool".to_string(), content: MessageContent::Array(vec![result]) })
+        .collect();
+    if !rest.is_empty() {
+        let role = if msg.role == "tool" { "user".to_string() } else { msg.role };
+        split.push(Message { role, content: MessageContent::Array(rest) });
+    }
+    split
+}
+
+fn alternate(messages: Vec<Message>, assistant_role: &str) -> Result<Vec<Message>, LlmError> {
+    let mut system: Vec<Message> = Vec::new();
+    let mut conversation: Vec<Message> = Vec::new();
+    let mut previous = "";
+
+    for msg in messages {
+        let role = match msg.role.as_str() {
+            "system" | "developer" => {
+                system.push(Message { role: "system".to_string(), content: msg.content });
+                continue;
+            }
+            "user" => "user",
+            "assistant" | "model" => "assistant",
+            "tool" | "function" => "tool",
+            other => {
+                return Err(LlmError::InvalidRequest {
+                    message: format!("Unknown message role '{}'", other),
+                })
+            }
+        };
+
+        if role == "tool" && previous != "assistant" && previous != "tool" {
+            return Err(LlmError::InvalidRequest {
+                message: "A tool result must follow the assistant message that called the tool".to_string(),
+            });
+        }
+        previous = role;
+
+        // Both providers expect tool results inside a user turn
+        let wire_role = match role {
+            "assistant" => assistant_role,
+            _ => "user",
+        };
+
+        match conversation.last_mut() {
+            Some(last) if last.role == wire_role => {
+                let mut parts = last.content.as_parts();
+                parts.extend(msg.content.as_parts());
+                last.content = MessageContent::Array(parts);
+            }
+            _ => conversation.push(Message { role: wire_role.to_string(), content: msg.content }),
+        }
+    }
+
+    if conversation.is_empty() {
+        return Err(LlmError::InvalidRequest {
+            message: "The conversation needs at least one user or assistant message".to_string(),
+        });
+    }
+    // Both providers reject a conversation that opens with the model's turn
+    if conversation[0].role == assistant_role {
+        return Err(LlmError::InvalidRequest {
+            message: "The conversation must start with a user message, not an assistant one".to_string(),
+        });
+    }
+
+    system.extend(conversation);
+    Ok(system)
+}
@@ -1,18 +1,21 @@
 // src/openai.rs
-use reqwest::header::{HeaderMap, HeaderValue};
-use reqwest::Client;
-use std::env;
-use dotenv::dotenv;
 
 use futures::stream::{BoxStream, StreamExt};
 use async_stream::stream;
 use serde::Deserialize;
-
-use crate::errors::GeneralError;
-use crate::structs::general::{Message, LlmResponse, LlmChunk}; 
-use crate::structs::openai::{ChatCompletion, EmbeddingRequest};
-use crate::models::openai::{APIResponse, ErrorResponse, EmbeddingResponse};
-use crate::config::LlmConfig; // <-- Import config
+use serde_json::json;
+
+use crate::errors::{LlmError, parse_retry_after};
+use crate::structs::general::{Message, MessageContent, MessagePart, MediaSource, DocumentPart, ImagePart, openai_audio_format, LlmResponse, LlmChunk, ToolCall, ToolCallAssembler, Usage, FinishReason}; 
+use crate::structs::openai::{ChatCompletion, EmbeddingRequest, OpenAITool, map_tool_choice_to_openai};
+use crate::models::openai::{APIResponse, APIUsage, EmbeddingResponse};
+use crate::config::{check_config, with_warnings, LlmConfig, ReasoningEffort}; // <-- Import config
+use crate::client::{LlmClient, RequestBuilderExt};
+use crate::normalize::normalize_messages;
+use crate::media::prepare_media;
+use crate::llm::LLM;
+use crate::options::OpenAiOptions;
+use crate::structured::openai_response_format;
 
 const CHAT_COMPLETION_MODEL: &str = "gpt-4o"; // Updated default
 const EMBEDDING_MODEL: &str = "text-embedding-3-small";
@@ -21,6 +24,37 @@ const EMBEDDING_ENCODING_FORMAT: &str = "float";
 #[derive(Debug, Deserialize)]
 pub struct StreamResponse {
     pub choices: Vec<StreamChoice>,
+    pub model: Option<String>,
+    /// Only set on the final chunk when `stream_options.include_usage` is requested
+    /// (llama-server always sends it).
+    pub usage: Option<APIUsage>,
+}
+
+/// Final metadata gathered while streaming, emitted with `LlmChunk::Done`.
+#[derive(Default)]
+pub(crate) struct StreamSummary {
+    pub usage: Option<Usage>,
+    pub finish_reason: Option<FinishReason>,
+    pub model: Option<String>,
+}
+
+impl StreamSummary {
+    /// Records the metadata carried by one OpenAI-style stream chunk.
+    pub fn observe(&mut self, response: StreamResponse) {
+        if let Some(model) = response.model {
+            self.model = Some(model);
+        }
+        if let Some(usage) = response.usage {
+            self.usage = Some(usage.into());
+        }
+        if let Some(reason) = response.choices.first().and_then(|c| c.finish_reason.as_deref()) {
+            self.finish_reason = Some(map_openai_finish_reason(reason));
+        }
+    }
+
+    pub fn into_chunk(self) -> LlmChunk {
+        LlmChunk::Done { usage: self.usage, finish_reason: self.finish_reason, model: self.model, warnings: Vec::new() }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -33,28 +67,159 @@ pub struct StreamChoice {
 pub struct StreamDelta {
     pub content: Option<String>,
     pub reasoning_content: Option<String>,
+    /// OpenRouter (and newer vLLM) name for `reasoning_content`
+    pub reasoning: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<StreamToolCallDelta>,
+}
+
+/// A fragment of a tool call. `id` and `function.name` only arrive on the first fragment of each `index`.
+#[derive(Debug, Deserialize)]
+pub struct StreamToolCallDelta {
+    pub index: usize,
+    pub id: Option<String>,
+    pub function: Option<StreamFunctionDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StreamFunctionDelta {
+    pub name: Option<String>,
+    pub arguments: Option<String>,
+}
+
+/// OpenAI falls back to `default`; other OpenAI-compatible servers have no common default model.
+fn model_name(client: &LlmClient, model: Option<&str>, default: &str) -> Result<String, LlmError> {
+    match (model, client.provider()) {
+        (Some(model), _) => Ok(model.to_string()),
+        (None, LLM::OpenAICompatible { base_url, .. }) => Err(LlmError::InvalidRequest {
+            message: format!("A model name is required for the OpenAI-compatible provider at {}", base_url),
+        }),
+        (None, _) => Ok(default.to_string()),
+    }
+}
+
+/// OpenAI's o-series reasoning models, which take `max_completion_tokens` and no temperature.
+pub(crate) fn is_reasoning_model(model: &str) -> bool {
+    model.starts_with("o1") || model.starts_with("o3")
+}
+
+/// `openai_options` with `reasoning.effort` as its `reasoning_effort`, unless that is already set.
+/// Effort `Off` has no OpenAI equivalent and is left out.
+fn openai_options_from_config(cfg: &LlmConfig) -> Option<OpenAiOptions> {
+    let effort = match cfg.reasoning.as_ref().and_then(|reasoning| reasoning.effort) {
+        Some(ReasoningEffort::Low) => "low",
+        Some(ReasoningEffort::Medium) => "medium",
+        Some(ReasoningEffort::High) => "high",
+        Some(ReasoningEffort::Off) | None => return cfg.openai_options.clone(),
+    };
+    let mut options = cfg.openai_options.clone().unwrap_or_default();
+    options.reasoning_effort.get_or_insert_with(|| effort.to_string());
+    Some(options)
+}
+
+/// Rejects parts the chat-completions format has no encoding for, before the request is built.
+/// `file` parts for PDFs are only sent when `documents` is set.
+pub(crate) fn check_chat_completion_parts(messages: &[Message], provider: &str, documents: bool) -> Result<(), LlmError> {
+    for msg in messages {
+        let MessageContent::Array(parts) = &msg.content else { continue };
+        for part in parts {
+            match part {
+                MessagePart::Audio(MediaSource::Url { .. }) => {
+                    return Err(LlmError::Unsupported {
+                        message: format!("{} does not accept audio URLs; use LlmConfig::with_inline_media_urls to send them inline", provider),
+                    });
+                }
+                MessagePart::Image(ImagePart { source: MediaSource::File { .. }, .. }) | MessagePart::Audio(MediaSource::File { .. }) => {
+                    return Err(LlmError::Unsupported {
+                        message: format!("{} only accepts uploaded files as document parts", provider),
+                    });
+                }
+                MessagePart::Audio(MediaSource::Base64 { media_type, .. }) if openai_audio_format(media_type).is_none() => {
+                    return Err(LlmError::Unsupported {
+                        message: format!("{} only accepts wav and mp3 audio, not {}", provider, media_type),
+                    });
+                }
+                MessagePart::Document(DocumentPart { source: MediaSource::Base64 { media_type, .. }, .. })
+                    if documents && media_type != "application/pdf" =>
+                {
+                    return Err(LlmError::Unsupported {
+                        message: format!("{} only accepts PDF documents, not {}", provider, media_type),
+                    });
+                }
+                MessagePart::Document(DocumentPart { source: MediaSource::Url { .. }, .. }) if documents => {
+                    return Err(LlmError::Unsupported {
+                        message: format!("{} does not accept document URLs; use LlmConfig::with_inline_media_urls to send them inline", provider),
+                    });
+                }
+                MessagePart::Document(_) if documents => {}
+                MessagePart::Video(_) | MessagePart::Document(_) => return Err(part.unsupported(provider)),
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Maps an OpenAI / llama-server `finish_reason` to the unified enum.
+pub(crate) fn map_openai_finish_reason(reason: &str) -> FinishReason {
+    match reason {
+        "stop" => FinishReason::Stop,
+        "length" => FinishReason::Length,
+        "tool_calls" | "function_call" => FinishReason::ToolCalls,
+        "content_filter" => FinishReason::ContentFilter,
+        other => FinishReason::Other(other.to_string()),
+    }
+}
+
+/// Turns one streamed choice into chunks, shared by OpenAI and llama-server.
+pub(crate) fn stream_choice_to_chunks(choice: &StreamChoice, tool_calls: &mut ToolCallAssembler) -> Vec<LlmChunk> {
+    let mut chunks = Vec::new();
+    if let Some(content) = &choice.delta.content {
+        chunks.push(LlmChunk::Text(content.clone()));
+    }
+    if let Some(reasoning) = choice.delta.reasoning_content.as_ref().or(choice.delta.reasoning.as_ref()) {
+        chunks.push(LlmChunk::Reasoning(reasoning.clone()));
+    }
+    for delta in &choice.delta.tool_calls {
+        let function = delta.function.as_ref();
+        if !tool_calls.is_started(delta.index) {
+            let id = delta.id.clone().unwrap_or_default();
+            let name = function.and_then(|f| f.name.clone()).unwrap_or_default();
+            chunks.push(tool_calls.start(delta.index, id, name, None));
+        }
+        if let Some(arguments) = function.and_then(|f| f.arguments.as_deref()) {
+            chunks.extend(tool_calls.append(delta.index, arguments));
+        }
+    }
+    if choice.finish_reason.is_some() {
+        chunks.extend(tool_calls.finish());
+    }
+    chunks
 }
 
 pub async fn call_gpt_stream(
     messages: Vec<Message>,
     model: Option<&str>,
     config: Option<&LlmConfig>,
-) -> Result<BoxStream<'static, Result<LlmChunk, Box<dyn std::error::Error + Send + Sync>>>, Box<dyn std::error::Error + Send + Sync>> {
-    dotenv().ok();
-
-    let api_key = env::var("OPEN_AI_KEY").expect("OPEN AI KEY not found");
-    let api_org = env::var("OPEN_AI_ORG").unwrap_or_default();
-    let url = "https://api.openai.com/v1/chat/completions";
-
-    let mut headers = HeaderMap::new();
-    headers.insert("authorization", HeaderValue::from_str(&format!("Bearer {}", api_key)).unwrap());
-    if !api_org.is_empty() {
-        headers.insert("OpenAI-Organization", HeaderValue::from_str(&api_org).unwrap());
-    }
+) -> Result<BoxStream<'static, Result<LlmChunk, LlmError>>, LlmError> {
+    let client = LlmClient::from_env(LLM::OpenAI)?;
+    call_gpt_stream_with_client(&client, messages, model, config).await
+}
 
-    let client = Client::builder().default_headers(headers).build()?;
-    let model_name = model.unwrap_or(CHAT_COMPLETION_MODEL).to_string();
-    let is_reasoning_model = model_name.starts_with("o1") || model_name.starts_with("o3");
+pub async fn call_gpt_stream_with_client(
+    client: &LlmClient,
+    messages: Vec<Message>,
+    model: Option<&str>,
+    config: Option<&LlmConfig>,
+) -> Result<BoxStream<'static, Result<LlmChunk, LlmError>>, LlmError> {
+    let model_name = model_name(client, model, CHAT_COMPLETION_MODEL)?;
+    let provider = client.provider();
+    let (config, warnings) = check_config(config, provider, Some(&model_name))?;
+    let config = config.as_ref();
+    let messages = prepare_media(client, normalize_messages(messages, provider)?, config).await?;
+    // Only OpenAI itself takes PDFs as `file` parts
+    check_chat_completion_parts(&messages, provider.name(), matches!(provider, LLM::OpenAI))?;
+    let is_reasoning_model = is_reasoning_model(&model_name);
 
     let mut chat_completion = ChatCompletion {
         model: model_name,
@@ -67,13 +232,24 @@ pub async fn call_gpt_stream(
         top_k: None,
         top_p: None,
         cache_prompt: None,
+        seed: None,
+        user: None,
+        service_tier: None,
         response_format: None,
+        tools: None,
+        tool_choice: None,
+        // Compatible servers often reject unknown fields; they can opt in through `extra_body`
+        stream_options: matches!(provider, LLM::OpenAI).then(|| json!({ "include_usage": true })),
+        chat_template_kwargs: None,
+        openai_options: None,
+        llama_options: None,
     };
 
     // Apply config (Same logic as your existing call_gpt)
     if let Some(cfg) = config {
         if is_reasoning_model {
             chat_completion.max_completion_tokens = cfg.max_tokens;
+            chat_completion.temperature = cfg.temperature.filter(|temp| (temp - 1.0).abs() < f64::EPSILON).map(|_| 1.0);
         } else {
             chat_completion.temperature = cfg.temperature.map(|t| t as f32);
             chat_completion.max_tokens = cfg.max_tokens;
@@ -81,14 +257,22 @@ pub async fn call_gpt_stream(
         chat_completion.stop = cfg.stop.clone();
         chat_completion.top_k = cfg.top_k;
         chat_completion.top_p = cfg.top_p;
-        chat_completion.response_format = cfg.json_schema.clone();
+        chat_completion.seed = cfg.seed;
+        chat_completion.user = cfg.user_id.clone();
+        chat_completion.service_tier = cfg.service_tier.clone();
+        chat_completion.response_format = cfg.json_schema.as_ref().map(openai_response_format);
+        chat_completion.tools = cfg.tools.as_ref().map(|tools| tools.iter().map(OpenAITool::from).collect());
+        chat_completion.tool_choice = cfg.tool_choice.as_ref().map(map_tool_choice_to_openai);
+        chat_completion.openai_options = openai_options_from_config(cfg);
     }
 
-    let res = client.post(url).json(&chat_completion).send().await?;
+    let res = client.post("/chat/completions")?.json_with_extras(&chat_completion, config)?.send().await?;
 
-    if !res.status().is_success() {
+    let status = res.status();
+    if !status.is_success() {
+        let retry_after = parse_retry_after(res.headers());
         let err_text = res.text().await?;
-        return Err(Box::new(GeneralError { message: format!("OpenAI Stream Error: {}", err_text) }));
+        return Err(LlmError::from_openai(status.as_u16(), &err_text, retry_after));
     }
 
     // The magic happens here: we convert the byte stream into an LlmChunk stream
@@ -97,6 +281,8 @@ pub async fn call_gpt_stream(
     let output_stream = stream! {
         let mut buffer = String::new();
         let mut bytes_stream = byte_stream;
+        let mut tool_calls = ToolCallAssembler::default();
+        let mut summary = StreamSummary::default();
 
         while let Some(item) = bytes_stream.next().await {
             match item {
@@ -109,21 +295,19 @@ pub async fn call_gpt_stream(
                         
                         if line.is_empty() { continue; }
                         if line == "data: [DONE]" {
-                            yield Ok(LlmChunk::Done);
+                            for chunk in tool_calls.finish() { yield Ok(chunk); }
+                            yield Ok(summary.into_chunk());
                             return; 
                         }
-                        if line.starts_with("data: ") {
-                            let json_str = &line[6..];
+                        if let Some(json_str) = line.strip_prefix("data: ") {
                             match serde_json::from_str::<StreamResponse>(json_str) {
                                 Ok(parsed) => {
                                     if let Some(choice) = parsed.choices.first() {
-                                        if let Some(content) = &choice.delta.content {
-                                            yield Ok(LlmChunk::Text(content.clone()));
-                                        }
-                                        if let Some(reasoning) = &choice.delta.reasoning_content {
-                                            yield Ok(LlmChunk::Reasoning(reasoning.clone()));
+                                        for chunk in stream_choice_to_chunks(choice, &mut tool_calls) {
+                                            yield Ok(chunk);
                                         }
                                     }
+                                    summary.observe(parsed);
                                 },
                                 Err(_e) => {
                                     // Some lines might be partial or metadata, we can choose to ignore or yield error
@@ -133,52 +317,42 @@ pub async fn call_gpt_stream(
                         }
                     }
                 }
-                Err(e) => yield Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>),
+                Err(e) => yield Err(LlmError::StreamInterrupted { message: e.to_string() }),
             }
         }
+        // The connection closed without `[DONE]`: finish pending tool calls and end anyway
+        for chunk in tool_calls.finish() { yield Ok(chunk); }
+        yield Ok(summary.into_chunk());
     };
 
-    Ok(Box::pin(output_stream))
+    Ok(with_warnings(Box::pin(output_stream), warnings))
 }
 
 pub async fn call_gpt(
     messages: Vec<Message>,
     model: Option<&str>, // <-- Added dynamic model
     config: Option<&LlmConfig>, // <-- Added dynamic config
-) -> Result<LlmResponse, Box<dyn std::error::Error + Send + Sync>> { 
-    dotenv().ok();
-
-    let api_key: String =
-        env::var("OPEN_AI_KEY").expect("OPEN AI KEY not found in environment variables");
-    let api_org: String =
-        env::var("OPEN_AI_ORG").unwrap_or_default(); // Made optional since not everyone uses orgs
-
-    let url: &str = "https://api.openai.com/v1/chat/completions";
-
-    let mut headers: HeaderMap = HeaderMap::new();
-    headers.insert(
-        "authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key))
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?,
-    );
-    
-    if !api_org.is_empty() {
-        headers.insert(
-            "OpenAI-Organization",
-            HeaderValue::from_str(api_org.as_str())
-                .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?,
-        );
-    }
-
-    let client = Client::builder()
-        .default_headers(headers)
-        .build()
-        .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?;
+) -> Result<LlmResponse, LlmError> { 
+    let client = LlmClient::from_env(LLM::OpenAI)?;
+    call_gpt_with_client(&client, messages, model, config).await
+}
 
-    let model_name = model.unwrap_or(CHAT_COMPLETION_MODEL).to_string();
+pub async fn call_gpt_with_client(
+    client: &LlmClient,
+    messages: Vec<Message>,
+    model: Option<&str>,
+    config: Option<&LlmConfig>,
+) -> Result<LlmResponse, LlmError> { 
+    let model_name = model_name(client, model, CHAT_COMPLETION_MODEL)?;
+    let provider = client.provider();
+    let (config, warnings) = check_config(config, provider, Some(&model_name))?;
+    let config = config.as_ref();
+    let messages = prepare_media(client, normalize_messages(messages, provider)?, config).await?;
+    // Only OpenAI itself takes PDFs as `file` parts
+    check_chat_completion_parts(&messages, provider.name(), matches!(provider, LLM::OpenAI))?;
     
     // Check if we are using an OpenAI reasoning model (o1, o3, etc.)
-    let is_reasoning_model = model_name.starts_with("o1") || model_name.starts_with("o3");
+    let is_reasoning_model = is_reasoning_model(&model_name);
 
     let mut chat_completion = ChatCompletion {
         model: model_name,
@@ -191,7 +365,16 @@ pub async fn call_gpt(
         top_k: None,
         top_p: None,
         cache_prompt: None,
+        seed: None,
+        user: None,
+        service_tier: None,
         response_format: None,
+        tools: None,
+        tool_choice: None,
+        stream_options: None,
+        chat_template_kwargs: None,
+        openai_options: None,
+        llama_options: None,
     };
 
     if let Some(cfg) = config {
@@ -214,45 +397,52 @@ pub async fn call_gpt(
         chat_completion.stop = cfg.stop.clone();
         chat_completion.top_k = cfg.top_k;
         chat_completion.top_p = cfg.top_p;
-        chat_completion.response_format = cfg.json_schema.clone();
+        chat_completion.seed = cfg.seed;
+        chat_completion.user = cfg.user_id.clone();
+        chat_completion.service_tier = cfg.service_tier.clone();
+        chat_completion.response_format = cfg.json_schema.as_ref().map(openai_response_format);
+        chat_completion.tools = cfg.tools.as_ref().map(|tools| tools.iter().map(OpenAITool::from).collect());
+        chat_completion.tool_choice = cfg.tool_choice.as_ref().map(map_tool_choice_to_openai);
+        chat_completion.openai_options = openai_options_from_config(cfg);
     }
 
     let res = client
-        .post(url)
-        .json(&chat_completion)
+        .post("/chat/completions")?
+        .json_with_extras(&chat_completion, config)?
         .send()
         .await
-        .map_err(|e| {
-            eprintln!("{:?}", e);
-            Box::new(GeneralError {
-                message: format!("Failed to send request to OpenAI Chat Completion API: {}", e),
-            }) as Box<dyn std::error::Error + Send + Sync>
+        .map_err(|e| LlmError::Network {
+            message: format!("Failed to send request to {} Chat Completion API: {}", provider.name(), e),
         })?;
 
     let status = res.status();
-    let rspns_strng = res.text().await.map_err(|e: reqwest::Error| {
-        Box::new(GeneralError {
-            message: format!("Failed to read response from OpenAI Chat Completion API: {}", e),
-        }) as Box<dyn std::error::Error + Send + Sync>
+    let retry_after = parse_retry_after(res.headers());
+    let rspns_strng = res.text().await.map_err(|e| LlmError::Network {
+        message: format!("Failed to read response from {} Chat Completion API: {}", provider.name(), e),
     })?;
 
     if !status.is_success() {
-        return Err(Box::new(GeneralError {
-            message: format!("OpenAI API Error (HTTP {}): {}", status, rspns_strng),
-        }));
+        return Err(LlmError::from_openai(status.as_u16(), &rspns_strng, retry_after));
     }
 
     match serde_json::from_str::<APIResponse>(&rspns_strng) {
         Ok(api_response) => {
+            let choice = &api_response.choices[0];
+            let message = &choice.message;
             Ok(LlmResponse {
-                text: api_response.choices[0].message.content.clone(),
-                reasoning: api_response.choices[0].message.reasoning_content.clone(),
+                text: message.content.clone().unwrap_or_default(),
+                reasoning: message.reasoning_content.clone().or_else(|| message.reasoning.clone()),
+                tool_calls: message.tool_calls.clone().unwrap_or_default().into_iter().map(ToolCall::from).collect(),
+                usage: api_response.usage.map(Usage::from),
+                finish_reason: choice.finish_reason.as_deref().map(map_openai_finish_reason),
+                warnings,
+                thinking: Vec::new(),
             })
         },
         Err(e) => {
-            Err(Box::new(GeneralError {
-                message: format!("Failed to parse response from OpenAI API: {} - Raw Response: {}", e, rspns_strng),
-            }))
+            Err(LlmError::Parse {
+                message: format!("Failed to parse response from {} API: {} - Raw Response: {}", provider.name(), e, rspns_strng),
+            })
         }
     }
 }
@@ -261,87 +451,57 @@ pub async fn get_embedding(
     input: String,
     model: Option<&str>, // Added model parameter
     dimensions: Option<u32>,
-    _config: Option<&LlmConfig>, // Added, prefixed with _ as it's currently unused
-) -> Result<Vec<f32>, Box<dyn std::error::Error + Send + Sync>> {
-    
-    dotenv().ok();
-
-    let api_key: String =
-        env::var("OPEN_AI_KEY").expect("OPEN AI KEY not found in environment variables");
-    let api_org: String =
-        env::var("OPEN_AI_ORG").unwrap_or_default(); // Changed to unwrap_or_default for robustness
-
-    let url: &str = "https://api.openai.com/v1/embeddings";
-
-    let mut headers: HeaderMap = HeaderMap::new();
-    headers.insert(
-        "authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key))
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?,
-    );
-    
-    if !api_org.is_empty() {
-        headers.insert(
-            "OpenAI-Organization",
-            HeaderValue::from_str(api_org.as_str())
-                .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?,
-        );
-    }
-    headers.insert(
-        "Content-Type",
-        HeaderValue::from_static("application/json"),
-    );
-
-    let client = Client::builder()
-        .default_headers(headers)
-        .build()
-        .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?;
+    config: Option<&LlmConfig>,
+) -> Result<Vec<f32>, LlmError> {
+    let client = LlmClient::from_env(LLM::OpenAI)?;
+    get_embedding_with_client(&client, input, model, dimensions, config).await
+}
 
+pub async fn get_embedding_with_client(
+    client: &LlmClient,
+    input: String,
+    model: Option<&str>,
+    dimensions: Option<u32>,
+    _config: Option<&LlmConfig>, // Currently unused
+) -> Result<Vec<f32>, LlmError> {
     let embedding_request = EmbeddingRequest {
-        model: model.unwrap_or(EMBEDDING_MODEL).to_string(), // Use provided model or default
+        model: model_name(client, model, EMBEDDING_MODEL)?,
         input,
         dimensions,
         encoding_format: EMBEDDING_ENCODING_FORMAT.to_string(),
     };
 
     let res = client
-        .post(url)
+        .post("/embeddings")?
         .json(&embedding_request)
         .send()
         .await
-        .map_err(|e| {
-            eprintln!("{:?}", e);
-            Box::new(GeneralError {
-                message: format!("Failed to send request to OpenAI Embeddings API: {}", e),
-            }) as Box<dyn std::error::Error + Send + Sync>
+        .map_err(|e| LlmError::Network {
+            message: format!("Failed to send request to {} Embeddings API: {}", client.provider().name(), e),
         })?;
 
-    let rspns_strng = res.text().await.map_err(|e: reqwest::Error| {
-        Box::new(GeneralError {
-            message: format!("Failed to read response from OpenAI Embeddings API: {}", e),
-        }) as Box<dyn std::error::Error + Send + Sync>
+    let status = res.status();
+    let retry_after = parse_retry_after(res.headers());
+    let rspns_strng = res.text().await.map_err(|e| LlmError::Network {
+        message: format!("Failed to read response from {} Embeddings API: {}", client.provider().name(), e),
     })?;
 
+    if !status.is_success() {
+        return Err(LlmError::from_openai(status.as_u16(), &rspns_strng, retry_after));
+    }
+
     match serde_json::from_str::<EmbeddingResponse>(&rspns_strng) {
         Ok(api_response) => {
             if let Some(data) = api_response.data.first() {
                 Ok(data.embedding.clone())
             } else {
-                Err(Box::new(GeneralError {
-                    message: "No embedding data found in the OpenAI Embeddings API response".to_string(),
-                }) as Box<dyn std::error::Error + Send + Sync>)
-            }
-        }
-        Err(_) => {
-            match serde_json::from_str::<ErrorResponse>(&rspns_strng) {
-                Ok(err) => Err(Box::new(GeneralError {
-                    message: format!("OpenAI Embeddings API Error: {}", err.error.message),
-                }) as Box<dyn std::error::Error + Send + Sync>),
-                Err(e) => Err(Box::new(GeneralError {
-                    message: format!("Failed to parse error response from OpenAI Embeddings API: {} - Raw Response: {}", e, rspns_strng),
-                }) as Box<dyn std::error::Error + Send + Sync>), // Ensure this also returns a boxed dynamic error
+                Err(LlmError::Parse {
+                    message: format!("No embedding data found in the {} Embeddings API response", client.provider().name()),
+                })
             }
         }
+        Err(e) => Err(LlmError::Parse {
+            message: format!("Failed to parse response from {} Embeddings API: {} - Raw Response: {}", client.provider().name(), e, rspns_strng),
+        }),
     }
 }
-    
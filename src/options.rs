@@ -0,0 +1,192 @@
+// src/options.rs
+use std::collections::BTreeMap;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Chat-completions parameters for OpenAI and OpenAI-compatible servers.
+///
+/// `seed`, `user` and `service_tier` come from `LlmConfig::seed`, `user_id` and `service_tier`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OpenAiOptions {
+    /// `minimal`, `low`, `medium` or `high`, for reasoning models.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    /// Token id to bias from -100 to 100.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logit_bias: Option<BTreeMap<String, i32>>,
+}
+
+impl OpenAiOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_reasoning_effort(mut self, reasoning_effort: impl Into<String>) -> Self {
+        self.reasoning_effort = Some(reasoning_effort.into());
+        self
+    }
+
+    pub fn with_presence_penalty(mut self, presence_penalty: f32) -> Self {
+        self.presence_penalty = Some(presence_penalty);
+        self
+    }
+
+    pub fn with_frequency_penalty(mut self, frequency_penalty: f32) -> Self {
+        self.frequency_penalty = Some(frequency_penalty);
+        self
+    }
+
+    pub fn with_logit_bias(mut self, logit_bias: BTreeMap<String, i32>) -> Self {
+        self.logit_bias = Some(logit_bias);
+        self
+    }
+}
+
+/// Which prefix of an Anthropic request to mark for prompt caching.
+///
+/// The cached prefix runs in the order tools, system prompt, messages. Each strategy places one
+/// `cache_control` breakpoint at the end of the part it names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnthropicCacheStrategy {
+    /// The tool definitions.
+    Tools,
+    /// The tool definitions and the system prompt.
+    System,
+    /// Everything up to the last message, for multi-turn conversations.
+    Conversation,
+}
+
+/// Messages API settings for Anthropic.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnthropicOptions {
+    /// Sent in the `anthropic-beta` header, e.g. `interleaved-thinking-2025-05-14`.
+    pub betas: Vec<String>,
+    /// Merged into the request's `metadata` object, next to `LlmConfig::user_id`.
+    pub metadata: Option<Map<String, Value>>,
+    pub cache_strategy: Option<AnthropicCacheStrategy>,
+}
+
+impl AnthropicOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_beta(mut self, beta: impl Into<String>) -> Self {
+        self.betas.push(beta.into());
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: Map<String, Value>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn with_cache_strategy(mut self, cache_strategy: AnthropicCacheStrategy) -> Self {
+        self.cache_strategy = Some(cache_strategy);
+        self
+    }
+}
+
+/// One entry of Gemini's `safetySettings`, e.g. `HARM_CATEGORY_HARASSMENT` with `BLOCK_ONLY_HIGH`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GeminiSafetySetting {
+    pub category: String,
+    pub threshold: String,
+}
+
+impl GeminiSafetySetting {
+    pub fn new(category: impl Into<String>, threshold: impl Into<String>) -> Self {
+        Self { category: category.into(), threshold: threshold.into() }
+    }
+}
+
+/// `generateContent` settings for Gemini.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GeminiOptions {
+    pub safety_settings: Vec<GeminiSafetySetting>,
+    /// `TEXT`, `IMAGE` or `AUDIO`, for models that can produce them.
+    pub response_modalities: Vec<String>,
+    /// `MEDIA_RESOLUTION_LOW`, `MEDIA_RESOLUTION_MEDIUM` or `MEDIA_RESOLUTION_HIGH`.
+    pub media_resolution: Option<String>,
+}
+
+impl GeminiOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_safety_setting(mut self, category: impl Into<String>, threshold: impl Into<String>) -> Self {
+        self.safety_settings.push(GeminiSafetySetting::new(category, threshold));
+        self
+    }
+
+    pub fn with_response_modalities(mut self, response_modalities: Vec<String>) -> Self {
+        self.response_modalities = response_modalities;
+        self
+    }
+
+    pub fn with_media_resolution(mut self, media_resolution: impl Into<String>) -> Self {
+        self.media_resolution = Some(media_resolution.into());
+        self
+    }
+}
+
+/// Sampling and slot settings for llama-server, on both the chat and the legacy completion
+/// endpoint.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LlamaOptions {
+    /// 0 disables Mirostat, 1 and 2 select the version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirostat: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirostat_tau: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirostat_eta: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f32>,
+    /// Prompt tokens kept when the context fills up; -1 keeps all of them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n_keep: Option<i32>,
+    /// Server slot to run on, to reuse its cached prompt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_slot: Option<i32>,
+}
+
+impl LlamaOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_mirostat(mut self, mirostat: u8, tau: f32, eta: f32) -> Self {
+        self.mirostat = Some(mirostat);
+        self.mirostat_tau = Some(tau);
+        self.mirostat_eta = Some(eta);
+        self
+    }
+
+    pub fn with_repeat_penalty(mut self, repeat_penalty: f32) -> Self {
+        self.repeat_penalty = Some(repeat_penalty);
+        self
+    }
+
+    pub fn with_min_p(mut self, min_p: f32) -> Self {
+        self.min_p = Some(min_p);
+        self
+    }
+
+    pub fn with_n_keep(mut self, n_keep: i32) -> Self {
+        self.n_keep = Some(n_keep);
+        self
+    }
+
+    pub fn with_id_slot(mut self, id_slot: i32) -> Self {
+        self.id_slot = Some(id_slot);
+        self
+    }
+}
@@ -0,0 +1,113 @@
+// src/provider.rs
+use std::fmt;
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
+
+use crate::config::LlmConfig;
+use crate::errors::LlmError;
+use crate::models::gemini::ModelInfo;
+use crate::structs::general::{LlmChunk, LlmResponse, Message};
+
+/// What a provider can do. Callers can check these before sending a request that would
+/// otherwise fail with `LlmError::Unsupported`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    pub chat: bool,
+    pub streaming: bool,
+    pub embeddings: bool,
+    pub tools: bool,
+    pub vision: bool,
+    pub reasoning: bool,
+    pub list_models: bool,
+    pub count_tokens: bool,
+}
+
+/// A chat backend. Implement this to plug a provider that is not built into the crate into
+/// `LLM::custom`, which then works with `Access` and everything built on it.
+///
+/// Only `name`, `capabilities` and `chat` are required. `stream` falls back to replaying the
+/// blocking response as chunks; the other operations return `LlmError::Unsupported`.
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// Short identifier used in error messages and `Debug` output.
+    fn name(&self) -> &str;
+
+    fn capabilities(&self) -> Capabilities;
+
+    async fn chat(
+        &self,
+        messages: Vec<Message>,
+        model: Option<&str>,
+        config: Option<&LlmConfig>,
+    ) -> Result<LlmResponse, LlmError>;
+
+    async fn stream(
+        &self,
+        messages: Vec<Message>,
+        model: Option<&str>,
+        config: Option<&LlmConfig>,
+    ) -> Result<BoxStream<'static, Result<LlmChunk, LlmError>>, LlmError> {
+        let response = self.chat(messages, model, config).await?;
+        Ok(stream::iter(response_to_chunks(response).into_iter().map(Ok)).boxed())
+    }
+
+    async fn embed(
+        &self,
+        _text: &str,
+        _model: Option<&str>,
+        _dimensions: Option<u32>,
+        _config: Option<&LlmConfig>,
+    ) -> Result<Vec<f32>, LlmError> {
+        Err(unsupported(self.name(), "embeddings"))
+    }
+
+    async fn get_model_info(&self, _model: &str) -> Result<ModelInfo, LlmError> {
+        Err(unsupported(self.name(), "get_model_info"))
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LlmError> {
+        Err(unsupported(self.name(), "list_models"))
+    }
+
+    async fn count_tokens(&self, _text: &str, _model: &str) -> Result<u32, LlmError> {
+        Err(unsupported(self.name(), "count_tokens"))
+    }
+}
+
+impl fmt::Debug for dyn Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Provider").field(&self.name()).finish()
+    }
+}
+
+/// Two custom providers are equal only if they are the same instance.
+impl PartialEq for dyn Provider {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
+}
+
+fn unsupported(name: &str, operation: &str) -> LlmError {
+    LlmError::Unsupported {
+        message: format!("{} does not support {}", name, operation),
+    }
+}
+
+/// Replays a blocking response as the chunks a streaming call would have produced.
+fn response_to_chunks(response: LlmResponse) -> Vec<LlmChunk> {
+    let mut chunks = Vec::new();
+    if let Some(reasoning) = response.reasoning {
+        chunks.push(LlmChunk::Reasoning(reasoning));
+    }
+    chunks.extend(response.thinking.into_iter().map(LlmChunk::Thinking));
+    if !response.text.is_empty() {
+        chunks.push(LlmChunk::Text(response.text));
+    }
+    for (index, call) in response.tool_calls.into_iter().enumerate() {
+        chunks.push(LlmChunk::ToolCallStart { index, id: call.id.clone(), name: call.name.clone() });
+        chunks.push(LlmChunk::ToolCallArgumentsDelta { index, delta: call.arguments.to_string() });
+        chunks.push(LlmChunk::ToolCallEnd { index, call });
+    }
+    chunks.push(LlmChunk::Done { usage: response.usage, finish_reason: response.finish_reason, model: None, warnings: response.warnings });
+    chunks
+}
@@ -1,15 +1,135 @@
 // src/structs/general.rs
 use serde::{Deserialize, Serialize, Serializer};
+use crate::errors::LlmError;
+use std::collections::BTreeMap;
+use serde_json::Value;
+
+use crate::structs::openai::OpenAIToolCall;
 
 // --- New Unified Response Type ---
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LlmResponse {
     pub text: String,
     pub reasoning: Option<String>,
+    /// Tool invocations requested by the model. Empty when the model answered with text only.
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+    /// Token counts reported by the provider, if any.
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    /// Why generation stopped. `Length` means the answer was truncated.
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
+    /// Config options that were dropped because the provider cannot honor them.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Signed reasoning blocks, in order. `Message::assistant_response` replays them; Anthropic
+    /// only.
+    #[serde(default)]
+    pub thinking: Vec<ThinkingBlock>,
+}
+
+/// Token usage normalized across providers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    /// Input tokens, including any served from the prompt cache.
+    pub prompt_tokens: u32,
+    /// Output tokens, including `reasoning_tokens`.
+    pub completion_tokens: u32,
+    /// Output tokens spent on reasoning, when the provider reports them separately.
+    pub reasoning_tokens: Option<u32>,
+    /// Input tokens read from the provider's prompt cache.
+    pub cached_input_tokens: Option<u32>,
+}
+
+impl Usage {
+    pub fn total_tokens(&self) -> u32 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// Why the model stopped generating, normalized across providers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FinishReason {
+    /// Natural end of the answer or a stop sequence was hit.
+    Stop,
+    /// The output token limit was reached; the answer is truncated.
+    Length,
+    /// The model stopped to call one or more tools.
+    ToolCalls,
+    /// The output was withheld or cut by a safety filter.
+    ContentFilter,
+    /// Any other provider-specific reason, kept verbatim.
+    Other(String),
+}
+
+// --- Tool / Function Calling ---
+
+/// A tool the model may call, described by a JSON Schema for its arguments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    /// JSON Schema (`{"type": "object", "properties": {...}}`) describing the arguments.
+    pub parameters: Value,
+}
+
+impl ToolDefinition {
+    pub fn new(name: impl Into<String>, description: impl Into<String>, parameters: Value) -> Self {
+        Self { name: name.into(), description: description.into(), parameters }
+    }
+}
+
+/// Controls whether and which tool the model must call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolChoice {
+    /// The model decides whether to call a tool (provider default).
+    Auto,
+    /// The model must not call any tool.
+    None,
+    /// The model must call at least one tool.
+    Required,
+    /// The model must call the named tool.
+    Tool(String),
+}
+
+/// A tool invocation produced by the model.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolCall {
+    /// Provider-assigned call id, echoed back in the matching `ToolResult`.
+    /// Gemini does not always assign one, in which case an id is synthesized.
+    pub id: String,
+    pub name: String,
+    /// Parsed JSON arguments. Falls back to a JSON string if the provider sent invalid JSON.
+    pub arguments: Value,
+    /// Opaque signature (Gemini `thoughtSignature`) that must be sent back with the call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// The output of a tool, sent back to the model in the next request.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolResult {
+    pub tool_call_id: String,
+    /// Name of the tool that produced the result (required by Gemini `functionResponse`).
+    pub name: String,
+    pub content: String,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+/// A reasoning block signed by the provider (Anthropic extended thinking). It must be sent back
+/// unchanged, ahead of the tool calls it led to, when the conversation continues.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ThinkingBlock {
+    Thinking { thinking: String, signature: String },
+    /// Reasoning the provider flagged and encrypted; only `data` is returned.
+    RedactedThinking { data: String },
 }
 
 // --- New Multimodal Support ---
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum MessageContent {
     Text(String),
@@ -37,8 +157,8 @@ impl MessageContent {
             MessageContent::Array(parts) => {
                 // Combine all text parts into a single string
                 parts.iter()
-                    .filter_map(|p| p.text.clone())
-                    .collect::<Vec<String>>()
+                    .filter_map(|p| p.as_text())
+                    .collect::<Vec<&str>>()
                     .join("\n")
             }
         }
@@ -47,63 +167,369 @@ impl MessageContent {
     /// Returns a vector of MessageParts regardless of whether the content is Text or Array.
     pub fn as_parts(&self) -> Vec<MessagePart> {
         match self {
-            MessageContent::Text(text) => vec![MessagePart {
-                r#type: "text".to_string(),
-                text: Some(text.clone()),
-                image_url: None,
-            }],
+            MessageContent::Text(text) => vec![MessagePart::Text(text.clone())],
             MessageContent::Array(parts) => parts.clone(),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// Where a piece of media comes from: a URL the provider fetches, inline base64 data, or a
+/// file uploaded through the provider's Files API.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(tag = "type", rename_all = "snake_case")]
-pub enum ImageSource {
+pub enum MediaSource {
     Url { url: String },
     Base64 { media_type: String, data: String },
+    /// An uploaded file, usually built from a `FileHandle`. `file_id` is the file URI for
+    /// Gemini and the file ID for Anthropic and OpenAI.
+    File { file_id: String, media_type: Option<String> },
 }
 
-impl ImageSource {
-    /// Utility to strip "data:image/<type>;base64," prefix from a base64 string.
+/// Images use the same source type as every other media part.
+pub type ImageSource = MediaSource;
+
+impl MediaSource {
+    /// Utility to strip a "data:<type>;base64," prefix from a base64 string.
     pub fn strip_base64_prefix(data: &str) -> &str {
         if let Some(comma_idx) = data.find(',') {
-            if data.starts_with("data:image/") && data.contains(";base64") {
+            if data.starts_with("data:") && data[..comma_idx].ends_with(";base64") {
                 return &data[comma_idx + 1..];
             }
         }
         data
     }
+
+    /// Parses a base64 `data:` URI such as `data:image/png;base64,iVBOR...`.
+    pub fn from_data_uri(uri: &str) -> Option<MediaSource> {
+        let (header, data) = uri.strip_prefix("data:")?.split_once(',')?;
+        let media_type = header.strip_suffix(";base64")?;
+        let media_type = media_type.split(';').next().unwrap_or(media_type);
+        if media_type.is_empty() {
+            return None;
+        }
+        Some(MediaSource::Base64 { media_type: media_type.to_string(), data: data.to_string() })
+    }
+
+    /// The source as a URL, using a `data:` URI for inline data. Uploaded files have none.
+    pub fn to_url(&self) -> Option<String> {
+        match self {
+            MediaSource::Url { url } => Some(url.clone()),
+            MediaSource::Base64 { media_type, data } => {
+                Some(format!("data:{};base64,{}", media_type, MediaSource::strip_base64_prefix(data)))
+            }
+            MediaSource::File { .. } => None,
+        }
+    }
 }
 
-impl Serialize for ImageSource {
+/// OpenAI's hint for how closely the model should look at an image.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageDetail {
+    Low,
+    High,
+    Auto,
+}
+
+/// An image, with an optional `detail` hint that only OpenAI-style providers read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImagePart {
+    pub source: ImageSource,
+    pub detail: Option<ImageDetail>,
+}
+
+impl ImagePart {
+    pub fn new(source: ImageSource) -> Self {
+        ImagePart { source, detail: None }
+    }
+
+    pub fn with_detail(mut self, detail: ImageDetail) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+}
+
+impl From<ImageSource> for ImagePart {
+    fn from(source: ImageSource) -> Self {
+        ImagePart::new(source)
+    }
+}
+
+/// A document such as a PDF or a plain-text file, with optional metadata for the model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentPart {
+    pub source: MediaSource,
+    /// Document title for Anthropic; the file name for OpenAI.
+    pub title: Option<String>,
+    /// Background about the document that Anthropic reads but never cites.
+    pub context: Option<String>,
+    /// Ask Anthropic to return citations pointing into the document.
+    pub citations: bool,
+}
+
+impl DocumentPart {
+    pub fn new(source: MediaSource) -> Self {
+        DocumentPart { source, title: None, context: None, citations: false }
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_context(mut self, context: impl Into<String>) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+
+    pub fn with_citations(mut self, citations: bool) -> Self {
+        self.citations = citations;
+        self
+    }
+}
+
+/// One piece of a multimodal message.
+///
+/// Serializes to the OpenAI chat-completions content-part shape where there is one, and to a
+/// `{"type": "image" | "audio" | "video" | "document", "source": ...}` shape otherwise, so stored
+/// histories deserialize back. The OpenAI shape keeps only what OpenAI reads: audio MIME types
+/// become `audio/wav` or `audio/mpeg`, and a PDF keeps its title but not its context or citation
+/// flag. Provider mappers return `LlmError::Unsupported` for parts the provider cannot take
+/// rather than dropping them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessagePart {
+    Text(String),
+    Image(ImagePart),
+    Audio(MediaSource),
+    Video(MediaSource),
+    Document(DocumentPart),
+    /// A tool call made by the assistant, replayed in the conversation history.
+    ToolCall(ToolCall),
+    /// The output of a tool call, sent back in a `tool` message.
+    ToolResult(ToolResult),
+    /// Signed reasoning from an earlier assistant turn. Only Anthropic takes it back; other
+    /// providers have it removed by `normalize_messages`.
+    Thinking(ThinkingBlock),
+}
+
+impl MessagePart {
+    pub fn text(text: impl Into<String>) -> Self {
+        MessagePart::Text(text.into())
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            MessagePart::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Short name of the part type, as used in error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MessagePart::Text(_) => "text",
+            MessagePart::Image(_) => "image",
+            MessagePart::Audio(_) => "audio",
+            MessagePart::Video(_) => "video",
+            MessagePart::Document(_) => "document",
+            MessagePart::ToolCall(_) => "tool_call",
+            MessagePart::ToolResult(_) => "tool_result",
+            MessagePart::Thinking(_) => "thinking",
+        }
+    }
+
+    pub(crate) fn unsupported(&self, provider: &str) -> LlmError {
+        LlmError::Unsupported {
+            message: format!("{} does not support {} message parts", provider, self.kind()),
+        }
+    }
+}
+
+/// The `input_audio.format` value for a MIME type; chat completions only take wav and mp3.
+pub(crate) fn openai_audio_format(media_type: &str) -> Option<&'static str> {
+    match media_type {
+        "audio/wav" | "audio/x-wav" | "audio/wave" => Some("wav"),
+        "audio/mpeg" | "audio/mp3" => Some("mp3"),
+        _ => None,
+    }
+}
+
+impl Serialize for MessagePart {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        use serde::ser::SerializeStruct;
-        let mut state = serializer.serialize_struct("ImageSource", 1)?;
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(None)?;
         match self {
-            ImageSource::Url { url } => {
-                state.serialize_field("url", url)?;
+            MessagePart::Text(text) => {
+                map.serialize_entry("type", "text")?;
+                map.serialize_entry("text", text)?;
+            }
+            MessagePart::Image(ImagePart { source, detail }) if source.to_url().is_some() => {
+                let url = source.to_url().unwrap_or_default();
+                map.serialize_entry("type", "image_url")?;
+                let mut image_url = serde_json::json!({ "url": url });
+                if let Some(detail) = detail {
+                    image_url["detail"] = serde_json::json!(detail);
+                }
+                map.serialize_entry("image_url", &image_url)?;
+            }
+            MessagePart::Audio(MediaSource::Base64 { media_type, data }) if openai_audio_format(media_type).is_some() => {
+                let format = openai_audio_format(media_type).unwrap_or_default();
+                map.serialize_entry("type", "input_audio")?;
+                map.serialize_entry("input_audio", &serde_json::json!({
+                    "data": MediaSource::strip_base64_prefix(data),
+                    "format": format,
+                }))?;
             }
-            ImageSource::Base64 { media_type, data } => {
-                let clean_data = ImageSource::strip_base64_prefix(data);
-                let data_uri = format!("data:{};base64,{}", media_type, clean_data);
-                state.serialize_field("url", &data_uri)?;
+            MessagePart::ToolCall(call) => {
+                map.serialize_entry("type", "tool_call")?;
+                map.serialize_entry("tool_call", call)?;
+            }
+            MessagePart::ToolResult(result) => {
+                map.serialize_entry("type", "tool_result")?;
+                map.serialize_entry("tool_result", result)?;
+            }
+            MessagePart::Thinking(ThinkingBlock::Thinking { thinking, signature }) => {
+                map.serialize_entry("type", "thinking")?;
+                map.serialize_entry("thinking", thinking)?;
+                map.serialize_entry("signature", signature)?;
+            }
+            MessagePart::Thinking(ThinkingBlock::RedactedThinking { data }) => {
+                map.serialize_entry("type", "redacted_thinking")?;
+                map.serialize_entry("data", data)?;
+            }
+            MessagePart::Document(DocumentPart { source: MediaSource::Base64 { media_type, data }, title, .. })
+                if media_type == "application/pdf" =>
+            {
+                let clean_data = MediaSource::strip_base64_prefix(data);
+                map.serialize_entry("type", "file")?;
+                map.serialize_entry("file", &serde_json::json!({
+                    "filename": title.as_deref().unwrap_or("document.pdf"),
+                    "file_data": format!("data:{};base64,{}", media_type, clean_data),
+                }))?;
+            }
+            MessagePart::Document(DocumentPart { source: MediaSource::File { file_id, .. }, .. }) => {
+                map.serialize_entry("type", "file")?;
+                map.serialize_entry("file", &serde_json::json!({ "file_id": file_id }))?;
+            }
+            MessagePart::Image(ImagePart { source, detail }) => {
+                map.serialize_entry("type", "image")?;
+                map.serialize_entry("source", source)?;
+                if let Some(detail) = detail {
+                    map.serialize_entry("detail", detail)?;
+                }
+            }
+            MessagePart::Audio(source) | MessagePart::Video(source) => {
+                map.serialize_entry("type", self.kind())?;
+                map.serialize_entry("source", source)?;
+            }
+            MessagePart::Document(DocumentPart { source, title, context, citations }) => {
+                map.serialize_entry("type", "document")?;
+                map.serialize_entry("source", source)?;
+                if let Some(title) = title {
+                    map.serialize_entry("title", title)?;
+                }
+                if let Some(context) = context {
+                    map.serialize_entry("context", context)?;
+                }
+                if *citations {
+                    map.serialize_entry("citations", citations)?;
+                }
             }
         }
-        state.end()
+        map.end()
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct MessagePart {
-    pub r#type: String, // "text" or "image_url"
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub text: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub image_url: Option<ImageSource>,
+/// Every shape `MessagePart` serializes to. `image_url` also takes a tagged `MediaSource`.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StoredPart {
+    Text { text: String },
+    ImageUrl { image_url: StoredImageUrl },
+    InputAudio { input_audio: StoredInputAudio },
+    File { file: StoredFile },
+    ToolCall { tool_call: ToolCall },
+    ToolResult { tool_result: ToolResult },
+    Thinking { thinking: String, signature: String },
+    RedactedThinking { data: String },
+    Image { source: MediaSource, detail: Option<ImageDetail> },
+    Audio { source: MediaSource },
+    Video { source: MediaSource },
+    Document {
+        source: MediaSource,
+        title: Option<String>,
+        context: Option<String>,
+        #[serde(default)]
+        citations: bool,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredImageUrl {
+    Url { url: String, detail: Option<ImageDetail> },
+    Source(MediaSource),
+}
+
+#[derive(Deserialize)]
+struct StoredInputAudio {
+    data: String,
+    format: String,
+}
+
+#[derive(Deserialize)]
+struct StoredFile {
+    filename: Option<String>,
+    file_data: Option<String>,
+    file_id: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for MessagePart {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let part = match StoredPart::deserialize(deserializer)? {
+            StoredPart::Text { text } => MessagePart::Text(text),
+            StoredPart::ImageUrl { image_url: StoredImageUrl::Url { url, detail } } => {
+                let source = MediaSource::from_data_uri(&url).unwrap_or(MediaSource::Url { url });
+                MessagePart::Image(ImagePart { source, detail })
+            }
+            StoredPart::ImageUrl { image_url: StoredImageUrl::Source(source) } => MessagePart::Image(ImagePart::new(source)),
+            StoredPart::InputAudio { input_audio } => {
+                let media_type = match input_audio.format.as_str() {
+                    "wav" => "audio/wav",
+                    "mp3" => "audio/mpeg",
+                    other => return Err(D::Error::custom(format!("unknown input_audio format {}", other))),
+                };
+                MessagePart::Audio(MediaSource::Base64 { media_type: media_type.to_string(), data: input_audio.data })
+            }
+            StoredPart::File { file: StoredFile { file_id: Some(file_id), .. } } => {
+                MessagePart::Document(DocumentPart::new(MediaSource::File { file_id, media_type: None }))
+            }
+            StoredPart::File { file: StoredFile { filename, file_data: Some(file_data), .. } } => {
+                let source = MediaSource::from_data_uri(&file_data)
+                    .ok_or_else(|| D::Error::custom("file_data must be a base64 data: URI"))?;
+                MessagePart::Document(DocumentPart { title: filename, ..DocumentPart::new(source) })
+            }
+            StoredPart::File { .. } => return Err(D::Error::custom("file parts need file_id or file_data")),
+            StoredPart::ToolCall { tool_call } => MessagePart::ToolCall(tool_call),
+            StoredPart::ToolResult { tool_result } => MessagePart::ToolResult(tool_result),
+            StoredPart::Thinking { thinking, signature } => MessagePart::Thinking(ThinkingBlock::Thinking { thinking, signature }),
+            StoredPart::RedactedThinking { data } => MessagePart::Thinking(ThinkingBlock::RedactedThinking { data }),
+            StoredPart::Image { source, detail } => MessagePart::Image(ImagePart { source, detail }),
+            StoredPart::Audio { source } => MessagePart::Audio(source),
+            StoredPart::Video { source } => MessagePart::Video(source),
+            StoredPart::Document { source, title, context, citations } => {
+                MessagePart::Document(DocumentPart { source, title, context, citations })
+            }
+        };
+        Ok(part)
+    }
 }
 
 // --- New: Enum to handle inconsistent 'thought' types from different models ---
@@ -115,15 +541,95 @@ pub enum ThoughtContent {
 }
 
 // --- Updated Message Struct ---
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Clone)]
 pub struct Message {
     pub role: String,
     pub content: MessageContent, // Changed from String
 }
 
+impl Message {
+    /// Builds the assistant turn that requested `calls`, to be replayed in the conversation history.
+    pub fn assistant_tool_calls(text: Option<String>, calls: Vec<ToolCall>) -> Self {
+        let mut parts: Vec<MessagePart> = Vec::new();
+        if let Some(t) = text {
+            parts.push(MessagePart::Text(t));
+        }
+        parts.extend(calls.into_iter().map(MessagePart::ToolCall));
+        Message { role: "assistant".to_string(), content: MessageContent::Array(parts) }
+    }
+
+    /// Builds the assistant turn for `response`, to be replayed in the conversation history.
+    /// Unlike `assistant_tool_calls`, it keeps the signed thinking blocks Anthropic requires in
+    /// front of the tool calls when extended thinking is on.
+    pub fn assistant_response(response: &LlmResponse) -> Self {
+        let mut parts: Vec<MessagePart> = response.thinking.iter().cloned().map(MessagePart::Thinking).collect();
+        if !response.text.is_empty() {
+            parts.push(MessagePart::Text(response.text.clone()));
+        }
+        parts.extend(response.tool_calls.iter().cloned().map(MessagePart::ToolCall));
+        Message { role: "assistant".to_string(), content: MessageContent::Array(parts) }
+    }
+
+    /// Builds a `tool` message carrying the output of a previous tool call.
+    pub fn tool_result(tool_call_id: impl Into<String>, name: impl Into<String>, content: impl Into<String>) -> Self {
+        Message {
+            role: "tool".to_string(),
+            content: MessageContent::Array(vec![MessagePart::ToolResult(ToolResult {
+                tool_call_id: tool_call_id.into(),
+                name: name.into(),
+                content: content.into(),
+                is_error: false,
+            })]),
+        }
+    }
+}
+
+/// Serializes to the OpenAI chat-completions wire shape: tool calls are lifted into the
+/// message-level `tool_calls` array and a tool result becomes `tool_call_id` + string content.
+/// OpenAI takes one result per `tool` message; `normalize_messages` splits messages that carry
+/// several before they are sent.
+impl Serialize for Message {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::SerializeMap;
+        let parts: &[MessagePart] = match &self.content {
+            MessageContent::Array(parts) => parts,
+            MessageContent::Text(_) => &[],
+        };
+        let tool_calls: Vec<OpenAIToolCall> = parts.iter()
+            .filter_map(|p| match p {
+                MessagePart::ToolCall(call) => Some(OpenAIToolCall::from(call)),
+                _ => None,
+            })
+            .collect();
+        let tool_result = parts.iter().find_map(|p| match p {
+            MessagePart::ToolResult(result) => Some(result),
+            _ => None,
+        });
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("role", &self.role)?;
+        if let Some(result) = tool_result {
+            map.serialize_entry("tool_call_id", &result.tool_call_id)?;
+            map.serialize_entry("content", &result.content)?;
+        } else if !tool_calls.is_empty() {
+            let text = self.content.extract_text();
+            map.serialize_entry("content", &(!text.is_empty()).then_some(text))?;
+            map.serialize_entry("tool_calls", &tool_calls)?;
+        } else {
+            map.serialize_entry("content", &self.content)?;
+        }
+        map.end()
+    }
+}
+
 // --- Existing Gemini Structs ---
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Content {
+    // Left empty for `systemInstruction`, which takes no role
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub role: String,
     pub parts: Vec<Part>,
 }
@@ -135,7 +641,33 @@ pub struct Part {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub inline_data: Option<GeminiInlineData>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_data: Option<GeminiFileData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub thought: Option<ThoughtContent>,
+    #[serde(rename = "functionCall", skip_serializing_if = "Option::is_none")]
+    pub function_call: Option<GeminiFunctionCall>,
+    #[serde(rename = "functionResponse", skip_serializing_if = "Option::is_none")]
+    pub function_response: Option<GeminiFunctionResponse>,
+    #[serde(rename = "thoughtSignature", skip_serializing_if = "Option::is_none")]
+    pub thought_signature: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GeminiFunctionCall {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub args: Value,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GeminiFunctionResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    /// Must be a JSON object.
+    pub response: Value,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -144,10 +676,97 @@ pub struct GeminiInlineData {
     pub data: String,
 }
 
+/// Media referenced by URI (a public URL or a Files API upload) rather than sent inline.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GeminiFileData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    pub file_uri: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LlmChunk {
     Text(String),
     Reasoning(String),
-    /// Use this to signal the end of the stream or provide final metadata (like token counts)
-    Done,
+    /// The model started a tool call. `index` identifies the call in the following chunks.
+    ToolCallStart { index: usize, id: String, name: String },
+    /// A fragment of the JSON-encoded arguments of the tool call at `index`.
+    ToolCallArgumentsDelta { index: usize, delta: String },
+    /// The tool call at `index` is complete; `call` holds the assembled, parsed arguments.
+    ToolCallEnd { index: usize, call: ToolCall },
+    /// A complete signed reasoning block, sent after its `Reasoning` chunks. Keep it for the
+    /// assistant turn in the history, as with `LlmResponse::thinking`.
+    Thinking(ThinkingBlock),
+    /// Signals the end of the stream and carries the final accounting, when the provider sent it.
+    Done {
+        usage: Option<Usage>,
+        finish_reason: Option<FinishReason>,
+        /// The model (version) that actually served the request.
+        model: Option<String>,
+        /// Config options that were dropped because the provider cannot honor them.
+        warnings: Vec<String>,
+    },
+}
+
+/// Parses JSON-encoded tool arguments, keeping the raw string if the model produced invalid JSON.
+pub(crate) fn parse_tool_arguments(raw: String) -> Value {
+    if raw.trim().is_empty() {
+        return Value::Object(Default::default());
+    }
+    serde_json::from_str(&raw).unwrap_or(Value::String(raw))
+}
+
+struct PendingToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+    signature: Option<String>,
+}
+
+/// Collects streamed tool-call fragments so the SSE loops can emit `ToolCallEnd` with the full call.
+#[derive(Default)]
+pub(crate) struct ToolCallAssembler {
+    pending: BTreeMap<usize, PendingToolCall>,
+}
+
+impl ToolCallAssembler {
+    pub fn is_started(&self, index: usize) -> bool {
+        self.pending.contains_key(&index)
+    }
+
+    pub fn start(&mut self, index: usize, id: String, name: String, signature: Option<String>) -> LlmChunk {
+        self.pending.insert(index, PendingToolCall {
+            id: id.clone(),
+            name: name.clone(),
+            arguments: String::new(),
+            signature,
+        });
+        LlmChunk::ToolCallStart { index, id, name }
+    }
+
+    pub fn append(&mut self, index: usize, delta: &str) -> Option<LlmChunk> {
+        let pending = self.pending.get_mut(&index)?;
+        if delta.is_empty() {
+            return None;
+        }
+        pending.arguments.push_str(delta);
+        Some(LlmChunk::ToolCallArgumentsDelta { index, delta: delta.to_string() })
+    }
+
+    pub fn end(&mut self, index: usize) -> Option<LlmChunk> {
+        let pending = self.pending.remove(&index)?;
+        let call = ToolCall {
+            id: pending.id,
+            name: pending.name,
+            arguments: parse_tool_arguments(pending.arguments),
+            signature: pending.signature,
+        };
+        Some(LlmChunk::ToolCallEnd { index, call })
+    }
+
+    /// Closes every call still open, in index order.
+    pub fn finish(&mut self) -> Vec<LlmChunk> {
+        let indices: Vec<usize> = self.pending.keys().copied().collect();
+        indices.into_iter().filter_map(|index| self.end(index)).collect()
+    }
 }
@@ -1,5 +1,6 @@
 // src/structs/llama_server.rs
 use serde::{Deserialize, Serialize};
+use crate::options::LlamaOptions;
 
 #[derive(Debug, Serialize)]
 pub struct LlamaCompletionRequest {
@@ -28,10 +29,16 @@ pub struct LlamaCompletionRequest {
     
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cache_prompt: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
     
     // For Gemma 4 vision inputs on the legacy path
     #[serde(skip_serializing_if = "Option::is_none")]
     pub image_data: Option<Vec<ImageData>>,
+
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub llama_options: Option<LlamaOptions>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,4 +51,9 @@ pub struct ImageData {
 pub struct LlamaCompletionResponse {
     pub content: String,
     pub stop: bool,
+    /// "eos", "word" (stop string), "limit" (n_predict reached) or "none"
+    pub stop_type: Option<String>,
+    pub tokens_predicted: Option<u32>,
+    pub tokens_evaluated: Option<u32>,
+    pub tokens_cached: Option<u32>,
 }
\ No newline at end of file
@@ -1,7 +1,8 @@
 // src/structs/openai.rs
-use serde::Serialize;
-use crate::structs::general::Message;
-use serde_json::Value;
+use serde::{Deserialize, Serialize};
+use crate::structs::general::{Message, ToolCall, ToolChoice, ToolDefinition, parse_tool_arguments};
+use serde_json::{json, Value};
+use crate::options::{LlamaOptions, OpenAiOptions};
 
 #[derive(Debug, Serialize, Clone, Default)]
 pub struct ChatCompletion {
@@ -31,9 +32,111 @@ pub struct ChatCompletion {
     
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cache_prompt: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<String>,
     
     #[serde(skip_serializing_if = "Option::is_none")]
     pub response_format: Option<Value>, 
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<OpenAITool>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<Value>,
+
+    // `{"include_usage": true}` adds a final usage chunk to streamed responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<Value>,
+
+    // llama-server only: variables for the model's chat template, e.g. `{"enable_thinking": false}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_template_kwargs: Option<Value>,
+
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub openai_options: Option<OpenAiOptions>,
+
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub llama_options: Option<LlamaOptions>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct OpenAITool {
+    pub r#type: String, // Always "function"
+    pub function: OpenAIFunction,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct OpenAIFunction {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+impl From<&ToolDefinition> for OpenAITool {
+    fn from(tool: &ToolDefinition) -> Self {
+        OpenAITool {
+            r#type: "function".to_string(),
+            function: OpenAIFunction {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                parameters: tool.parameters.clone(),
+            },
+        }
+    }
+}
+
+/// Maps a `ToolChoice` to the chat-completions `tool_choice` value.
+pub fn map_tool_choice_to_openai(choice: &ToolChoice) -> Value {
+    match choice {
+        ToolChoice::Auto => json!("auto"),
+        ToolChoice::None => json!("none"),
+        ToolChoice::Required => json!("required"),
+        ToolChoice::Tool(name) => json!({ "type": "function", "function": { "name": name } }),
+    }
+}
+
+/// A tool call as it appears in assistant messages, both in requests and responses.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OpenAIToolCall {
+    pub id: String,
+    #[serde(default)]
+    pub r#type: String,
+    pub function: OpenAIFunctionCall,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OpenAIFunctionCall {
+    pub name: String,
+    /// JSON-encoded arguments.
+    pub arguments: String,
+}
+
+impl From<&ToolCall> for OpenAIToolCall {
+    fn from(call: &ToolCall) -> Self {
+        let arguments = match &call.arguments {
+            Value::String(raw) => raw.clone(),
+            other => other.to_string(),
+        };
+        OpenAIToolCall {
+            id: call.id.clone(),
+            r#type: "function".to_string(),
+            function: OpenAIFunctionCall { name: call.name.clone(), arguments },
+        }
+    }
+}
+
+impl From<OpenAIToolCall> for ToolCall {
+    fn from(call: OpenAIToolCall) -> Self {
+        let arguments = parse_tool_arguments(call.function.arguments);
+        ToolCall { id: call.id, name: call.function.name, arguments, signature: None }
+    }
 }
 
 #[derive(Debug, Serialize)]
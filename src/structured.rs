@@ -0,0 +1,414 @@
+// src/structured.rs
+use async_stream::stream;
+use futures::stream::{BoxStream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+
+use crate::errors::LlmError;
+use crate::structs::general::LlmChunk;
+
+/// Name used when `LlmConfig::json_schema` is a bare schema without one.
+const DEFAULT_SCHEMA_NAME: &str = "json_response";
+
+/// The schema carried by `LlmConfig::json_schema`.
+///
+/// `json_schema` is either an OpenAI `response_format` object
+/// (`{"type": "json_schema", "json_schema": {"name", "schema"}}`, or `{"type": "json_object"}`
+/// with llama-server's optional `schema`) or a bare JSON Schema.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OutputSchema {
+    pub name: String,
+    /// `None` for plain JSON mode without a schema.
+    pub schema: Option<Value>,
+}
+
+/// Extracts the schema; `None` when the config asks for plain text.
+pub(crate) fn output_schema(json_schema: &Value) -> Option<OutputSchema> {
+    match json_schema.get("type").and_then(Value::as_str) {
+        Some("text") => None,
+        Some("json_schema") => {
+            let inner = json_schema.get("json_schema");
+            Some(OutputSchema {
+                name: inner
+                    .and_then(|s| s.get("name"))
+                    .and_then(Value::as_str)
+                    .unwrap_or(DEFAULT_SCHEMA_NAME)
+                    .to_string(),
+                schema: inner.and_then(|s| s.get("schema")).cloned(),
+            })
+        }
+        Some("json_object") => Some(OutputSchema {
+            name: DEFAULT_SCHEMA_NAME.to_string(),
+            schema: json_schema.get("schema").cloned(),
+        }),
+        _ => Some(OutputSchema { name: DEFAULT_SCHEMA_NAME.to_string(), schema: Some(json_schema.clone()) }),
+    }
+}
+
+/// The `response_format` sent to chat-completions endpoints: `response_format` objects pass
+/// through unchanged and bare schemas are wrapped in one.
+pub(crate) fn openai_response_format(json_schema: &Value) -> Value {
+    match json_schema.get("type").and_then(Value::as_str) {
+        Some("text" | "json_schema" | "json_object") => json_schema.clone(),
+        _ => json!({
+            "type": "json_schema",
+            "json_schema": { "name": DEFAULT_SCHEMA_NAME, "schema": json_schema },
+        }),
+    }
+}
+
+/// Best-effort parse of a JSON document that is still being streamed.
+///
+/// Open objects, arrays and strings are closed where the text stops. A member whose key or value
+/// is cut off is left out, except strings, which are kept with the text received so far. Numbers
+/// and `true`/`false`/`null` only appear once the token is complete. Anything before the first
+/// `{` or `[` (such as a Markdown code fence) is skipped, as is anything after the document.
+///
+/// Returns `None` until there is something to show, or when the text is not JSON.
+pub fn parse_partial_json(text: &str) -> Option<Value> {
+    let start = text.find(['{', '['])?;
+    let mut parser = PartialJson { text: &text[start..], pos: 0 };
+    parser.value().ok().flatten().map(|(value, _)| value)
+}
+
+/// Turns the stream of a `json_schema` request into successive snapshots of the JSON received so
+/// far, as parsed by [`parse_partial_json`]. A snapshot is yielded each time it changes.
+///
+/// Chunks other than `LlmChunk::Text` are ignored. If the finished text is not a complete JSON
+/// document, the stream ends with `LlmError::Parse`.
+pub fn partial_json_stream(
+    chunks: BoxStream<'static, Result<LlmChunk, LlmError>>,
+) -> BoxStream<'static, Result<Value, LlmError>> {
+    let output_stream = stream! {
+        let mut chunks = chunks;
+        let mut text = String::new();
+        let mut last: Option<Value> = None;
+        while let Some(chunk) = chunks.next().await {
+            match chunk {
+                Ok(LlmChunk::Text(delta)) => {
+                    text.push_str(&delta);
+                    if let Some(snapshot) = parse_partial_json(&text) {
+                        if last.as_ref() != Some(&snapshot) {
+                            last = Some(snapshot.clone());
+                            yield Ok(snapshot);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            }
+        }
+
+        let complete = text
+            .find(['{', '['])
+            .and_then(|start| serde_json::Deserializer::from_str(&text[start..]).into_iter::<Value>().next())
+            .and_then(Result::ok);
+        match complete {
+            Some(value) if last.as_ref() != Some(&value) => yield Ok(value),
+            Some(_) => {}
+            None => yield Err(LlmError::Parse {
+                message: format!("Streamed structured output is not complete JSON: {}", text),
+            }),
+        }
+    };
+    output_stream.boxed()
+}
+
+/// Like [`partial_json_stream`], but yields each snapshot that deserializes into `T`.
+///
+/// Give `T` optional or `#[serde(default)]` fields so the early snapshots deserialize. The stream
+/// ends with `LlmError::Parse` if the complete document does not deserialize.
+pub fn partial_stream<T>(
+    chunks: BoxStream<'static, Result<LlmChunk, LlmError>>,
+) -> BoxStream<'static, Result<T, LlmError>>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let output_stream = stream! {
+        let mut snapshots = partial_json_stream(chunks);
+        let mut last_error = None;
+        while let Some(snapshot) = snapshots.next().await {
+            match snapshot {
+                Ok(value) => match serde_json::from_value::<T>(value) {
+                    Ok(parsed) => {
+                        last_error = None;
+                        yield Ok(parsed);
+                    }
+                    Err(e) => last_error = Some(e),
+                },
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            }
+        }
+        if let Some(e) = last_error {
+            yield Err(LlmError::Parse { message: format!("Streamed structured output does not match the expected structure: {}", e) });
+        }
+    };
+    output_stream.boxed()
+}
+
+/// Recursive-descent parser behind [`parse_partial_json`]. Each method returns `Err` for text
+/// that is not JSON, and `Ok(None)` when the text ends before anything usable.
+struct PartialJson<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+/// A parsed value and whether it was closed before the text ended.
+type Parsed = Option<(Value, bool)>;
+
+impl PartialJson<'_> {
+    fn peek(&mut self) -> Option<u8> {
+        while let Some(&b) = self.text.as_bytes().get(self.pos) {
+            if !b.is_ascii_whitespace() {
+                return Some(b);
+            }
+            self.pos += 1;
+        }
+        None
+    }
+
+    fn value(&mut self) -> Result<Parsed, ()> {
+        match self.peek() {
+            None => Ok(None),
+            Some(b'{') => self.object().map(Some),
+            Some(b'[') => self.array().map(Some),
+            Some(b'"') => self.string().map(Some),
+            Some(_) => self.scalar(),
+        }
+    }
+
+    fn object(&mut self) -> Result<(Value, bool), ()> {
+        self.pos += 1;
+        let mut map = Map::new();
+        loop {
+            match self.peek() {
+                None => return Ok((Value::Object(map), false)),
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok((Value::Object(map), true));
+                }
+                Some(b',') => {
+                    self.pos += 1;
+                    continue;
+                }
+                Some(b'"') => {}
+                Some(_) => return Err(()),
+            }
+            let key = match self.string()? {
+                (Value::String(key), true) => key,
+                _ => return Ok((Value::Object(map), false)),
+            };
+            match self.peek() {
+                None => return Ok((Value::Object(map), false)),
+                Some(b':') => self.pos += 1,
+                Some(_) => return Err(()),
+            }
+            match self.value()? {
+                None => return Ok((Value::Object(map), false)),
+                Some((value, complete)) => {
+                    map.insert(key, value);
+                    if !complete {
+                        return Ok((Value::Object(map), false));
+                    }
+                }
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<(Value, bool), ()> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Ok((Value::Array(items), false)),
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok((Value::Array(items), true));
+                }
+                Some(b',') => {
+                    self.pos += 1;
+                    continue;
+                }
+                Some(_) => {}
+            }
+            match self.value()? {
+                None => return Ok((Value::Array(items), false)),
+                Some((value, complete)) => {
+                    items.push(value);
+                    if !complete {
+                        return Ok((Value::Array(items), false));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Decoding is left to serde_json; an unterminated string is closed first, dropping a
+    /// trailing escape sequence that was cut off.
+    fn string(&mut self) -> Result<(Value, bool), ()> {
+        let bytes = self.text.as_bytes();
+        let start = self.pos;
+        let mut i = start + 1;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => i += 2,
+                b'"' => {
+                    self.pos = i + 1;
+                    let value = serde_json::from_str(&self.text[start..=i]).map_err(|_| ())?;
+                    return Ok((value, true));
+                }
+                _ => i += 1,
+            }
+        }
+        self.pos = bytes.len();
+
+        let raw = &self.text[start..];
+        let value = serde_json::from_str(&format!("{}\"", raw))
+            .or_else(|_| serde_json::from_str(&format!("{}\"", &raw[..raw.rfind('\\').unwrap_or(raw.len())])))
+            .unwrap_or_else(|_| Value::String(String::new()));
+        Ok((value, false))
+    }
+
+    /// Numbers and literals, which are only returned once something follows them.
+    fn scalar(&mut self) -> Result<Parsed, ()> {
+        let rest = &self.text[self.pos..];
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.')))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(());
+        }
+        if self.pos + len == self.text.len() {
+            self.pos += len;
+            return Ok(None);
+        }
+        let value = serde_json::from_str(&rest[..len]).map_err(|_| ())?;
+        self.pos += len;
+        Ok(Some((value, true)))
+    }
+}
+
+#[cfg(feature = "structured")]
+pub use typed::StructuredAccess;
+
+#[cfg(feature = "structured")]
+mod typed {
+    use async_trait::async_trait;
+    use schemars::JsonSchema;
+    use serde::de::DeserializeOwned;
+    use serde_json::{json, Value};
+
+    use crate::config::LlmConfig;
+    use crate::errors::LlmError;
+    use crate::llm::Access;
+    use crate::structs::general::{Message, MessageContent};
+
+    /// Typed structured output, available on everything that implements `Access`.
+    ///
+    /// ```ignore
+    /// #[derive(serde::Deserialize, schemars::JsonSchema)]
+    /// struct Person { name: String, age: u32 }
+    ///
+    /// let person: Person = LLM::Gemini.send_structured(messages, None, None).await?;
+    /// ```
+    #[async_trait]
+    pub trait StructuredAccess: Access {
+        /// Sends `messages` with a JSON Schema derived from `T` and deserializes the reply.
+        ///
+        /// The reply is validated against the schema locally. When it does not parse or validate,
+        /// the errors are sent back to the model and it is asked again, up to
+        /// `LlmConfig::structured_retries` times (2 by default). Running out of retries returns
+        /// `LlmError::Parse`.
+        async fn send_structured<T>(
+            &self,
+            messages: Vec<Message>,
+            model: Option<&str>,
+            config: Option<&LlmConfig>,
+        ) -> Result<T, LlmError>
+        where
+            T: JsonSchema + DeserializeOwned + Send,
+        {
+            let schema = schema_value::<T>();
+            let validator = jsonschema::validator_for(&schema).map_err(|e| LlmError::InvalidRequest {
+                message: format!("Invalid schema for {}: {}", T::schema_name(), e),
+            })?;
+            let config = config.cloned().unwrap_or_default().with_json_schema(json!({
+                "type": "json_schema",
+                "json_schema": { "name": schema_name::<T>(), "schema": schema },
+            }));
+            let retries = config.structured_retries.unwrap_or(DEFAULT_STRUCTURED_RETRIES);
+
+            let mut messages = messages;
+            let mut attempt = 0;
+            loop {
+                let response = self.send_convo_message(messages.clone(), model, Some(&config)).await?;
+                let problem = match serde_json::from_str::<Value>(strip_code_fence(&response.text)) {
+                    Err(e) => format!("The reply is not valid JSON: {}", e),
+                    Ok(value) => {
+                        let errors: Vec<String> = validator
+                            .iter_errors(&value)
+                            .map(|e| format!("at '{}': {}", e.instance_path, e))
+                            .collect();
+                        if errors.is_empty() {
+                            match serde_json::from_value::<T>(value) {
+                                Ok(parsed) => return Ok(parsed),
+                                Err(e) => format!("The reply does not match the expected structure: {}", e),
+                            }
+                        } else {
+                            format!("The reply does not match the JSON Schema:\n- {}", errors.join("\n- "))
+                        }
+                    }
+                };
+
+                if attempt >= retries {
+                    return Err(LlmError::Parse {
+                        message: format!("Structured output failed after {} attempts. {}", attempt + 1, problem),
+                    });
+                }
+                attempt += 1;
+                messages.push(Message { role: "assistant".to_string(), content: MessageContent::Text(response.text) });
+                messages.push(Message {
+                    role: "user".to_string(),
+                    content: MessageContent::Text(format!("{}\nReply again with only the corrected JSON.", problem)),
+                });
+            }
+        }
+    }
+
+    impl<A: Access + Sync + ?Sized> StructuredAccess for A {}
+
+    const DEFAULT_STRUCTURED_RETRIES: u32 = 2;
+
+    /// The schema for `T`, without the `$schema` keyword that Gemini rejects.
+    fn schema_value<T: JsonSchema>() -> Value {
+        let mut schema = schemars::schema_for!(T).to_value();
+        if let Some(object) = schema.as_object_mut() {
+            object.remove("$schema");
+        }
+        schema
+    }
+
+    /// `T`'s schema name, limited to the characters and length OpenAI accepts.
+    fn schema_name<T: JsonSchema>() -> String {
+        T::schema_name()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .take(64)
+            .collect()
+    }
+
+    /// Some models wrap JSON in a Markdown code fence even when asked not to.
+    fn strip_code_fence(text: &str) -> &str {
+        let trimmed = text.trim();
+        match trimmed.strip_prefix("```").and_then(|rest| rest.strip_suffix("```")) {
+            Some(inner) => inner.trim_start_matches("json").trim(),
+            None => trimmed,
+        }
+    }
+}
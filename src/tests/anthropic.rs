@@ -3,6 +3,15 @@ mod tests {
     use crate::anthropic::call_anthropic;
     use crate::structs::general::{ Message, MessageContent };
     use crate::config::LlmConfig; // <-- Added to test thinking blocks
+    use crate::anthropic::{call_anthropic_stream_with_client, call_anthropic_with_client};
+    use crate::structs::general::LlmChunk;
+    use futures::StreamExt;
+    use crate::llm::LLM;
+    use crate::structs::general::ToolChoice;
+    use crate::tests::{ask, client, mock_server, mock_server_sequence, request_body};
+    use crate::structs::general::{ThinkingBlock, ToolDefinition};
+    use crate::normalize::normalize_messages;
+    use serde_json::{json, Value};
 
     #[tokio::test]
     async fn test_call_anthropic() {
@@ -23,7 +32,7 @@ mod tests {
             }
             Err(err) => {
                 eprintln!("Error: {}", err);
-                assert!(false, "Call to Anthropic API failed");
+                panic!("Call to Anthropic API failed");
             }
         }
     }
@@ -72,13 +81,13 @@ mod tests {
                     }
                     Err(err) => {
                         eprintln!("Error: {}", err);
-                        assert!(false, "Call to Anthropic API failed");
+                        panic!("Call to Anthropic API failed");
                     }
                 }
             }
             Err(err) => {
                 eprintln!("Error: {}", err);
-                assert!(false, "Call to Anthropic API failed");
+                panic!("Call to Anthropic API failed");
             }
         }
     }
@@ -109,8 +118,108 @@ mod tests {
             }
             Err(err) => {
                 eprintln!("Error: {}", err);
-                assert!(false, "Call to Anthropic API with thinking failed");
+                panic!("Call to Anthropic API with thinking failed");
             }
         }
     }
-}
\ No newline at end of file
+
+    async fn sent_body(config: LlmConfig) -> Value {
+        let (base_url, server) = mock_server(
+            r#"{"id":"msg_1","role":"assistant","content":[{"type":"text","text":"Hi"}],"stop_reason":"end_turn"}"#,
+        ).await;
+        let client = client(LLM::Anthropic, base_url);
+        let messages = ask("Hi");
+        call_anthropic_with_client(&client, messages, None, Some(&config)).await.unwrap();
+        let raw = server.await.unwrap();
+        request_body(&raw)
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_config_parity() {
+        let config = LlmConfig::new()
+            .with_temperature(0.5)
+            .with_stop(vec!["END".to_string()])
+            .with_top_k(40)
+            .with_top_p(0.5)
+            .with_user_id("user-123")
+            .with_service_tier("standard_only");
+
+        let body = sent_body(config.clone()).await;
+        assert_eq!(body["temperature"], 0.5);
+        assert_eq!(body["stop_sequences"], json!(["END"]));
+        assert_eq!(body["top_k"], 40);
+        assert_eq!(body["top_p"], 0.5);
+        assert_eq!(body["metadata"], json!({ "user_id": "user-123" }));
+        assert_eq!(body["service_tier"], "standard_only");
+
+        let thinking = config.with_thinking_budget(2048).with_tool_choice(ToolChoice::Required);
+        let body = sent_body(thinking).await;
+        assert_eq!(body["thinking"], json!({ "type": "enabled", "budget_tokens": 2048 }));
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("top_k").is_none());
+        assert_eq!(body["top_p"], 0.95);
+        assert_eq!(body["tool_choice"], json!({ "type": "auto" }));
+        assert_eq!(body["stop_sequences"], json!(["END"]));
+    }
+
+    #[tokio::test]
+    async fn test_thinking_replayed_in_tool_loop() {
+        let (base_url, server) = mock_server_sequence(vec![
+            (200, "", r#"{"id":"msg_1","role":"assistant","content":[
+                {"type":"thinking","thinking":"Need the weather.","signature":"sig-1"},
+                {"type":"tool_use","id":"toolu_1","name":"get_weather","input":{"city":"Paris"}}
+            ],"stop_reason":"tool_use"}"#),
+            (200, "", r#"{"id":"msg_2","role":"assistant","content":[{"type":"text","text":"Sunny."}],"stop_reason":"end_turn"}"#),
+        ]).await;
+        let client = client(LLM::Anthropic, base_url);
+        let config = LlmConfig::new()
+            .with_thinking_budget(2048)
+            .with_tools(vec![ToolDefinition::new("get_weather", "Current weather", json!({ "type": "object" }))]);
+        let mut messages = ask("Weather in Paris?");
+
+        let first = call_anthropic_with_client(&client, messages.clone(), None, Some(&config)).await.unwrap();
+        assert_eq!(first.thinking, vec![ThinkingBlock::Thinking { thinking: "Need the weather.".to_string(), signature: "sig-1".to_string() }]);
+        messages.push(Message::assistant_response(&first));
+        messages.push(Message::tool_result("toolu_1", "get_weather", "Sunny"));
+        call_anthropic_with_client(&client, messages.clone(), None, Some(&config)).await.unwrap();
+
+        let raw = server.await.unwrap().remove(1);
+        let body = request_body(&raw);
+        assert_eq!(body["messages"][1], json!({ "role": "assistant", "content": [
+            { "type": "thinking", "thinking": "Need the weather.", "signature": "sig-1" },
+            { "type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": { "city": "Paris" } },
+        ] }));
+
+        // Other providers cannot take the signed block back
+        let openai = normalize_messages(messages, &LLM::OpenAI).unwrap();
+        assert_eq!(openai[1].content.as_parts().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_signed_thinking() {
+        let (base_url, server) = mock_server(concat!(
+            "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"thinking\",\"thinking\":\"\"}}\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"thinking_delta\",\"thinking\":\"Hmm.\"}}\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"signature_delta\",\"signature\":\"sig-1\"}}\n",
+            "data: {\"type\":\"content_block_stop\",\"index\":0}\n",
+            "data: {\"type\":\"message_stop\"}\n",
+        )).await;
+        let client = client(LLM::Anthropic, base_url);
+        let messages = ask("Hi");
+
+        let chunks: Vec<LlmChunk> = call_anthropic_stream_with_client(&client, messages, None, Some(&LlmConfig::new().with_thinking_budget(2048)))
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        server.await.unwrap();
+
+        assert!(matches!(&chunks[0], LlmChunk::Reasoning(r) if r == "Hmm."));
+        assert!(matches!(
+            &chunks[1],
+            LlmChunk::Thinking(ThinkingBlock::Thinking { thinking, signature }) if thinking == "Hmm." && signature == "sig-1"
+        ));
+        assert!(matches!(chunks[2], LlmChunk::Done { .. }));
+    }
+}
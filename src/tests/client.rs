@@ -0,0 +1,115 @@
+// src/tests/client.rs
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::client::{LlmClient, ProviderConfig};
+    use crate::errors::LlmError;
+    use crate::llm::{Access, LLM};
+    use crate::tests::{ask, client, mock_server, request_body};
+    use crate::config::LlmConfig;
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+    use tokio::task::JoinHandle;
+
+    #[tokio::test]
+    async fn test_openai_client_uses_config() {
+        let (base_url, server) = mock_server(
+            r#"{"choices":[{"message":{"content":"pong"},"finish_reason":"stop"}],"usage":{"prompt_tokens":3,"completion_tokens":1}}"#,
+        ).await;
+
+        let client = LlmClient::new(LLM::OpenAI, ProviderConfig::new()
+            .with_api_key("test-key")
+            .with_base_url(format!("{}/v1/", base_url))
+            .with_header("x-tenant", "acme")
+            .with_timeout(Duration::from_secs(5))).unwrap();
+
+        let response = client.send_single_message("ping".into(), Some("gpt-4o-mini"), None).await.unwrap();
+        assert_eq!(response.text, "pong");
+
+        let request = server.await.unwrap().to_ascii_lowercase();
+        assert!(request.starts_with("post /v1/chat/completions "));
+        assert!(request.contains("authorization: bearer test-key"));
+        assert!(request.contains("x-tenant: acme"));
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_client_headers() {
+        let (base_url, server) = mock_server(
+            r#"{"id":"msg_1","role":"assistant","content":[{"type":"text","text":"pong"}],"stop_reason":"end_turn"}"#,
+        ).await;
+
+        let client = LlmClient::new(LLM::Anthropic, ProviderConfig::new()
+            .with_api_key("ant-key")
+            .with_base_url(base_url)).unwrap();
+
+        let response = client.send_single_message("ping".into(), None, None).await.unwrap();
+        assert_eq!(response.text, "pong");
+
+        let request = server.await.unwrap().to_ascii_lowercase();
+        assert!(request.starts_with("post /messages "));
+        assert!(request.contains("x-api-key: ant-key"));
+        assert!(request.contains("anthropic-version: 2023-06-01"));
+    }
+
+    #[tokio::test]
+    async fn test_openai_compatible_provider() {
+        let (base_url, server) = mock_server(
+            r#"{"choices":[{"message":{"content":"pong","reasoning":"thinking"},"finish_reason":"stop"}]}"#,
+        ).await;
+
+        std::env::set_var("TEST_COMPAT_KEY", "compat-key");
+        let provider = LLM::OpenAICompatible {
+            base_url: format!("{}/openai/v1", base_url),
+            api_key_env: Some("TEST_COMPAT_KEY".to_string()),
+            headers: vec![("HTTP-Referer".to_string(), "https://example.com".to_string())],
+        };
+
+        let err = provider.send_single_message("ping".into(), None, None).await.unwrap_err();
+        assert!(matches!(err, LlmError::InvalidRequest { .. }));
+
+        let response = provider.send_single_message("ping".into(), Some("llama-3.1-8b-instant"), None).await.unwrap();
+        assert_eq!(response.text, "pong");
+        assert_eq!(response.reasoning.as_deref(), Some("thinking"));
+
+        let request = server.await.unwrap().to_ascii_lowercase();
+        assert!(request.starts_with("post /openai/v1/chat/completions "));
+        assert!(request.contains("authorization: bearer compat-key"));
+        assert!(request.contains("http-referer: https://example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_api_key() {
+        let client = LlmClient::new(LLM::Gemini, ProviderConfig::new()).unwrap();
+        let err = client.send_single_message("ping".into(), None, None).await.unwrap_err();
+        assert!(matches!(err, LlmError::Authentication { .. }));
+    }
+
+    async fn streamed_body(provider: LLM, base_url: String, server: JoinHandle<String>, config: LlmConfig) -> Value {
+        let client = client(provider, base_url);
+        let messages = ask("Hi");
+        let _: Vec<_> = client.send_streaming_convo_message(messages, Some("m"), Some(&config)).await.unwrap().collect().await;
+        let raw = server.await.unwrap();
+        request_body(&raw)
+    }
+
+    #[tokio::test]
+    async fn test_stream_options_only_for_openai() {
+        let reply = "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"},\"finish_reason\":\"stop\"}]}\n\ndata: [DONE]\n\n";
+        let compatible = |base_url: &str| LLM::OpenAICompatible { base_url: base_url.to_string(), api_key_env: None, headers: Vec::new() };
+
+        let (base_url, server) = mock_server(reply).await;
+        let body = streamed_body(LLM::OpenAI, base_url, server, LlmConfig::new()).await;
+        assert_eq!(body["stream_options"], json!({ "include_usage": true }));
+
+        let (base_url, server) = mock_server(reply).await;
+        let body = streamed_body(compatible(&base_url), base_url, server, LlmConfig::new()).await;
+        assert!(body.get("stream_options").is_none());
+
+        // Compatible servers that support it opt in through `extra_body`
+        let (base_url, server) = mock_server(reply).await;
+        let opt_in = LlmConfig::new().with_extra_param("stream_options", json!({ "include_usage": true }));
+        let body = streamed_body(compatible(&base_url), base_url, server, opt_in).await;
+        assert_eq!(body["stream_options"], json!({ "include_usage": true }));
+    }
+}
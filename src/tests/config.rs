@@ -0,0 +1,247 @@
+// src/tests/config.rs
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+    use crate::config::{ConfigOption, LlmConfig, ReasoningConfig, ReasoningEffort};
+    use crate::errors::LlmError;
+    use crate::llm::{Access, LLM};
+    use crate::openai::call_gpt_with_client;
+    use crate::structs::general::{LlmChunk, ToolChoice};
+    use crate::tests::{ask, client, mock_server, request_body};
+
+    #[test]
+    fn test_support_matrix() {
+        assert!(!LLM::OpenAI.supports(ConfigOption::TopK));
+        assert!(LLM::Anthropic.supports(ConfigOption::TopK));
+        assert!(LLM::Gemini.supports(ConfigOption::GroundingWithSearch));
+        assert!(!LLM::Anthropic.supports(ConfigOption::GroundingWithSearch));
+        assert!(LLM::LlamaServer.supports(ConfigOption::CachePrompt));
+        assert!(!LLM::Gemini.supports(ConfigOption::CachePrompt));
+        assert!(LLM::OpenAI.supports(ConfigOption::Seed));
+        assert!(LLM::OpenAI.supports(ConfigOption::UserId));
+        assert!(LLM::LlamaServer.supports(ConfigOption::Seed));
+        assert!(!LLM::LlamaServer.supports(ConfigOption::ServiceTier));
+    }
+
+    #[tokio::test]
+    async fn test_strict_rejects_before_sending() {
+        let config = LlmConfig::new().with_strict(true).with_top_k(40).with_candidate_count(2).with_temperature(0.5);
+
+        // Nothing listens on port 9; reaching the network would give `LlmError::Network`
+        let err = client(LLM::OpenAI, "http://127.0.0.1:9".to_string())
+            .send_convo_message(ask("Hi"), None, Some(&config))
+            .await
+            .unwrap_err();
+
+        match err {
+            LlmError::UnsupportedOptions { provider, options, reasons } => {
+                assert_eq!(provider, "OpenAI");
+                assert_eq!(options, vec![ConfigOption::TopK, ConfigOption::CandidateCount]);
+                assert_eq!(reasons[0], "LlmConfig::top_k is not supported by OpenAI and was ignored");
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_direct_provider_call_checks_config() {
+        let config = LlmConfig::new().with_strict(true).with_top_k(40);
+
+        let err = call_gpt_with_client(&client(LLM::OpenAI, "http://127.0.0.1:9".to_string()), ask("Hi"), None, Some(&config))
+            .await
+            .unwrap_err();
+        match err {
+            LlmError::UnsupportedOptions { options, .. } => assert_eq!(options, vec![ConfigOption::TopK]),
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        let (base_url, server) = mock_server(
+            r#"{"choices":[{"message":{"content":"Hi"},"finish_reason":"stop"}]}"#,
+        ).await;
+        let config = LlmConfig::new().with_top_k(40).with_temperature(0.5);
+        let res = call_gpt_with_client(&client(LLM::OpenAI, base_url), ask("Hi"), Some("o3-mini"), Some(&config)).await.unwrap();
+
+        let body = request_body(&server.await.unwrap());
+        assert!(body.get("top_k").is_none());
+        assert!(body.get("temperature").is_none());
+        assert_eq!(res.warnings, vec![
+            "LlmConfig::top_k is not supported by OpenAI and was ignored".to_string(),
+            "o3-mini only accepts a temperature of 1; 0.5 was dropped".to_string(),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_lenient_drops_and_warns() {
+        let (base_url, server) = mock_server(
+            r#"{"choices":[{"message":{"content":"Hi"},"finish_reason":"stop"}]}"#,
+        ).await;
+        let config = LlmConfig::new().with_top_k(40).with_temperature(0.5);
+
+        let res = client(LLM::OpenAI, base_url).send_convo_message(ask("Hi"), None, Some(&config)).await.unwrap();
+
+        let raw = server.await.unwrap();
+        let body = request_body(&raw);
+        assert!(body.get("top_k").is_none());
+        assert_eq!(body["temperature"], 0.5);
+        assert_eq!(res.warnings, vec!["LlmConfig::top_k is not supported by OpenAI and was ignored".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_lenient_stream_warns_on_done() {
+        let (base_url, server) = mock_server(concat!(
+            "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n",
+            "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"}}\n",
+            "data: {\"type\":\"message_stop\"}\n",
+        )).await;
+        let config = LlmConfig::new().with_grounding_with_search(true);
+
+        let mut stream = client(LLM::Anthropic, base_url).send_streaming_convo_message(ask("Hi"), None, Some(&config)).await.unwrap();
+        let mut warnings = None;
+        while let Some(chunk) = stream.next().await {
+            if let LlmChunk::Done { warnings: w, .. } = chunk.unwrap() {
+                warnings = Some(w);
+            }
+        }
+        server.await.unwrap();
+
+        assert_eq!(warnings, Some(vec!["LlmConfig::grounding_with_search is not supported by Anthropic and was ignored".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn test_extra_body_deep_merged() {
+        let (base_url, server) = mock_server(
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"Hi"}]},"finishReason":"STOP"}]}"#,
+        ).await;
+        let config = LlmConfig::new()
+            .with_temperature(0.5)
+            .with_max_tokens(100)
+            .with_extra_param("labels", json!({ "team": "search" }))
+            .with_extra_param("generationConfig", json!({ "responseLogprobs": true, "maxOutputTokens": null }))
+            .with_extra_header("x-goog-user-project", "my-project");
+
+        client(LLM::Gemini, base_url).send_convo_message(ask("Hi"), Some("gemini-test"), Some(&config)).await.unwrap();
+
+        let raw = server.await.unwrap();
+        assert!(raw.contains("x-goog-user-project: my-project"));
+        let body = request_body(&raw);
+        assert_eq!(body["labels"], json!({ "team": "search" }));
+        assert_eq!(body["generationConfig"], json!({ "temperature": 0.5, "responseLogprobs": true }));
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "Hi");
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_mapped_per_provider() {
+        let (base_url, server) = mock_server(
+            r#"{"choices":[{"message":{"content":"Hi"},"finish_reason":"stop"}]}"#,
+        ).await;
+        let high = LlmConfig::new().with_reasoning(ReasoningConfig::new().with_effort(ReasoningEffort::High));
+        let res = client(LLM::OpenAI, base_url).send_convo_message(ask("Hi"), None, Some(&high)).await.unwrap();
+        assert_eq!(request_body(&server.await.unwrap())["reasoning_effort"], "high");
+        assert!(res.warnings.is_empty());
+
+        let (base_url, server) = mock_server(
+            r#"{"id":"msg_1","role":"assistant","content":[{"type":"text","text":"Hi"}],"stop_reason":"end_turn"}"#,
+        ).await;
+        let dynamic = LlmConfig::new().with_reasoning(ReasoningConfig::new().with_budget_tokens(-1));
+        let res = client(LLM::Anthropic, base_url).send_convo_message(ask("Hi"), None, Some(&dynamic)).await.unwrap();
+        assert_eq!(request_body(&server.await.unwrap())["thinking"], json!({ "type": "enabled", "budget_tokens": 8192 }));
+        assert_eq!(
+            res.warnings,
+            vec!["Anthropic has no dynamic thinking budget; the Medium budget of 8192 tokens was sent instead".to_string()]
+        );
+
+        let gemini = |config: &LlmConfig| {
+            serde_json::to_value(crate::gemini::gemini_generation_config_from_config(config).unwrap()).unwrap()
+        };
+        assert_eq!(gemini(&dynamic)["thinkingConfig"], json!({ "thinkingBudget": -1, "includeThoughts": true }));
+        let off = LlmConfig::new().with_reasoning(ReasoningConfig::new().with_effort(ReasoningEffort::Off));
+        assert_eq!(gemini(&off)["thinkingConfig"], json!({ "thinkingBudget": 0 }));
+        let low_quiet = LlmConfig::new()
+            .with_reasoning(ReasoningConfig::new().with_effort(ReasoningEffort::Low).with_include_thoughts(false));
+        assert_eq!(gemini(&low_quiet)["thinkingConfig"], json!({ "thinkingBudget": 1024, "includeThoughts": false }));
+    }
+
+    #[tokio::test]
+    async fn test_llama_thinking_switch() {
+        async fn llama_body(config: &LlmConfig) -> Value {
+            let (base_url, server) = mock_server(
+                r#"{"choices":[{"message":{"content":"Hi"},"finish_reason":"stop"}]}"#,
+            ).await;
+            client(LLM::LlamaServer, base_url).send_convo_message(ask("Hi"), None, Some(config)).await.unwrap();
+            request_body(&server.await.unwrap())
+        }
+
+        let on = llama_body(&LlmConfig::new().with_reasoning(ReasoningConfig::new().with_budget_tokens(-1))).await;
+        assert_eq!(on["chat_template_kwargs"], json!({ "enable_thinking": true }));
+        assert_eq!(on["messages"][0], json!({ "role": "system", "content": "<|think|>" }));
+
+        let off = llama_body(&LlmConfig::new().with_reasoning(ReasoningConfig::new().with_effort(ReasoningEffort::Off))).await;
+        assert_eq!(off["chat_template_kwargs"], json!({ "enable_thinking": false }));
+        assert_eq!(off["messages"], json!([{ "role": "user", "content": "Hi" }]));
+
+        let zero = llama_body(&LlmConfig::new().with_thinking_budget(0)).await;
+        assert_eq!(zero["chat_template_kwargs"], json!({ "enable_thinking": false }));
+
+        let unset = llama_body(&LlmConfig::new()).await;
+        assert!(unset.get("chat_template_kwargs").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_strict_rejects_unexpressible_reasoning() {
+        let config = LlmConfig::new()
+            .with_strict(true)
+            .with_reasoning(ReasoningConfig::new().with_effort(ReasoningEffort::High));
+
+        let err = client(LLM::LlamaServer, "http://127.0.0.1:9".to_string())
+            .send_convo_message(ask("Hi"), None, Some(&config))
+            .await
+            .unwrap_err();
+
+        match err {
+            LlmError::UnsupportedOptions { options, reasons, .. } => {
+                assert_eq!(options, vec![ConfigOption::ReasoningEffort]);
+                assert_eq!(reasons, vec!["llama-server can only switch thinking on or off; it was switched on".to_string()]);
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_thinking_conflicts_reported() {
+        let (base_url, server) = mock_server(
+            r#"{"id":"msg_1","role":"assistant","content":[{"type":"text","text":"Hi"}],"stop_reason":"end_turn"}"#,
+        ).await;
+        let config = LlmConfig::new()
+            .with_thinking_budget(2048)
+            .with_temperature(0.5)
+            .with_top_k(40)
+            .with_top_p(0.5)
+            .with_tool_choice(ToolChoice::Required);
+
+        let res = client(LLM::Anthropic, base_url).send_convo_message(ask("Hi"), None, Some(&config)).await.unwrap();
+        server.await.unwrap();
+        assert_eq!(res.warnings, vec![
+            "Anthropic extended thinking does not accept temperature; it was dropped".to_string(),
+            "Anthropic extended thinking does not accept top_k; it was dropped".to_string(),
+            "Anthropic extended thinking needs top_p of at least 0.95; 0.5 was raised to 0.95".to_string(),
+            "Anthropic extended thinking cannot force tool use; tool_choice was sent as auto".to_string(),
+        ]);
+
+        let err = client(LLM::Anthropic, "http://127.0.0.1:9".to_string())
+            .send_convo_message(ask("Hi"), None, Some(&config.with_strict(true)))
+            .await
+            .unwrap_err();
+        match err {
+            LlmError::UnsupportedOptions { options, .. } => assert_eq!(options, vec![
+                ConfigOption::Temperature,
+                ConfigOption::TopK,
+                ConfigOption::TopP,
+                ConfigOption::ToolChoice,
+            ]),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+}
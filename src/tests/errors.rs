@@ -0,0 +1,73 @@
+// src/tests/errors.rs
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::errors::LlmError;
+
+    #[test]
+    fn test_openai_error_classification() {
+        let body = r#"{"error":{"message":"This model's maximum context length is 128000 tokens.","type":"invalid_request_error","param":"messages","code":"context_length_exceeded"}}"#;
+        assert!(matches!(LlmError::from_openai(400, body, None), LlmError::ContextLengthExceeded { .. }));
+
+        let body = r#"{"error":{"message":"Rate limit reached","type":"requests","param":null,"code":"rate_limit_exceeded"}}"#;
+        match LlmError::from_openai(429, body, Some(Duration::from_secs(3))) {
+            LlmError::RateLimited { retry_after, .. } => assert_eq!(retry_after, Some(Duration::from_secs(3))),
+            other => panic!("Expected RateLimited, got {:?}", other),
+        }
+
+        let body = r#"{"error":{"message":"You exceeded your current quota.","type":"insufficient_quota","param":null,"code":"insufficient_quota"}}"#;
+        let err = LlmError::from_openai(429, body, None);
+        assert!(matches!(err, LlmError::QuotaExceeded { .. }));
+        assert!(!err.is_retryable());
+
+        // llama-server sends a numeric code and its own error type
+        let body = r#"{"error":{"code":400,"message":"the request exceeds the available context size","type":"exceed_context_size_error"}}"#;
+        assert!(matches!(LlmError::from_openai(400, body, None), LlmError::ContextLengthExceeded { .. }));
+    }
+
+    #[test]
+    fn test_anthropic_error_classification() {
+        let body = r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        let err = LlmError::from_anthropic(529, body, None);
+        assert!(matches!(err, LlmError::ServerOverloaded { .. }));
+        assert!(err.is_retryable());
+
+        let body = r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#;
+        let err = LlmError::from_anthropic(401, body, None);
+        assert!(matches!(err, LlmError::Authentication { .. }));
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn test_gemini_error_classification() {
+        let body = r#"{"error":{"code":429,"message":"Quota exceeded","status":"RESOURCE_EXHAUSTED","details":[{"@type":"type.googleapis.com/google.rpc.RetryInfo","retryDelay":"16s"}]}}"#;
+        assert_eq!(LlmError::from_gemini(429, body).retry_after(), Some(Duration::from_secs(16)));
+
+        let body = r#"{"error":{"code":400,"message":"API key not valid.","status":"INVALID_ARGUMENT","details":[{"@type":"type.googleapis.com/google.rpc.ErrorInfo","reason":"API_KEY_INVALID","domain":"googleapis.com"}]}}"#;
+        assert!(matches!(LlmError::from_gemini(400, body), LlmError::Authentication { .. }));
+    }
+
+    #[test]
+    fn test_unparseable_error_body_falls_back_to_status() {
+        assert!(matches!(LlmError::from_openai(502, "Bad Gateway", None), LlmError::Http { status: 502, .. }));
+        assert!(matches!(LlmError::from_gemini(503, "<html></html>"), LlmError::ServerOverloaded { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_llama_server_does_not_retry_client_errors() {
+        use crate::client::{LlmClient, ProviderConfig};
+        use crate::llm::{Access, LLM};
+        use crate::tests::{ask, mock_server_sequence};
+
+        let (base_url, server) = mock_server_sequence(vec![
+            (400, "", r#"{"error":{"code":400,"message":"bad request","type":"invalid_request_error"}}"#),
+        ]).await;
+        let client = LlmClient::new(LLM::LlamaServer, ProviderConfig::new().with_base_url(base_url)).unwrap();
+        let messages = ask("Hi");
+
+        let err = client.send_convo_message(messages, None, None).await.unwrap_err();
+        assert!(matches!(err, LlmError::InvalidRequest { .. }));
+        assert_eq!(server.await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,94 @@
+// src/tests/files.rs
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::errors::LlmError;
+    use crate::files::FileHandle;
+    use crate::gemini::map_message_parts_to_gemini;
+    use crate::llm::{Access, LLM};
+    use crate::structs::general::{DocumentPart, MediaSource, Message, MessageContent, MessagePart};
+    use crate::tests::{client, mock_server, mock_server_sequence, request_body};
+
+    #[tokio::test]
+    async fn test_gemini_resumable_upload() {
+        let (base_url, server) = mock_server_sequence(vec![
+            (200, "x-goog-upload-url: {base_url}/session\r\n", ""),
+            (503, "", r#"{"error":{"code":503,"message":"try again","status":"UNAVAILABLE"}}"#),
+            (200, "x-goog-upload-status: active\r\nx-goog-upload-size-received: 0\r\n", ""),
+            (200, "", r#"{"file":{"name":"files/abc","mimeType":"application/pdf","sizeBytes":"8","uri":"https://generativelanguage.googleapis.com/v1beta/files/abc","state":"ACTIVE"}}"#),
+        ]).await;
+        let client = client(LLM::Gemini, format!("{}/v1beta", base_url));
+
+        let file = client.upload_file(b"%PDF-1.4".to_vec(), "spec.pdf", None).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /upload/v1beta/files "), "{}", requests[0]);
+        assert!(requests[0].to_ascii_lowercase().contains("x-goog-upload-header-content-type: application/pdf"));
+        assert!(requests[1].to_ascii_lowercase().contains("x-goog-upload-command: upload, finalize"));
+        assert!(requests[2].to_ascii_lowercase().contains("x-goog-upload-command: query"));
+        assert!(requests[3].ends_with("%PDF-1.4"));
+        assert_eq!(file.id, "files/abc");
+        assert_eq!(file.size_bytes, Some(8));
+
+        let parts = map_message_parts_to_gemini(vec![file.to_part()]).unwrap();
+        let file_data = parts[0].file_data.as_ref().unwrap();
+        assert_eq!(file_data.file_uri, "https://generativelanguage.googleapis.com/v1beta/files/abc");
+        assert_eq!(file_data.mime_type.as_deref(), Some("application/pdf"));
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_file_upload_and_reference() {
+        let (base_url, server) = mock_server(
+            r#"{"id":"file_1","type":"file","filename":"spec.pdf","mime_type":"application/pdf","size_bytes":8}"#,
+        ).await;
+        let file = client(LLM::Anthropic, base_url).upload_file(b"%PDF-1.4".to_vec(), "spec.pdf", None).await.unwrap();
+
+        let raw = server.await.unwrap();
+        assert!(raw.starts_with("POST /files "));
+        assert!(raw.contains("anthropic-beta: files-api-2025-04-14"));
+        assert!(raw.contains(r#"filename="spec.pdf""#));
+        assert_eq!(file.to_part(), MessagePart::Document(DocumentPart::new(MediaSource::File {
+            file_id: "file_1".to_string(),
+            media_type: Some("application/pdf".to_string()),
+        })));
+
+        let (base_url, server) = mock_server(
+            r#"{"id":"msg_1","role":"assistant","content":[{"type":"text","text":"Done"}],"stop_reason":"end_turn"}"#,
+        ).await;
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: MessageContent::Array(vec![MessagePart::text("Summarize this."), file.into()]),
+        }];
+        client(LLM::Anthropic, base_url).send_convo_message(messages, None, None).await.unwrap();
+
+        let raw = server.await.unwrap();
+        assert!(raw.contains("anthropic-beta: files-api-2025-04-14"));
+        let body = request_body(&raw);
+        assert_eq!(body["messages"][0]["content"][1]["source"], json!({ "type": "file", "file_id": "file_1" }));
+    }
+
+    #[tokio::test]
+    async fn test_openai_list_files_pages() {
+        let (base_url, server) = mock_server_sequence(vec![
+            (200, "", r#"{"object":"list","data":[{"id":"file-1","bytes":8,"filename":"a.pdf","purpose":"user_data"}],"has_more":true,"last_id":"file-1"}"#),
+            (200, "", r#"{"object":"list","data":[{"id":"file-2","bytes":3,"filename":"b.png","purpose":"user_data"}],"has_more":false}"#),
+        ]).await;
+
+        let files = client(LLM::OpenAI, base_url).list_files().await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[1].starts_with("GET /files?limit=100&after=file-1 "), "{}", requests[1]);
+        assert_eq!(files.iter().map(|f| f.id.as_str()).collect::<Vec<_>>(), ["file-1", "file-2"]);
+        assert_eq!(files[1].media_type.as_deref(), Some("image/png"));
+
+        let part = FileHandle { media_type: Some("application/pdf".to_string()), ..files[0].clone() }.to_part();
+        assert_eq!(serde_json::to_value(&part).unwrap(), json!({ "type": "file", "file": { "file_id": "file-1" } }));
+    }
+
+    #[tokio::test]
+    async fn test_files_unsupported() {
+        let err = client(LLM::LlamaServer, "http://127.0.0.1:9".to_string()).list_files().await.unwrap_err();
+        assert!(matches!(err, LlmError::Unsupported { .. }), "{:?}", err);
+    }
+}
@@ -19,7 +19,7 @@ mod tests {
             }
             Err(err) => {
                 eprintln!("Error: {}", err);
-                assert!(false, "Call to Anthropic API failed");
+                panic!("Call to Anthropic API failed");
             }
         }
     }
@@ -52,7 +52,7 @@ mod tests {
             }
             Err(err) => {
                 eprintln!("Error: {}", err);
-                assert!(false, "Call to Anthropic API failed");
+                panic!("Call to Anthropic API failed");
             }
         }
     }
@@ -79,7 +79,8 @@ mod tests {
                     full_text.push_str(&t);
                 }
                 LlmChunk::Reasoning(r) => eprintln!("\n[Reasoning]: {}", r),
-                LlmChunk::Done => break,
+                LlmChunk::Done { .. } => break,
+                _ => {}
             }
         }
 
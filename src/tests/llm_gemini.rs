@@ -158,19 +158,11 @@ mod tests {
         let messages = vec![Message {
             role: "user".to_string(),
             content: MessageContent::Array(vec![
-                MessagePart {
-                    r#type: "text".to_string(),
-                    text: Some("Describe this image:".to_string()),
-                    image_url: None,
-                },
-                MessagePart {
-                    r#type: "image_url".to_string(),
-                    text: None,
-                    image_url: Some(ImageSource::Base64 {
-                        media_type: "image/png".to_string(),
-                        data: base64_data.to_string(),
-                    }),
-                },
+                MessagePart::Text("Describe this image:".to_string()),
+                MessagePart::Image(ImageSource::Base64 {
+                    media_type: "image/png".to_string(),
+                    data: base64_data.to_string(),
+                }.into()),
             ]),
         }];
 
@@ -202,12 +194,80 @@ mod tests {
                     full_text.push_str(&t);
                 }
                 LlmChunk::Reasoning(r) => eprintln!("\n[Thought]: {}", r),
-                LlmChunk::Done => break,
+                LlmChunk::Done { .. } => break,
+                _ => {}
             }
         }
 
         assert!(!full_text.is_empty(), "Gemini stream should return text");
     }
+
+    #[test]
+    fn test_gemini_generation_config_mapping() {
+        let config = LlmConfig::new()
+            .with_temperature(0.2)
+            .with_max_tokens(256)
+            .with_stop(vec!["END".to_string()])
+            .with_top_k(40)
+            .with_top_p(0.5)
+            .with_candidate_count(1)
+            .with_seed(7)
+            .with_thinking_budget(1024);
+
+        let generation_config = crate::gemini::gemini_generation_config_from_config(&config).unwrap();
+
+        assert_eq!(serde_json::to_value(&generation_config).unwrap(), serde_json::json!({
+            "temperature": 0.2,
+            "maxOutputTokens": 256,
+            "stopSequences": ["END"],
+            "topK": 40,
+            "topP": 0.5,
+            "candidateCount": 1,
+            "seed": 7,
+            "thinkingConfig": { "thinkingBudget": 1024, "includeThoughts": true },
+        }));
+
+        let no_thinking = crate::gemini::gemini_generation_config_from_config(&LlmConfig::new().with_thinking_budget(0)).unwrap();
+        assert_eq!(serde_json::to_value(&no_thinking).unwrap(), serde_json::json!({ "thinkingConfig": { "thinkingBudget": 0 } }));
+        assert!(crate::gemini::gemini_generation_config_from_config(&LlmConfig::new()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_gemini_thought_parts_are_reasoning() {
+        use crate::tests::{ask, client, mock_server};
+
+        let (base_url, server) = mock_server(concat!(
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"Weighing it up. \",\"thought\":true}]}}]}\n",
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"Done.\",\"thought\":true},{\"text\":\"{\\\"a\\\":1}\"}]},\"finishReason\":\"STOP\"}]}\n",
+        )).await;
+        let client = client(LLM::Gemini, base_url);
+        let messages = ask("Hi");
+
+        let chunks: Vec<LlmChunk> = client
+            .send_streaming_convo_message(messages, Some("gemini-test"), None)
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        server.await.unwrap();
+
+        let reasoning: Vec<&str> = chunks.iter().filter_map(|c| match c { LlmChunk::Reasoning(r) => Some(r.as_str()), _ => None }).collect();
+        let text: Vec<&str> = chunks.iter().filter_map(|c| match c { LlmChunk::Text(t) => Some(t.as_str()), _ => None }).collect();
+        assert_eq!(reasoning, vec!["Weighing it up. ", "Done."]);
+        assert_eq!(text, vec!["{\"a\":1}"]);
+
+        let response: crate::gemini::types::GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{ "content": { "role": "model", "parts": [
+                { "text": "Weighing it up. ", "thought": true },
+                { "text": "Done.", "thought": true },
+                { "text": "Answer" },
+            ] }, "finishReason": "STOP" }],
+        })).unwrap();
+        let response = crate::gemini::gemini_to_llm_response(response).unwrap();
+        assert_eq!(response.reasoning.as_deref(), Some("Weighing it up. Done."));
+        assert_eq!(response.text, "Answer");
+    }
 }
 
 #[cfg(test)]
@@ -124,7 +124,8 @@ mod tests {
                     full_text.push_str(&t);
                 }
                 LlmChunk::Reasoning(r) => eprintln!("\n[Thinking]: {}", r),
-                LlmChunk::Done => break,
+                LlmChunk::Done { .. } => break,
+                _ => {}
             }
         }
 
@@ -141,19 +142,11 @@ mod tests {
         let messages = vec![Message {
             role: "user".to_string(),
             content: MessageContent::Array(vec![
-                MessagePart {
-                    r#type: "text".to_string(),
-                    text: Some("What do you see in this image?".to_string()),
-                    image_url: None,
-                },
-                MessagePart {
-                    r#type: "image_url".to_string(),
-                    text: None,
-                    image_url: Some(ImageSource::Base64 {
-                        media_type: "image/png".to_string(),
-                        data: base64_data.to_string(),
-                    }),
-                },
+                MessagePart::Text("What do you see in this image?".to_string()),
+                MessagePart::Image(ImageSource::Base64 {
+                    media_type: "image/png".to_string(),
+                    data: base64_data.to_string(),
+                }.into()),
             ]),
         }];
 
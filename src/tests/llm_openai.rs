@@ -19,7 +19,7 @@ mod tests {
             }
             Err(err) => {
                 eprintln!("Error: {}", err);
-                assert!(false, "Call to OpenAI API failed");
+                panic!("Call to OpenAI API failed");
             }
         }
     }
@@ -53,7 +53,7 @@ mod tests {
             }
             Err(err) => {
                 eprintln!("Error: {}", err);
-                assert!(false, "Call to OpenAI API failed");
+                panic!("Call to OpenAI API failed");
             }
         }
     }
@@ -0,0 +1,171 @@
+// src/tests/media.rs
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::client::{LlmClient, ProviderConfig};
+    use crate::gemini::map_message_parts_to_gemini;
+    use crate::llm::{Access, LLM};
+    use crate::media::{download, inline_media_urls, inline_media_urls_with_client, mime_type_from_extension, sniff_mime_type};
+    use crate::errors::LlmError;
+    use crate::structs::general::{DocumentPart, ImageDetail, ImagePart, MediaSource, Message, MessageContent, MessagePart};
+    use crate::tests::{client, mock_server, request_body};
+
+    fn image_url_message(url: &str) -> Vec<Message> {
+        vec![Message {
+            role: "user".to_string(),
+            content: MessageContent::Array(vec![
+                MessagePart::text("What is this?"),
+                MessagePart::Image(MediaSource::Url { url: url.to_string() }.into()),
+            ]),
+        }]
+    }
+
+    #[test]
+    fn test_mime_detection() {
+        assert_eq!(sniff_mime_type(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A]), Some("image/png"));
+        assert_eq!(sniff_mime_type(b"%PDF-1.7"), Some("application/pdf"));
+        assert_eq!(sniff_mime_type(b"hello"), None);
+        assert_eq!(sniff_mime_type(b"\0\0\0\x18ftypheic\0\0\0\0"), Some("image/heic"));
+        assert_eq!(sniff_mime_type(b"\0\0\0\x18ftypmif1\0\0\0\0"), Some("image/heic"));
+        assert_eq!(sniff_mime_type(b"\0\0\0\x20ftypM4A \0\0\0\0"), Some("audio/mp4"));
+        assert_eq!(sniff_mime_type(b"\0\0\0\x14ftypqt  \0\0\0\0"), Some("video/quicktime"));
+        assert_eq!(sniff_mime_type(b"\0\0\0\x20ftypisom\0\0\x02\0"), Some("video/mp4"));
+        assert_eq!(sniff_mime_type(b"\0\0\0\x1cftypavif\0\0\0\0mif1miaf"), Some("image/avif"));
+        assert_eq!(sniff_mime_type(b"\0\0\0\x18ftyp3gp5\0\0\x02\0"), Some("video/3gpp"));
+        assert_eq!(sniff_mime_type(b"\0\0\0\x18ftypcrx \0\0\0\x01"), None);
+        assert!(matches!(MessagePart::from_bytes(b"\0\0\0\x1cftypavif\0\0\0\0mif1miaf"), Ok(MessagePart::Image(_))));
+        assert_eq!(mime_type_from_extension("https://example.com/cat.JPG?size=large"), Some("image/jpeg"));
+        assert_eq!(mime_type_from_extension("https://example.com/download"), None);
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_image_url_source() {
+        let (base_url, server) = mock_server(
+            r#"{"id":"msg_1","role":"assistant","content":[{"type":"text","text":"A cat"}],"stop_reason":"end_turn"}"#,
+        ).await;
+        let client = client(LLM::Anthropic, base_url);
+
+        client.send_convo_message(image_url_message("https://example.com/cat.png"), None, None).await.unwrap();
+
+        let raw = server.await.unwrap();
+        let body = request_body(&raw);
+        let source = &body["messages"][0]["content"][1]["source"];
+        assert_eq!(source["type"], "url");
+        assert_eq!(source["url"], "https://example.com/cat.png");
+    }
+
+    #[test]
+    fn test_gemini_image_url_file_data() {
+        let parts = map_message_parts_to_gemini(image_url_message("https://example.com/cat.webp").remove(0).content.as_parts()).unwrap();
+        let file_data = parts[1].file_data.as_ref().unwrap();
+        assert_eq!(file_data.file_uri, "https://example.com/cat.webp");
+        assert_eq!(file_data.mime_type.as_deref(), Some("image/webp"));
+    }
+
+    #[tokio::test]
+    async fn test_inline_media_urls() {
+        let (base_url, server) = mock_server("%PDF-1.4").await;
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: MessageContent::Array(vec![
+                MessagePart::Document(DocumentPart::new(MediaSource::Url { url: format!("{}/download", base_url) }).with_title("Spec")),
+            ]),
+        }];
+
+        let inlined = inline_media_urls(messages).await.unwrap();
+        server.await.unwrap();
+
+        assert_eq!(inlined[0].content.as_parts()[0], MessagePart::Document(DocumentPart::new(MediaSource::Base64 {
+            media_type: "application/pdf".to_string(),
+            data: "JVBERi0xLjQ=".to_string(),
+        }).with_title("Spec")));
+    }
+
+    #[tokio::test]
+    async fn test_inline_media_urls_with_client() {
+        let (base_url, server) = mock_server("%PDF-1.4").await;
+        let client = LlmClient::new(LLM::OpenAI, ProviderConfig::new().with_api_key("sk-secret").with_header("OpenAI-Organization", "org-1")).unwrap();
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: MessageContent::Array(vec![MessagePart::Document(DocumentPart::new(MediaSource::Url { url: format!("{}/spec.pdf", base_url) }))]),
+        }];
+
+        let inlined = inline_media_urls_with_client(&client, messages).await.unwrap();
+        assert!(matches!(&inlined[0].content.as_parts()[0], MessagePart::Document(DocumentPart { source: MediaSource::Base64 { .. }, .. })));
+        let raw = server.await.unwrap().to_ascii_lowercase();
+        assert!(!raw.contains("sk-secret") && !raw.contains("openai-organization"));
+
+        let (base_url, server) = mock_server("%PDF-1.4").await;
+        let url = format!("{}/spec.pdf", base_url);
+        let err = download(client.get_external(&url), &url, 4).await.unwrap_err();
+        server.await.unwrap();
+        assert!(matches!(err, LlmError::InvalidRequest { ref message } if message.contains("byte limit")), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_data_uri_sources() {
+        assert_eq!(MediaSource::strip_base64_prefix("data:audio/wav;base64,UklGRg=="), "UklGRg==");
+        assert_eq!(MediaSource::from_data_uri("data:text/plain;charset=utf-8;base64,aGk="), Some(MediaSource::Base64 {
+            media_type: "text/plain".to_string(),
+            data: "aGk=".to_string(),
+        }));
+
+        let (base_url, server) = mock_server(
+            r#"{"id":"msg_1","role":"assistant","content":[{"type":"text","text":"A dot"}],"stop_reason":"end_turn"}"#,
+        ).await;
+        let client = client(LLM::Anthropic, base_url);
+
+        client.send_convo_message(image_url_message("data:image/png;base64,iVBORw0KGgo="), None, None).await.unwrap();
+
+        let raw = server.await.unwrap();
+        let body = request_body(&raw);
+        assert_eq!(body["messages"][0]["content"][1]["source"], json!({
+            "type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="
+        }));
+    }
+
+    #[test]
+    fn test_parts_from_bytes() {
+        let png = [0x89, b'P', b'N', b'G', 0x0D, 0x0A];
+        assert_eq!(MessagePart::image_from_bytes(&png).unwrap(), MessagePart::Image(ImagePart::new(MediaSource::Base64 {
+            media_type: "image/png".to_string(),
+            data: "iVBORw0K".to_string(),
+        })));
+        assert!(matches!(MessagePart::from_bytes(b"%PDF-1.4").unwrap(), MessagePart::Document(_)));
+        assert!(matches!(MessagePart::image_from_bytes(b"%PDF-1.4"), Err(LlmError::InvalidRequest { .. })));
+        assert!(matches!(MessagePart::from_bytes(b"hello"), Err(LlmError::InvalidRequest { .. })));
+    }
+
+    #[test]
+    fn test_openai_image_detail() {
+        let part = MessagePart::Image(ImagePart::new(MediaSource::Url { url: "https://example.com/cat.png".to_string() })
+            .with_detail(ImageDetail::Low));
+        assert_eq!(serde_json::to_value(&part).unwrap(), json!({
+            "type": "image_url",
+            "image_url": { "url": "https://example.com/cat.png", "detail": "low" }
+        }));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_fit_image() {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+        use crate::media::{fit_image, ImageLimits};
+
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgb8(4000, 1000)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let source = MediaSource::Base64 { media_type: "image/png".to_string(), data: STANDARD.encode(&png) };
+
+        let fitted = fit_image(&source, &ImageLimits::for_provider(&LLM::Anthropic)).unwrap();
+        let MediaSource::Base64 { media_type, data } = fitted else { panic!("expected base64") };
+        assert_eq!(media_type, "image/jpeg");
+        let img = image::load_from_memory(&STANDARD.decode(data).unwrap()).unwrap();
+        assert_eq!((img.width(), img.height()), (1568, 392));
+
+        let small = ImageLimits { max_dimension: 8000, max_pixels: u64::MAX, max_bytes: usize::MAX };
+        assert_eq!(fit_image(&source, &small).unwrap(), source);
+    }
+}
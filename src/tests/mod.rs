@@ -6,12 +6,91 @@ pub mod embeddings;
 pub mod multimodal;
 pub mod openai;
 pub mod anthropic;
+pub mod errors;
+pub mod tools;
+pub mod usage;
+pub mod client;
+pub mod provider;
+pub mod system_prompt;
+pub mod normalize;
+pub mod media;
+pub mod files;
+pub mod structured;
+pub mod config;
+pub mod options;
 
 use std::env;
 use dotenv::dotenv;
-
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use crate::client::{LlmClient, ProviderConfig};
+use crate::llm::LLM;
+use crate::structs::general::{Message, MessageContent};
 
 pub fn get_base64_var() -> String {
     dotenv().ok(); 
     env::var("BASE64_DATA").unwrap_or_else(|_| "default_base64_value".to_string())
-}
\ No newline at end of file
+}
+
+/// A conversation of one user message.
+pub fn ask(text: &str) -> Vec<Message> {
+    vec![Message { role: "user".to_string(), content: MessageContent::Text(text.to_string()) }]
+}
+
+/// A client for `provider` with a dummy key that sends every request to `base_url`.
+pub fn client(provider: LLM, base_url: String) -> LlmClient {
+    LlmClient::new(provider, ProviderConfig::new().with_api_key("k").with_base_url(base_url)).unwrap()
+}
+
+/// The JSON body of a raw request captured by `mock_server`.
+pub fn request_body(raw: &str) -> Value {
+    serde_json::from_str(&raw[raw.find("\r\n\r\n").unwrap() + 4..]).unwrap()
+}
+
+/// Serves a single canned JSON response and hands back the raw request it received.
+pub async fn mock_server(body: &'static str) -> (String, JoinHandle<String>) {
+    let (base_url, handle) = mock_server_sequence(vec![(200, "", body)]).await;
+    (base_url, tokio::spawn(async move { handle.await.unwrap().remove(0) }))
+}
+
+/// Serves canned `(status, extra header lines, JSON body)` responses in order, one per
+/// connection, and hands back the raw requests. `{base_url}` in the headers is replaced with
+/// the server's own URL.
+pub async fn mock_server_sequence(responses: Vec<(u16, &'static str, &'static str)>) -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let own_url = base_url.clone();
+
+    let handle = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for (status, headers, body) in responses {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let content_length = text.lines()
+                        .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + content_length { break; }
+                }
+                if n == 0 { break; }
+            }
+            let headers = headers.replace("{base_url}", &own_url);
+            let response = format!(
+                "HTTP/1.1 {} Mock\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n{}\r\n{}",
+                status, body.len(), headers, body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            requests.push(String::from_utf8_lossy(&request).to_string());
+        }
+        requests
+    });
+
+    (base_url, handle)
+}
@@ -1,7 +1,22 @@
 #[cfg(test)]
 mod tests {
+    use serde_json::json;
+    use crate::client::{LlmClient, ProviderConfig};
+    use crate::errors::LlmError;
+    use crate::gemini::map_message_parts_to_gemini;
     use crate::llm::{LLM, Access};
-    use crate::structs::general::{Message, MessageContent, MessagePart, ImageSource};
+    use crate::structs::general::{Message, MessageContent, MessagePart, ImageSource, MediaSource, DocumentPart, ImageDetail, ImagePart, ToolCall, ToolResult};
+    use crate::tests::{client, mock_server, request_body};
+
+    fn audio_message() -> Vec<Message> {
+        vec![Message {
+            role: "user".to_string(),
+            content: MessageContent::Array(vec![
+                MessagePart::text("Transcribe this."),
+                MessagePart::Audio(MediaSource::Base64 { media_type: "audio/wav".to_string(), data: "UklGRg==".to_string() }),
+            ]),
+        }]
+    }
 
     #[tokio::test]
     async fn test_openai_multimodal_serialization() {
@@ -9,22 +24,159 @@ mod tests {
         let messages = vec![Message {
             role: "user".to_string(),
             content: MessageContent::Array(vec![
-                MessagePart {
-                    r#type: "text".to_string(),
-                    text: Some("What is in this image?".to_string()),
-                    image_url: None,
-                },
-                MessagePart {
-                    r#type: "image_url".to_string(),
-                    text: None,
-                    image_url: Some(ImageSource::Url { 
-                        url: "https://example.com/image.png".to_string() 
-                    }),
-                },
+                MessagePart::Text("What is in this image?".to_string()),
+                MessagePart::Image(ImageSource::Url { 
+                    url: "https://example.com/image.png".to_string() 
+                }.into()),
             ]),
         }];
 
         let res = llm.send_convo_message(messages, Some("gpt-4o"), None).await;
         assert!(res.is_ok(), "OpenAI failed to process image URL: {:?}", res.err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_message_parts_openai_wire_shape() {
+        let message = Message {
+            role: "user".to_string(),
+            content: MessageContent::Array(vec![
+                MessagePart::text("Describe this."),
+                MessagePart::Image(ImageSource::Base64 { media_type: "image/png".to_string(), data: "iVBORw0KGgo=".to_string() }.into()),
+            ]),
+        };
+
+        assert_eq!(serde_json::to_value(&message).unwrap(), json!({
+            "role": "user",
+            "content": [
+                { "type": "text", "text": "Describe this." },
+                { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw0KGgo=" } }
+            ]
+        }));
+    }
+
+    #[test]
+    fn test_gemini_inlines_any_base64_media() {
+        let parts = map_message_parts_to_gemini(audio_message().remove(0).content.as_parts()).unwrap();
+        assert_eq!(parts.len(), 2);
+        let inline = parts[1].inline_data.as_ref().unwrap();
+        assert_eq!(inline.mime_type, "audio/wav");
+        assert_eq!(inline.data, "UklGRg==");
+    }
+
+    fn video_message() -> Vec<Message> {
+        vec![Message {
+            role: "user".to_string(),
+            content: MessageContent::Array(vec![
+                MessagePart::text("Summarize this clip."),
+                MessagePart::Video(MediaSource::Url { url: "https://www.youtube.com/watch?v=abc".to_string() }),
+            ]),
+        }]
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_parts_are_errors() {
+        // Mapping fails before any request is sent, so the base URL is never contacted
+        let cases = [
+            (LLM::Anthropic, audio_message(), "audio"),
+            (LLM::OpenAI, video_message(), "video"),
+            (LLM::LlamaServer, pdf_message(), "document"),
+            (LLM::OpenAICompatible { base_url: String::new(), api_key_env: None, headers: Vec::new() }, pdf_message(), "document"),
+        ];
+        for (provider, messages, modality) in cases {
+            let name = provider.name().to_string();
+            let client = LlmClient::new(provider, ProviderConfig::new()
+                .with_api_key("k")
+                .with_base_url("http://127.0.0.1:9")).unwrap();
+            let err = client.send_convo_message(messages, Some("some-model"), None).await.unwrap_err();
+            assert!(matches!(err, LlmError::Unsupported { ref message } if message.contains(modality) && message.starts_with(&name)), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn test_message_content_round_trip() {
+        let content = MessageContent::Array(vec![
+            MessagePart::text("Look at these."),
+            MessagePart::Image(ImagePart::new(MediaSource::Url { url: "https://example.com/a.png".to_string() }).with_detail(ImageDetail::Low)),
+            MessagePart::Image(ImageSource::Base64 { media_type: "image/png".to_string(), data: "iVBORw0KGgo=".to_string() }.into()),
+            MessagePart::Image(MediaSource::File { file_id: "file-1".to_string(), media_type: Some("image/png".to_string()) }.into()),
+            MessagePart::Audio(MediaSource::Base64 { media_type: "audio/wav".to_string(), data: "UklGRg==".to_string() }),
+            MessagePart::Audio(MediaSource::Base64 { media_type: "audio/ogg".to_string(), data: "T2dnUw==".to_string() }),
+            MessagePart::Video(MediaSource::Url { url: "https://www.youtube.com/watch?v=abc".to_string() }),
+            MessagePart::Document(DocumentPart::new(MediaSource::Base64 { media_type: "application/pdf".to_string(), data: "JVBERi0xLjQ=".to_string() }).with_title("spec.pdf")),
+            MessagePart::Document(DocumentPart::new(MediaSource::Base64 { media_type: "text/plain".to_string(), data: "aGVsbG8=".to_string() })
+                .with_context("Draft from March")
+                .with_citations(true)),
+            MessagePart::ToolCall(ToolCall { id: "call_1".to_string(), name: "get_weather".to_string(), arguments: json!({ "city": "Paris" }), signature: None }),
+            MessagePart::ToolResult(ToolResult { tool_call_id: "call_1".to_string(), name: "get_weather".to_string(), content: "18C".to_string(), is_error: false }),
+        ]);
+
+        let stored = serde_json::to_string(&content).unwrap();
+        assert_eq!(serde_json::from_str::<MessageContent>(&stored).unwrap(), content);
+        assert_eq!(serde_json::from_str::<MessageContent>(r#""Hi""#).unwrap(), MessageContent::Text("Hi".to_string()));
+    }
+
+    #[test]
+    fn test_openai_input_audio() {
+        let message = audio_message().remove(0);
+        assert_eq!(serde_json::to_value(&message).unwrap()["content"][1], json!({
+            "type": "input_audio",
+            "input_audio": { "data": "UklGRg==", "format": "wav" }
+        }));
+    }
+
+    #[test]
+    fn test_gemini_video_file_data() {
+        let parts = map_message_parts_to_gemini(video_message().remove(0).content.as_parts()).unwrap();
+        assert_eq!(parts[1].file_data.as_ref().unwrap().file_uri, "https://www.youtube.com/watch?v=abc");
+    }
+
+    fn pdf_message() -> Vec<Message> {
+        let pdf = DocumentPart::new(MediaSource::Base64 { media_type: "application/pdf".to_string(), data: "JVBERi0xLjQ=".to_string() })
+            .with_title("spec.pdf")
+            .with_context("Draft from March")
+            .with_citations(true);
+        let notes = DocumentPart::new(MediaSource::Base64 { media_type: "text/plain".to_string(), data: "aGVsbG8=".to_string() });
+        vec![Message {
+            role: "user".to_string(),
+            content: MessageContent::Array(vec![
+                MessagePart::text("Summarize these."),
+                MessagePart::Document(pdf),
+                MessagePart::Document(notes),
+            ]),
+        }]
+    }
+
+    #[test]
+    fn test_openai_file_part() {
+        let message = Message {
+            role: "user".to_string(),
+            content: MessageContent::Array(pdf_message().remove(0).content.as_parts()[..2].to_vec()),
+        };
+        assert_eq!(serde_json::to_value(&message).unwrap()["content"][1], json!({
+            "type": "file",
+            "file": { "filename": "spec.pdf", "file_data": "data:application/pdf;base64,JVBERi0xLjQ=" }
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_document_blocks() {
+        let (base_url, server) = mock_server(
+            r#"{"id":"msg_1","role":"assistant","content":[{"type":"text","text":"Done"}],"stop_reason":"end_turn"}"#,
+        ).await;
+        let client = client(LLM::Anthropic, base_url);
+
+        client.send_convo_message(pdf_message(), None, None).await.unwrap();
+
+        let raw = server.await.unwrap();
+        let body = request_body(&raw);
+        let content = &body["messages"][0]["content"];
+        assert_eq!(content[1], json!({
+            "type": "document",
+            "source": { "type": "base64", "media_type": "application/pdf", "data": "JVBERi0xLjQ=" },
+            "title": "spec.pdf",
+            "context": "Draft from March",
+            "citations": { "enabled": true }
+        }));
+        assert_eq!(content[2]["source"], json!({ "type": "text", "media_type": "text/plain", "data": "hello" }));
+    }
+}
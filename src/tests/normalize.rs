@@ -0,0 +1,103 @@
+// src/tests/normalize.rs
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::errors::LlmError;
+    use crate::llm::LLM;
+    use crate::normalize::normalize_messages;
+    use crate::structs::general::{Message, MessageContent, MessagePart, ToolCall};
+
+    fn text(role: &str, text: &str) -> Message {
+        Message { role: role.to_string(), content: text.into() }
+    }
+
+    /// A tool-calling history in the shape OpenAI expects.
+    fn openai_history() -> Vec<Message> {
+        let call = |id: &str| ToolCall { id: id.to_string(), name: "get_weather".to_string(), arguments: json!({}), signature: None };
+        vec![
+            text("system", "Be brief."),
+            text("user", "Weather in Paris and Rome?"),
+            Message::assistant_tool_calls(None, vec![call("call_1"), call("call_2")]),
+            Message::tool_result("call_1", "get_weather", "18C"),
+            Message::tool_result("call_2", "get_weather", "24C"),
+            text("user", "Which is warmer?"),
+        ]
+    }
+
+    fn roles(messages: &[Message]) -> Vec<&str> {
+        messages.iter().map(|m| m.role.as_str()).collect()
+    }
+
+    #[test]
+    fn test_gemini_merges_tool_results_into_user_turn() {
+        let messages = normalize_messages(openai_history(), &LLM::Gemini).unwrap();
+        assert_eq!(roles(&messages), vec!["system", "user", "model", "user"]);
+
+        let parts = messages[3].content.as_parts();
+        assert_eq!(parts.len(), 3);
+        assert!(matches!(parts[0], MessagePart::ToolResult(_)));
+        assert!(matches!(parts[1], MessagePart::ToolResult(_)));
+        assert_eq!(parts[2], MessagePart::text("Which is warmer?"));
+    }
+
+    #[test]
+    fn test_anthropic_role_names_and_merging() {
+        let messages = vec![
+            text("user", "Hi"),
+            text("user", "Are you there?"),
+            text("model", "Yes."),
+            text("developer", "Use French."),
+            text("assistant", "Oui."),
+        ];
+        let messages = normalize_messages(messages, &LLM::Anthropic).unwrap();
+        assert_eq!(roles(&messages), vec!["system", "user", "assistant"]);
+        assert!(matches!(&messages[1].content, MessageContent::Array(parts) if parts.len() == 2));
+        assert_eq!(messages[2].content.extract_text(), "Yes.\nOui.");
+    }
+
+    #[test]
+    fn test_openai_only_renames_model() {
+        let messages = vec![text("user", "Hi"), text("model", "Hello"), text("model", "Again")];
+        let messages = normalize_messages(messages, &LLM::OpenAI).unwrap();
+        assert_eq!(roles(&messages), vec!["user", "assistant", "assistant"]);
+    }
+
+    #[test]
+    fn test_openai_splits_merged_tool_results() {
+        let merged = normalize_messages(openai_history(), &LLM::Anthropic).unwrap();
+        let messages = normalize_messages(merged, &LLM::OpenAI).unwrap();
+        assert_eq!(roles(&messages), vec!["system", "user", "assistant", "tool", "tool", "user"]);
+
+        let results: Vec<serde_json::Value> = messages[3..5].iter().map(|m| serde_json::to_value(m).unwrap()).collect();
+        assert_eq!(results[0], json!({ "role": "tool", "tool_call_id": "call_1", "content": "18C" }));
+        assert_eq!(results[1], json!({ "role": "tool", "tool_call_id": "call_2", "content": "24C" }));
+        assert_eq!(messages[5].content.extract_text(), "Which is warmer?");
+    }
+
+    #[test]
+    fn test_impossible_sequences() {
+        let orphan = vec![text("user", "Hi"), Message::tool_result("call_1", "get_weather", "18C")];
+        let unknown = vec![text("narrator", "Once upon a time")];
+        let system_only = vec![text("system", "Be brief.")];
+
+        for messages in [orphan, unknown, system_only] {
+            let err = normalize_messages(messages, &LLM::Anthropic).unwrap_err();
+            assert!(matches!(err, LlmError::InvalidRequest { .. }));
+        }
+    }
+
+    #[test]
+    fn test_assistant_first_rejected() {
+        let messages = vec![text("system", "Be brief."), text("assistant", "Hello!"), text("user", "Hi")];
+
+        for provider in [LLM::Anthropic, LLM::Gemini] {
+            match normalize_messages(messages.clone(), &provider).unwrap_err() {
+                LlmError::InvalidRequest { message } => assert!(message.contains("must start with a user message")),
+                other => panic!("unexpected error {:?}", other),
+            }
+        }
+        // Chat completions accept an opening assistant turn
+        assert_eq!(roles(&normalize_messages(messages, &LLM::OpenAI).unwrap()), ["system", "assistant", "user"]);
+    }
+}
@@ -83,7 +83,7 @@
             }
             Err(err) => {
                 eprintln!("Error: {}", err);
-                assert!(false, "Call to OpenAI API with reasoning model failed");
+                panic!("Call to OpenAI API with reasoning model failed");
             }
         }
     }
@@ -151,10 +151,11 @@
                                     eprintln!("\n[Reasoning]: {}", r);
                                     reasoning_text.push_str(&r);
                                 }
-                                LlmChunk::Done => {
+                                LlmChunk::Done { .. } => {
                                     received_done = true;
                                     eprintln!("\n--- Stream Done ---");
                                 }
+                                _ => {}
                             }
                         }
                         Err(e) => panic!("Stream encountered an error: {}", e),
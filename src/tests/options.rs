@@ -0,0 +1,115 @@
+// src/tests/options.rs
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use serde_json::{json, Map, Value};
+    use crate::config::LlmConfig;
+    use crate::llm::{Access, LLM};
+    use crate::options::{AnthropicCacheStrategy, AnthropicOptions, GeminiOptions, LlamaOptions, OpenAiOptions};
+    use crate::structs::general::{Message, MessageContent};
+    use crate::tests::{client, mock_server, request_body};
+
+    const OPENAI_REPLY: &str = r#"{"choices":[{"message":{"content":"Hi"},"finish_reason":"stop"}]}"#;
+
+    fn messages() -> Vec<Message> {
+        vec![
+            Message { role: "system".to_string(), content: MessageContent::Text("Be brief.".to_string()) },
+            Message { role: "user".to_string(), content: MessageContent::Text("Hi".to_string()) },
+        ]
+    }
+
+    /// One config carrying every provider's block.
+    fn config() -> LlmConfig {
+        let mut metadata = Map::new();
+        metadata.insert("tenant".to_string(), json!("acme"));
+        LlmConfig::new()
+            .with_openai_options(OpenAiOptions::new()
+                .with_reasoning_effort("low")
+                .with_logit_bias(BTreeMap::from([("50256".to_string(), -100)])))
+            .with_anthropic_options(AnthropicOptions::new()
+                .with_beta("interleaved-thinking-2025-05-14")
+                .with_metadata(metadata)
+                .with_cache_strategy(AnthropicCacheStrategy::System))
+            .with_gemini_options(GeminiOptions::new()
+                .with_safety_setting("HARM_CATEGORY_HARASSMENT", "BLOCK_ONLY_HIGH")
+                .with_response_modalities(vec!["TEXT".to_string()])
+                .with_media_resolution("MEDIA_RESOLUTION_LOW"))
+            .with_llama_options(LlamaOptions::new().with_min_p(0.05).with_id_slot(1))
+    }
+
+    async fn send(provider: LLM, reply: &'static str, config: LlmConfig) -> (String, Value) {
+        let (base_url, server) = mock_server(reply).await;
+        let client = client(provider, base_url);
+        client.send_convo_message(messages(), Some("test-model"), Some(&config)).await.unwrap();
+        let raw = server.await.unwrap();
+        let body = request_body(&raw);
+        (raw, body)
+    }
+
+    #[tokio::test]
+    async fn test_openai_reads_only_its_block() {
+        let config = config().with_seed(7).with_user_id("user-123").with_service_tier("flex");
+        let (_, body) = send(LLM::OpenAI, OPENAI_REPLY, config).await;
+
+        assert_eq!(body["reasoning_effort"], "low");
+        assert_eq!(body["seed"], 7);
+        assert_eq!(body["user"], "user-123");
+        assert_eq!(body["service_tier"], "flex");
+        assert_eq!(body["logit_bias"], json!({ "50256": -100 }));
+        assert!(body.get("min_p").is_none());
+        assert!(body.get("safetySettings").is_none());
+        assert!(body.get("metadata").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_llama_reads_only_its_block() {
+        let (_, body) = send(LLM::LlamaServer, OPENAI_REPLY, config().with_seed(7)).await;
+
+        assert_eq!(body["min_p"], 0.05);
+        assert_eq!(body["seed"], 7);
+        assert_eq!(body["id_slot"], 1);
+        assert!(body.get("reasoning_effort").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_options() {
+        let (raw, body) = send(
+            LLM::Anthropic,
+            r#"{"id":"msg_1","role":"assistant","content":[{"type":"text","text":"Hi"}],"stop_reason":"end_turn"}"#,
+            config().with_user_id("user-123"),
+        ).await;
+
+        assert!(raw.contains("anthropic-beta: interleaved-thinking-2025-05-14"));
+        assert_eq!(body["metadata"], json!({ "user_id": "user-123", "tenant": "acme" }));
+        assert_eq!(body["system"], json!([{ "type": "text", "text": "Be brief.", "cache_control": { "type": "ephemeral" } }]));
+        assert!(body.get("reasoning_effort").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_conversation_cache() {
+        let config = LlmConfig::new()
+            .with_anthropic_options(AnthropicOptions::new().with_cache_strategy(AnthropicCacheStrategy::Conversation));
+        let (_, body) = send(
+            LLM::Anthropic,
+            r#"{"id":"msg_1","role":"assistant","content":[{"type":"text","text":"Hi"}],"stop_reason":"end_turn"}"#,
+            config,
+        ).await;
+
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["messages"][0]["content"][0]["cache_control"], json!({ "type": "ephemeral" }));
+    }
+
+    #[tokio::test]
+    async fn test_gemini_options() {
+        let (_, body) = send(
+            LLM::Gemini,
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"Hi"}]},"finishReason":"STOP"}]}"#,
+            config(),
+        ).await;
+
+        assert_eq!(body["safetySettings"], json!([{ "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_ONLY_HIGH" }]));
+        assert_eq!(body["generationConfig"], json!({ "responseModalities": ["TEXT"], "mediaResolution": "MEDIA_RESOLUTION_LOW" }));
+        assert!(body.get("seed").is_none());
+    }
+}
@@ -0,0 +1,76 @@
+// src/tests/provider.rs
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use futures::StreamExt;
+    use crate::config::LlmConfig;
+    use crate::errors::LlmError;
+    use crate::llm::{Access, LLM};
+    use crate::provider::{Capabilities, Provider};
+    use crate::structs::general::{LlmChunk, LlmResponse, Message, MessageContent};
+
+    /// Echoes the last message back, prefixed with the model name.
+    struct EchoProvider;
+
+    #[async_trait]
+    impl Provider for EchoProvider {
+        fn name(&self) -> &str {
+            "Echo"
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities { chat: true, ..Default::default() }
+        }
+
+        async fn chat(
+            &self,
+            messages: Vec<Message>,
+            model: Option<&str>,
+            _config: Option<&LlmConfig>,
+        ) -> Result<LlmResponse, LlmError> {
+            let last = match &messages.last().unwrap().content {
+                MessageContent::Text(text) => text.clone(),
+                _ => String::new(),
+            };
+            Ok(LlmResponse {
+                text: format!("{}: {}", model.unwrap_or("echo-1"), last),
+                reasoning: None,
+                tool_calls: vec![],
+                usage: None,
+                finish_reason: None,
+                warnings: vec![],
+                thinking: vec![],
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_provider_chat() {
+        let llm = LLM::custom(Box::new(EchoProvider));
+        assert!(llm.capabilities().chat);
+        assert!(!llm.capabilities().embeddings);
+        assert_eq!(format!("{:?}", llm), "Custom(Provider(\"Echo\"))");
+        assert_eq!(llm, llm.clone());
+        assert_ne!(llm, LLM::custom(Box::new(EchoProvider)));
+
+        let response = llm.send_single_message("hello".into(), Some("echo-2"), None).await.unwrap();
+        assert_eq!(response.text, "echo-2: hello");
+
+        let err = llm.embed("hello", None, None, None).await.unwrap_err();
+        assert!(matches!(err, LlmError::Unsupported { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_custom_provider_default_stream() {
+        let llm = LLM::custom(Box::new(EchoProvider));
+        let messages = vec![Message { role: "user".to_string(), content: "hi".into() }];
+        let chunks: Vec<LlmChunk> = llm.send_streaming_convo_message(messages, None, None).await.unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        assert!(matches!(&chunks[0], LlmChunk::Text(text) if text == "echo-1: hi"));
+        assert!(matches!(chunks.last(), Some(LlmChunk::Done { .. })));
+    }
+}
@@ -0,0 +1,223 @@
+// src/tests/structured.rs
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+    use crate::config::{ConfigOption, LlmConfig};
+    use crate::gemini::gemini_generation_config_from_config;
+    use crate::llm::{Access, LLM};
+    use crate::structs::general::{FinishReason, LlmChunk};
+    use crate::errors::LlmError;
+    use crate::structured::{openai_response_format, parse_partial_json, partial_json_stream, partial_stream};
+    use crate::tests::{ask, client, mock_server, request_body};
+
+    fn person_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": { "name": { "type": "string" }, "age": { "type": "integer" } },
+            "required": ["name", "age"]
+        })
+    }
+
+    fn response_format() -> Value {
+        json!({ "type": "json_schema", "json_schema": { "name": "person", "schema": person_schema(), "strict": true } })
+    }
+
+    #[test]
+    fn test_gemini_response_json_schema() {
+        let config = LlmConfig::new().with_json_schema(response_format());
+        let generation_config = gemini_generation_config_from_config(&config).unwrap();
+        assert_eq!(serde_json::to_value(&generation_config).unwrap(), json!({
+            "responseMimeType": "application/json",
+            "responseJsonSchema": person_schema(),
+        }));
+
+        let json_mode = LlmConfig::new().with_json_schema(json!({ "type": "json_object" }));
+        let generation_config = gemini_generation_config_from_config(&json_mode).unwrap();
+        assert_eq!(serde_json::to_value(&generation_config).unwrap(), json!({ "responseMimeType": "application/json" }));
+    }
+
+    #[test]
+    fn test_bare_schema_wrapped_for_openai() {
+        assert_eq!(openai_response_format(&response_format()), response_format());
+        assert_eq!(openai_response_format(&person_schema()), json!({
+            "type": "json_schema",
+            "json_schema": { "name": "json_response", "schema": person_schema() },
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_forced_output_tool() {
+        let (base_url, server) = mock_server(
+            r#"{"id":"msg_1","role":"assistant","content":[{"type":"tool_use","id":"toolu_1","name":"person","input":{"name":"Ada","age":36}}],"stop_reason":"tool_use"}"#,
+        ).await;
+        let client = client(LLM::Anthropic, base_url);
+        let config = LlmConfig::new().with_json_schema(response_format());
+
+        let res = client.send_convo_message(ask("Who wrote the first program?"), None, Some(&config)).await.unwrap();
+
+        let raw = server.await.unwrap();
+        let body = request_body(&raw);
+        assert_eq!(body["tools"][0]["name"], "person");
+        assert_eq!(body["tools"][0]["input_schema"], person_schema());
+        assert_eq!(body["tool_choice"], json!({ "type": "tool", "name": "person" }));
+        assert_eq!(serde_json::from_str::<Value>(&res.text).unwrap(), json!({ "name": "Ada", "age": 36 }));
+        assert!(res.tool_calls.is_empty());
+        assert_eq!(res.finish_reason, Some(FinishReason::Stop));
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_output_tool_not_forced_with_thinking() {
+        let (base_url, server) = mock_server(
+            r#"{"id":"msg_1","role":"assistant","content":[{"type":"text","text":"Ada, 36"}],"stop_reason":"end_turn"}"#,
+        ).await;
+        let config = LlmConfig::new().with_json_schema(response_format()).with_thinking_budget(2048);
+
+        let res = client(LLM::Anthropic, base_url).send_convo_message(ask("Who wrote the first program?"), None, Some(&config)).await.unwrap();
+
+        let body = request_body(&server.await.unwrap());
+        assert_eq!(body["tools"][0]["name"], "person");
+        assert!(body.get("tool_choice").is_none());
+        assert_eq!(res.warnings, vec![
+            "Anthropic extended thinking cannot force the json_schema tool; the model may answer in plain text".to_string(),
+        ]);
+
+        let err = client(LLM::Anthropic, "http://127.0.0.1:9".to_string())
+            .send_convo_message(ask("Who wrote the first program?"), None, Some(&config.with_strict(true)))
+            .await
+            .unwrap_err();
+        match err {
+            LlmError::UnsupportedOptions { options, .. } => assert_eq!(options, vec![ConfigOption::JsonSchema]),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_streamed_output_tool() {
+        let (base_url, server) = mock_server(concat!(
+            "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"json_response\",\"input\":{}}}\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"name\\\": \\\"Ada\\\"\"}}\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\", \\\"age\\\": 36}\"}}\n",
+            "data: {\"type\":\"content_block_stop\",\"index\":0}\n",
+            "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"}}\n",
+            "data: {\"type\":\"message_stop\"}\n",
+        )).await;
+        let client = client(LLM::Anthropic, base_url);
+        let config = LlmConfig::new().with_json_schema(person_schema());
+
+        let mut stream = client.send_streaming_convo_message(ask("Who?"), None, Some(&config)).await.unwrap();
+        let mut text = String::new();
+        let mut finish_reason = None;
+        while let Some(chunk) = stream.next().await {
+            match chunk.unwrap() {
+                LlmChunk::Text(t) => text.push_str(&t),
+                LlmChunk::Done { finish_reason: reason, .. } => finish_reason = reason,
+                other => panic!("unexpected chunk {:?}", other),
+            }
+        }
+        server.await.unwrap();
+
+        assert_eq!(serde_json::from_str::<Value>(&text).unwrap(), json!({ "name": "Ada", "age": 36 }));
+        assert_eq!(finish_reason, Some(FinishReason::Stop));
+    }
+
+    #[cfg(feature = "structured")]
+    #[tokio::test]
+    async fn test_send_structured_reasks() {
+        use crate::structured::StructuredAccess;
+
+        #[derive(Debug, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+        struct Person {
+            name: String,
+            age: u32,
+        }
+
+        let (base_url, server) = crate::tests::mock_server_sequence(vec![
+            (200, "", r#"{"choices":[{"message":{"content":"```json\n{\"name\": \"Ada\"}\n```"},"finish_reason":"stop"}]}"#),
+            (200, "", r#"{"choices":[{"message":{"content":"{\"name\": \"Ada\", \"age\": 36}"},"finish_reason":"stop"}]}"#),
+        ]).await;
+        let client = client(LLM::OpenAI, base_url);
+
+        let person: Person = client.send_structured(ask("Who wrote the first program?"), None, None).await.unwrap();
+
+        assert_eq!(person, Person { name: "Ada".to_string(), age: 36 });
+        let requests = server.await.unwrap();
+        let first = request_body(&requests[0]);
+        assert_eq!(first["response_format"]["json_schema"]["name"], "Person");
+        assert_eq!(first["response_format"]["json_schema"]["schema"]["required"], json!(["name", "age"]));
+        let second = request_body(&requests[1]);
+        let feedback = second["messages"][2]["content"].as_str().unwrap();
+        assert!(feedback.contains("\"age\" is a required property"), "{}", feedback);
+    }
+
+    #[cfg(feature = "structured")]
+    #[tokio::test]
+    async fn test_send_structured_gives_up() {
+        use crate::structured::StructuredAccess;
+
+        let (base_url, server) = crate::tests::mock_server_sequence(vec![
+            (200, "", r#"{"choices":[{"message":{"content":"not json"},"finish_reason":"stop"}]}"#),
+            (200, "", r#"{"choices":[{"message":{"content":"still not json"},"finish_reason":"stop"}]}"#),
+        ]).await;
+        let client = client(LLM::OpenAI, base_url);
+        let config = LlmConfig::new().with_structured_retries(1);
+
+        let err = client.send_structured::<Vec<String>>(ask("List colors"), None, Some(&config)).await.unwrap_err();
+
+        assert!(matches!(err, crate::errors::LlmError::Parse { ref message } if message.contains("after 2 attempts")), "{:?}", err);
+        assert_eq!(server.await.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_partial_json() {
+        assert_eq!(parse_partial_json(""), None);
+        assert_eq!(parse_partial_json("```json\n{\"na"), Some(json!({})));
+        assert_eq!(parse_partial_json(r#"{"name": "Ad"#), Some(json!({ "name": "Ad" })));
+        assert_eq!(parse_partial_json(r#"{"name": "A\u00"#), Some(json!({ "name": "A" })));
+        assert_eq!(parse_partial_json(r#"{"name": "Ada", "age": 3"#), Some(json!({ "name": "Ada" })));
+        assert_eq!(parse_partial_json(r#"{"name": "Ada", "age": 36, "langs": [{"n": "x"}, tr"#), Some(json!({
+            "name": "Ada", "age": 36, "langs": [{ "n": "x" }],
+        })));
+        assert_eq!(parse_partial_json(r#"{"a": [1, 2]} trailing"#), Some(json!({ "a": [1, 2] })));
+        assert_eq!(parse_partial_json(r#"{"a": nope}"#), None);
+    }
+
+    fn text_stream(pieces: &[&str]) -> futures::stream::BoxStream<'static, Result<LlmChunk, LlmError>> {
+        let mut chunks: Vec<Result<LlmChunk, LlmError>> = pieces.iter().map(|p| Ok(LlmChunk::Text(p.to_string()))).collect();
+        chunks.push(Ok(LlmChunk::Done { usage: None, finish_reason: Some(FinishReason::Stop), model: None, warnings: Vec::new() }));
+        futures::stream::iter(chunks).boxed()
+    }
+
+    #[tokio::test]
+    async fn test_partial_json_stream_snapshots() {
+        let chunks = text_stream(&[r#"{"name": "A"#, "da\", ", r#""age""#, ": 36", "}"]);
+
+        let snapshots: Vec<Value> = partial_json_stream(chunks).map(Result::unwrap).collect().await;
+
+        assert_eq!(snapshots, vec![
+            json!({ "name": "A" }),
+            json!({ "name": "Ada" }),
+            json!({ "name": "Ada", "age": 36 }),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_partial_stream_typed() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Person {
+            name: Option<String>,
+            #[serde(default)]
+            tags: Vec<String>,
+        }
+
+        let chunks = text_stream(&[r#"{"name": "Ada", "#, r#""tags": ["math"#, r#"s", "code"]}"#]);
+        let people: Vec<Person> = partial_stream(chunks).map(Result::unwrap).collect().await;
+        assert_eq!(people.last(), Some(&Person { name: Some("Ada".to_string()), tags: vec!["maths".to_string(), "code".to_string()] }));
+        assert_eq!(people[0], Person { name: Some("Ada".to_string()), tags: vec![] });
+
+        let mut truncated = partial_stream::<Person>(text_stream(&[r#"{"name": "Ada""#]));
+        assert!(truncated.next().await.unwrap().is_ok());
+        assert!(matches!(truncated.next().await, Some(Err(LlmError::Parse { .. }))));
+    }
+}
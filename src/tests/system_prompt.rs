@@ -0,0 +1,47 @@
+// src/tests/system_prompt.rs
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::{Access, LLM};
+    use crate::structs::general::Message;
+    use crate::tests::{client, mock_server, request_body};
+
+    fn conversation() -> Vec<Message> {
+        vec![
+            Message { role: "system".to_string(), content: "Answer in one word.".into() },
+            Message { role: "user".to_string(), content: "ping".into() },
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_top_level_system() {
+        let (base_url, server) = mock_server(
+            r#"{"id":"msg_1","role":"assistant","content":[{"type":"text","text":"pong"}],"stop_reason":"end_turn"}"#,
+        ).await;
+        let client = client(LLM::Anthropic, base_url);
+
+        client.send_convo_message(conversation(), None, None).await.unwrap();
+
+        let body = request_body(&server.await.unwrap());
+        assert_eq!(body["system"], "Answer in one word.");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][0]["role"], "user");
+    }
+
+    #[tokio::test]
+    async fn test_gemini_system_instruction() {
+        let (base_url, server) = mock_server(
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"pong"}]},"finishReason":"STOP"}]}"#,
+        ).await;
+        let client = client(LLM::Gemini, base_url);
+
+        let response = client.send_convo_message(conversation(), Some("gemini-2.5-flash"), None).await.unwrap();
+        assert_eq!(response.text, "pong");
+
+        let body = request_body(&server.await.unwrap());
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Answer in one word.");
+        assert!(body["systemInstruction"].get("role").is_none());
+        assert_eq!(body["contents"].as_array().unwrap().len(), 1);
+        assert_eq!(body["contents"][0]["role"], "user");
+    }
+}
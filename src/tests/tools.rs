@@ -0,0 +1,158 @@
+// src/tests/tools.rs
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::config::LlmConfig;
+    use crate::gemini::gemini_to_llm_response;
+    use crate::gemini::api::call_gemini::{map_messages_to_gemini, gemini_tool_config_from_config, gemini_tools_from_config};
+    use crate::gemini::types::GeminiResponse;
+    use crate::structs::general::{Message, ToolCall, ToolChoice, ToolDefinition};
+    use crate::structs::general::{LlmChunk, ToolCallAssembler};
+    use crate::structs::openai::{OpenAIToolCall, map_tool_choice_to_openai};
+    use crate::openai::{StreamResponse, stream_choice_to_chunks};
+    use crate::llm::{Access, LLM};
+    use crate::tests::{ask, client, mock_server};
+    use futures::StreamExt;
+
+    fn weather_call() -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            name: "get_weather".to_string(),
+            arguments: json!({"city": "Paris"}),
+            signature: Some("sig".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_openai_tool_message_serialization() {
+        let call = serde_json::to_value(Message::assistant_tool_calls(None, vec![weather_call()])).unwrap();
+        assert_eq!(call["role"], "assistant");
+        assert!(call["content"].is_null());
+        assert_eq!(call["tool_calls"][0]["id"], "call_1");
+        assert_eq!(call["tool_calls"][0]["type"], "function");
+        assert_eq!(call["tool_calls"][0]["function"]["arguments"], r#"{"city":"Paris"}"#);
+
+        let result = serde_json::to_value(Message::tool_result("call_1", "get_weather", "18C")).unwrap();
+        assert_eq!(result, json!({"role": "tool", "tool_call_id": "call_1", "content": "18C"}));
+
+        // Plain text messages keep their original shape
+        let text = serde_json::to_value(Message { role: "user".to_string(), content: "Hi".into() }).unwrap();
+        assert_eq!(text, json!({"role": "user", "content": "Hi"}));
+    }
+
+    #[test]
+    fn test_openai_tool_call_parsing() {
+        let raw: OpenAIToolCall = serde_json::from_value(json!({
+            "id": "call_9",
+            "type": "function",
+            "function": {"name": "get_weather", "arguments": "{\"city\":\"Oslo\"}"}
+        })).unwrap();
+        let call = ToolCall::from(raw);
+        assert_eq!(call.id, "call_9");
+        assert_eq!(call.arguments, json!({"city": "Oslo"}));
+
+        assert_eq!(map_tool_choice_to_openai(&ToolChoice::Required), json!("required"));
+        assert_eq!(
+            map_tool_choice_to_openai(&ToolChoice::Tool("get_weather".to_string())),
+            json!({"type": "function", "function": {"name": "get_weather"}})
+        );
+    }
+
+    #[test]
+    fn test_gemini_tool_mapping() {
+        let messages = vec![
+            Message::assistant_tool_calls(None, vec![weather_call()]),
+            Message::tool_result("call_1", "get_weather", "18C"),
+        ];
+        let contents = serde_json::to_value(map_messages_to_gemini(messages).unwrap()).unwrap();
+        assert_eq!(contents[0]["parts"][0]["functionCall"]["name"], "get_weather");
+        assert_eq!(contents[0]["parts"][0]["thoughtSignature"], "sig");
+        assert_eq!(contents[1]["role"], "user");
+        assert_eq!(contents[1]["parts"][0]["functionResponse"]["response"], json!({"result": "18C"}));
+
+        let config = LlmConfig::new()
+            .with_tools(vec![ToolDefinition::new("get_weather", "Current weather", json!({"type": "object"}))])
+            .with_tool_choice(ToolChoice::Tool("get_weather".to_string()));
+        let tools = serde_json::to_value(gemini_tools_from_config(&config)).unwrap();
+        assert_eq!(tools[0]["functionDeclarations"][0]["name"], "get_weather");
+        let tool_config = serde_json::to_value(gemini_tool_config_from_config(&config)).unwrap();
+        assert_eq!(tool_config["functionCallingConfig"]["mode"], "ANY");
+        assert_eq!(tool_config["functionCallingConfig"]["allowedFunctionNames"][0], "get_weather");
+    }
+
+    #[test]
+    fn test_gemini_function_call_response() {
+        let response: GeminiResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{
+                    "functionCall": {"name": "get_weather", "args": {"city": "Rome"}},
+                    "thoughtSignature": "abc"
+                }]},
+                "finishReason": "STOP"
+            }]
+        })).unwrap();
+        let response = gemini_to_llm_response(response).unwrap();
+        assert_eq!(response.tool_calls.len(), 1);
+        assert_eq!(response.tool_calls[0].id, "get_weather_0");
+        assert_eq!(response.tool_calls[0].arguments, json!({"city": "Rome"}));
+        assert_eq!(response.tool_calls[0].signature.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_openai_streamed_tool_call_assembly() {
+        let events = [
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"get_weather","arguments":""}}]},"finish_reason":null}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"city\":"}}]},"finish_reason":null}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"Paris\"}"}}]},"finish_reason":null}]}"#,
+            r#"{"choices":[{"delta":{},"finish_reason":"tool_calls"}]}"#,
+        ];
+
+        let mut assembler = ToolCallAssembler::default();
+        let chunks: Vec<LlmChunk> = events.iter().flat_map(|event| {
+            let parsed: StreamResponse = serde_json::from_str(event).unwrap();
+            stream_choice_to_chunks(&parsed.choices[0], &mut assembler)
+        }).collect();
+
+        assert_eq!(chunks.len(), 4);
+        assert!(matches!(&chunks[0], LlmChunk::ToolCallStart { index: 0, id, name } if id == "call_1" && name == "get_weather"));
+        assert!(matches!(&chunks[1], LlmChunk::ToolCallArgumentsDelta { index: 0, delta } if delta == r#"{"city":"#));
+        match &chunks[3] {
+            LlmChunk::ToolCallEnd { index: 0, call } => assert_eq!(call.arguments, json!({"city": "Paris"})),
+            other => panic!("Expected ToolCallEnd, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_without_sentinel_still_finishes() {
+        let openai = concat!(
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"get_weather\",\"arguments\":\"\"}}]},\"finish_reason\":null}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"city\\\":\\\"Paris\\\"}\"}}]},\"finish_reason\":null}]}\n\n",
+        );
+        let anthropic = concat!(
+            "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"get_weather\",\"input\":{}}}\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"city\\\":\\\"Paris\\\"}\"}}\n",
+        );
+
+        for (provider, body) in [(LLM::OpenAI, openai), (LLM::LlamaServer, openai), (LLM::Anthropic, anthropic)] {
+            let (base_url, server) = mock_server(body).await;
+            let client = client(provider.clone(), base_url);
+            let chunks: Vec<LlmChunk> = client
+                .send_streaming_convo_message(ask("Weather?"), None, None)
+                .await
+                .unwrap()
+                .map(|chunk| chunk.unwrap())
+                .collect()
+                .await;
+            server.await.unwrap();
+
+            let call = chunks.iter().find_map(|chunk| match chunk {
+                LlmChunk::ToolCallEnd { call, .. } => Some(call),
+                _ => None,
+            });
+            assert_eq!(call.map(|call| &call.arguments), Some(&json!({ "city": "Paris" })), "{}", provider.name());
+            assert!(matches!(chunks.last(), Some(LlmChunk::Done { .. })), "{}", provider.name());
+        }
+    }
+}
+
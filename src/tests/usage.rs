@@ -0,0 +1,90 @@
+// src/tests/usage.rs
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::gemini::gemini_to_llm_response;
+    use crate::gemini::types::GeminiResponse;
+    use crate::models::openai::APIResponse;
+    use crate::openai::{StreamResponse, StreamSummary};
+    use crate::structs::general::{FinishReason, LlmChunk, Usage};
+
+    #[test]
+    fn test_openai_usage_parsing() {
+        let response: APIResponse = serde_json::from_value(json!({
+            "choices": [{"message": {"content": "Hi"}, "finish_reason": "length"}],
+            "usage": {
+                "prompt_tokens": 20,
+                "completion_tokens": 15,
+                "total_tokens": 35,
+                "prompt_tokens_details": {"cached_tokens": 8},
+                "completion_tokens_details": {"reasoning_tokens": 5}
+            }
+        })).unwrap();
+
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("length"));
+        let usage = Usage::from(response.usage.unwrap());
+        assert_eq!(usage, Usage { prompt_tokens: 20, completion_tokens: 15, reasoning_tokens: Some(5), cached_input_tokens: Some(8) });
+        assert_eq!(usage.total_tokens(), 35);
+    }
+
+    #[test]
+    fn test_gemini_usage_and_finish_reason() {
+        let response: GeminiResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": "Once upon a"}]},
+                "finishReason": "MAX_TOKENS"
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 12,
+                "candidatesTokenCount": 4,
+                "thoughtsTokenCount": 30,
+                "totalTokenCount": 46
+            }
+        })).unwrap();
+
+        let response = gemini_to_llm_response(response).unwrap();
+        assert_eq!(response.finish_reason, Some(FinishReason::Length));
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.completion_tokens, 34);
+        assert_eq!(usage.reasoning_tokens, Some(30));
+        assert_eq!(usage.total_tokens(), 46);
+    }
+
+    #[test]
+    fn test_gemini_function_call_finish_reason() {
+        let response: GeminiResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"functionCall": {"name": "get_weather", "args": {}}}]},
+                "finishReason": "STOP"
+            }]
+        })).unwrap();
+
+        let response = gemini_to_llm_response(response).unwrap();
+        assert_eq!(response.finish_reason, Some(FinishReason::ToolCalls));
+        assert!(response.usage.is_none());
+    }
+
+    #[test]
+    fn test_openai_stream_summary() {
+        let events = [
+            r#"{"model":"gpt-4o-2024-08-06","choices":[{"delta":{"content":"Hi"},"finish_reason":null}],"usage":null}"#,
+            r#"{"model":"gpt-4o-2024-08-06","choices":[{"delta":{},"finish_reason":"stop"}],"usage":null}"#,
+            r#"{"model":"gpt-4o-2024-08-06","choices":[],"usage":{"prompt_tokens":9,"completion_tokens":2,"total_tokens":11}}"#,
+        ];
+
+        let mut summary = StreamSummary::default();
+        for event in events {
+            summary.observe(serde_json::from_str::<StreamResponse>(event).unwrap());
+        }
+
+        match summary.into_chunk() {
+            LlmChunk::Done { usage, finish_reason, model, .. } => {
+                assert_eq!(usage.map(|u| u.total_tokens()), Some(11));
+                assert_eq!(finish_reason, Some(FinishReason::Stop));
+                assert_eq!(model.as_deref(), Some("gpt-4o-2024-08-06"));
+            }
+            other => panic!("Expected Done, got {:?}", other),
+        }
+    }
+}